@@ -0,0 +1,117 @@
+use crate::postgres_provider::postgres_provider::PostgresTls;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::time::Instant;
+use tokio_postgres::CancelToken;
+
+#[derive(Clone)]
+pub struct RunningBuild {
+    pub label: String,
+    pub started: Instant,
+    cancel_token: CancelToken,
+}
+
+/// Index builds currently executing on the server, keyed by backend PID,
+/// plus overall counters of the import run.
+pub struct BuildRegistry {
+    running_builds: Mutex<HashMap<i32, RunningBuild>>,
+    /// `"schema"."name"` of every `CREATE INDEX CONCURRENTLY` this run started
+    concurrent_indexes: Mutex<BTreeSet<String>>,
+    total_tasks: AtomicUsize,
+    finished_tasks: AtomicUsize,
+    started: Instant,
+    /// cancel requests go over TLS like the connections themselves
+    tls: PostgresTls,
+}
+
+impl BuildRegistry {
+    pub fn new(total_tasks: usize, tls: PostgresTls) -> Self {
+        BuildRegistry {
+            running_builds: Mutex::new(HashMap::new()),
+            concurrent_indexes: Mutex::new(BTreeSet::new()),
+            total_tasks: AtomicUsize::new(total_tasks),
+            finished_tasks: AtomicUsize::new(0),
+            started: Instant::now(),
+            tls,
+        }
+    }
+
+    pub fn register(&self, backend_pid: i32, label: &str, cancel_token: CancelToken) {
+        self.running_builds.lock().unwrap().insert(
+            backend_pid,
+            RunningBuild {
+                label: label.to_string(),
+                started: Instant::now(),
+                cancel_token,
+            },
+        );
+    }
+
+    pub fn unregister(&self, backend_pid: i32) {
+        self.running_builds.lock().unwrap().remove(&backend_pid);
+    }
+
+    /// Same as `pg_cancel_backend`, sent over a separate connection.
+    pub async fn cancel(&self, backend_pid: i32) -> Result<(), tokio_postgres::Error> {
+        let cancel_token = self
+            .running_builds
+            .lock()
+            .unwrap()
+            .get(&backend_pid)
+            .map(|b| b.cancel_token.clone());
+        match cancel_token {
+            Some(cancel_token) => cancel_token.cancel_query(self.tls.clone()).await,
+            None => Ok(()), // finished in the meantime
+        }
+    }
+
+    pub fn get_running_builds(&self) -> Vec<(i32, RunningBuild)> {
+        let mut running_builds: Vec<(i32, RunningBuild)> = self
+            .running_builds
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(pid, build)| (*pid, build.clone()))
+            .collect();
+        running_builds.sort_by_key(|(_, build)| build.started);
+        running_builds
+    }
+
+    pub fn add_concurrent_index(&self, quoted_index_name: &str) {
+        self.concurrent_indexes
+            .lock()
+            .unwrap()
+            .insert(quoted_index_name.to_string());
+    }
+
+    pub fn get_concurrent_indexes(&self) -> Vec<String> {
+        self.concurrent_indexes
+            .lock()
+            .unwrap()
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    pub fn add_task(&self) {
+        self.total_tasks.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Returns the position of the task among the finished ones, starting at 1.
+    pub fn finish_task(&self) -> usize {
+        self.finished_tasks.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    pub fn get_total_tasks(&self) -> usize {
+        self.total_tasks.load(Ordering::SeqCst)
+    }
+
+    pub fn get_finished_tasks(&self) -> usize {
+        self.finished_tasks.load(Ordering::SeqCst)
+    }
+
+    pub fn get_started(&self) -> Instant {
+        self.started
+    }
+}
@@ -1,5 +1,11 @@
-
-use clap::{Args, Parser, value_parser};
+use crate::importer::importer::TransactionMode;
+use crate::logger::logger::{LogFormat, LogLevel};
+use crate::run_report::run_report::ReportFormat;
+use crate::scheduler::scheduler::ScheduleStrategy;
+use crate::shared::duration_utils::parse_duration;
+use clap::builder::BoolishValueParser;
+use clap::{ArgAction, Args, FromArgMatches, Parser, Subcommand, value_parser};
+use std::time::Duration;
 
 #[derive(Parser, Debug)]
 #[command(
@@ -8,20 +14,220 @@ use clap::{Args, Parser, value_parser};
     about = "Utility for extracting indexes from SQL Server and importing them to Postgres"
 )]
 pub struct Cli {
-    #[command(flatten)]
-    pub exclusive_options: ExclusiveOptions,
+    #[command(subcommand)]
+    pub command: Command,
 
     #[arg(
         long,
+        env = "PG_INDEX_IMPORT_CONFIG_FILE",
         short = 'f',
+        global = true,
         default_value = "pg_index_import.toml",
         help = "Configuration file name"
     )]
     pub config_file: String,
 
-    // region Export
     #[arg(
         long,
+        env = "PG_INDEX_IMPORT_PROFILE",
+        global = true,
+        help = "Profile of the configuration file to apply on top of its [settings]"
+    )]
+    pub profile: Option<String>,
+
+    #[command(flatten)]
+    pub log: LogArgs,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Export indexes from SQL Server to files
+    Export(ExportArgs),
+    /// Import indexes from files to Postgres
+    Import(ImportArgs),
+    /// Export indexes from SQL Server and import them to Postgres in one run
+    Migrate(MigrateArgs),
+    /// Compare the indexes in SQL Server with the ones in Postgres
+    Verify(VerifyArgs),
+    /// Write a script that brings the Postgres indexes in line with SQL Server
+    Diff(DiffArgs),
+    /// Run a rollback script written by an earlier import
+    Rollback(RollbackArgs),
+    /// Work with the configuration file
+    Config(ConfigArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    pub command: ConfigCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Validate the configuration file and its profiles without connecting anywhere
+    Check,
+}
+
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    #[command(flatten)]
+    pub source: SourceArgs,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+
+    #[command(flatten)]
+    pub report: ReportArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct ImportArgs {
+    #[arg(
+        long,
+        env = "PG_INDEX_IMPORT_INPUT_DIR",
+        short = 'I',
+        help = "Current directory sub directory for input files",
+        default_value = "INPUT"
+    )]
+    pub input_dir: String,
+
+    #[command(flatten)]
+    pub build: BuildArgs,
+
+    #[command(flatten)]
+    pub report: ReportArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct MigrateArgs {
+    #[command(flatten)]
+    pub source: SourceArgs,
+
+    #[arg(
+        long,
+        env = "PG_INDEX_IMPORT_OUTPUT_DIR",
+        short,
+        help = "Also write the exported statements to this current directory sub directory, as an audit trail"
+    )]
+    pub output_dir: Option<String>,
+
+    #[command(flatten)]
+    pub build: BuildArgs,
+
+    #[command(flatten)]
+    pub report: ReportArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct VerifyArgs {
+    #[command(flatten)]
+    pub source: SourceArgs,
+
+    #[command(flatten)]
+    pub report: ReportArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct DiffArgs {
+    #[command(flatten)]
+    pub source: SourceArgs,
+
+    #[arg(
+        long,
+        env = "PG_INDEX_IMPORT_DROP_EXTRA",
+        num_args = 0..=1,
+        require_equals = true,
+        default_value_t = false,
+        default_missing_value = "true",
+        action = ArgAction::Set,
+        value_parser = BoolishValueParser::new(),
+        help = "Also drop Postgres indexes that no longer exist in SQL Server, =false turns off the config file value"
+    )]
+    pub drop_extra: bool,
+
+    #[arg(
+        long,
+        env = "PG_INDEX_IMPORT_OUTPUT_DIR",
+        short,
+        help = "Current directory sub directory for the sync script, the current directory without it"
+    )]
+    pub output_dir: Option<String>,
+
+    #[command(flatten)]
+    pub report: ReportArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct RollbackArgs {
+    #[arg(
+        value_name = "FILE",
+        help = "Rollback script written by an earlier import"
+    )]
+    pub rollback_file: String,
+
+    #[command(flatten)]
+    pub execution: ExecutionArgs,
+
+    #[command(flatten)]
+    pub report: ReportArgs,
+}
+
+// region Argument groups
+/// Logging options, taken by every command.
+#[derive(Args, Debug)]
+pub struct LogArgs {
+    #[arg(
+        long,
+        env = "PG_INDEX_IMPORT_LOG_LEVEL",
+        global = true,
+        value_enum,
+        default_value_t = LogLevel::Info,
+        help = "Most detailed level of events that are logged"
+    )]
+    pub log_level: LogLevel,
+
+    #[arg(
+        long,
+        env = "PG_INDEX_IMPORT_LOG_FILE",
+        global = true,
+        help = "Also append every event to this file, with a timestamp, worker, table, index and elapsed time"
+    )]
+    pub log_file: Option<String>,
+
+    #[arg(
+        long,
+        env = "PG_INDEX_IMPORT_LOG_FORMAT",
+        global = true,
+        value_enum,
+        default_value_t = LogFormat::Text,
+        help = "Format of the events on the console and in the log file"
+    )]
+    pub log_format: LogFormat,
+
+    #[arg(
+        long,
+        short = 'q',
+        env = "PG_INDEX_IMPORT_QUIET",
+        global = true,
+        help = "Only print warnings and errors to the console, the log file still gets every event"
+    )]
+    pub quiet: bool,
+
+    #[arg(
+        long,
+        env = "PG_INDEX_IMPORT_NO_COLOR",
+        global = true,
+        help = "Print the console output without colors"
+    )]
+    pub no_color: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct SourceArgs {
+    #[arg(
+        long,
+        env = "PG_INDEX_IMPORT_SOURCE_SCHEMA",
         short = 's',
         help = "Source schema name. Use '*' to import all schemas",
         default_value = "*"
@@ -30,32 +236,32 @@ pub struct Cli {
 
     #[arg(
         long,
+        env = "PG_INDEX_IMPORT_SOURCE_TABLE",
         short = 't',
         help = "Source table name. Use '*' to import all tables in schema",
         default_value = "*"
     )]
     pub source_table: String,
+}
 
+#[derive(Args, Debug)]
+pub struct OutputArgs {
     #[arg(
         long,
+        env = "PG_INDEX_IMPORT_OUTPUT_DIR",
         short,
         help = "Current directory sub directory for output files",
         default_value = "OUTPUT"
     )]
-    pub output_dir: Option<String>,
-    // endregion
-
-    // region Import
-    #[arg(
-        long,
-        short = 'I',
-        help = "Current directory sub directory for input files",
-        default_value = "INPUT"
-    )]
-    pub input_dir: Option<String>,
+    pub output_dir: String,
+}
 
+/// How statements run on the Postgres workers.
+#[derive(Args, Debug)]
+pub struct ExecutionArgs {
     #[arg(
         long,
+        env = "PG_INDEX_IMPORT_THREADS",
         short = 'r',
         default_value = "2",
         value_parser = value_parser!(u32).range(1..=10),
@@ -65,26 +271,127 @@ pub struct Cli {
 
     #[arg(
         long,
+        env = "PG_INDEX_IMPORT_BUILD_TIMEOUT",
         short = 'T',
-        default_value = "24",
-        value_parser = value_parser!(u64).range(1..=72),
-        help = "Command timeout in hours from 1 to 72"
+        default_value = "24h",
+        value_parser = parse_duration,
+        help = "Maximum build time of a single index, e.g. 90m, 1h30m, 45s. 0 means no limit"
     )]
-    pub timeout_in_hours: u64,
-    // endregion
+    pub build_timeout: Duration,
+
+    #[arg(
+        long,
+        env = "PG_INDEX_IMPORT_LOCK_TIMEOUT",
+        short = 'L',
+        default_value = "0",
+        value_parser = parse_duration,
+        help = "Maximum time an index build waits for a table lock, e.g. 5m, 30s. 0 means no limit"
+    )]
+    pub lock_timeout: Duration,
+
+    #[arg(
+        long,
+        env = "PG_INDEX_IMPORT_MAX_RETRIES",
+        default_value = "3",
+        value_parser = value_parser!(u32).range(0..=10),
+        help = "Number of retries for transient PostgreSQL errors from 0 to 10"
+    )]
+    pub max_retries: u32,
+
+    #[arg(
+        long,
+        env = "PG_INDEX_IMPORT_RETRY_DELAY_IN_MS",
+        default_value = "1000",
+        value_parser = value_parser!(u64).range(1..=60000),
+        help = "Initial retry delay in milliseconds, doubled on every retry"
+    )]
+    pub retry_delay_in_ms: u64,
+
+    #[arg(
+        long,
+        env = "PG_INDEX_IMPORT_PROGRESS_INTERVAL",
+        default_value = "30s",
+        value_parser = parse_duration,
+        help = "How often index build progress is printed, e.g. 30s, 5m. 0 disables progress"
+    )]
+    pub progress_interval: Duration,
 }
 
+/// What gets built and in which order.
 #[derive(Args, Debug)]
-#[group(multiple = false)]
-pub struct ExclusiveOptions {
-    #[arg(long, short, help = "Export indexes from SQL Server to files")]
-    pub export: Option<bool>,
+pub struct BuildArgs {
+    #[command(flatten)]
+    pub execution: ExecutionArgs,
 
     #[arg(
         long,
-        short,
-        help = "Import indexes from files to Postgres",
-        default_value = "true"
+        env = "PG_INDEX_IMPORT_SCHEDULE",
+        value_enum,
+        default_value_t = ScheduleStrategy::LargestFirst,
+        help = "Order in which tables are processed"
     )]
-    pub import: Option<bool>,
+    pub schedule: ScheduleStrategy,
+
+    #[arg(
+        long,
+        env = "PG_INDEX_IMPORT_CONCURRENTLY",
+        num_args = 0..=1,
+        require_equals = true,
+        default_value_t = false,
+        default_missing_value = "true",
+        action = ArgAction::Set,
+        value_parser = BoolishValueParser::new(),
+        help = "Build plain CREATE INDEX statements with CREATE INDEX CONCURRENTLY, =false turns off the config file value"
+    )]
+    pub concurrently: bool,
+
+    #[arg(
+        long,
+        env = "PG_INDEX_IMPORT_TRANSACTIONAL",
+        value_enum,
+        value_name = "MODE",
+        help = "Run all statements of a table in one transaction, rolled back as a whole on any failure"
+    )]
+    pub transactional: Option<TransactionMode>,
+
+    #[arg(
+        long,
+        env = "PG_INDEX_IMPORT_ANALYZE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_value_t = false,
+        default_missing_value = "true",
+        action = ArgAction::Set,
+        value_parser = BoolishValueParser::new(),
+        help = "Run ANALYZE on every table once all its statements are done, =false turns off the config file value"
+    )]
+    pub analyze: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct ReportArgs {
+    #[arg(
+        long,
+        env = "PG_INDEX_IMPORT_REPORT",
+        help = "Write a report of the run to this file, JSON or JUnit XML for .xml files"
+    )]
+    pub report: Option<String>,
+
+    #[arg(
+        long,
+        env = "PG_INDEX_IMPORT_REPORT_FORMAT",
+        value_enum,
+        help = "Report format, overrides the one derived from the report file extension"
+    )]
+    pub report_format: Option<ReportFormat>,
+}
+// endregion
+
+/// An argument group with all its defaults, for the commands that do not take it. The
+/// `PG_INDEX_IMPORT_*` variables are left out, they only apply to the commands taking them.
+pub fn default_args<T: Args + FromArgMatches>() -> T {
+    let matches = T::augment_args(clap::Command::new("defaults"))
+        .mut_args(|arg| arg.env(None::<&str>))
+        .get_matches_from(["defaults"]);
+    T::from_arg_matches(&matches).expect("argument defaults are valid")
 }
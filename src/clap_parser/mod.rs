@@ -1 +1 @@
-pub mod clap_parser;
\ No newline at end of file
+pub mod clap_parser;
@@ -0,0 +1,47 @@
+use crate::clap_parser::clap_parser::Cli;
+use crate::config_provider::ConfigProvider;
+use crate::exit_codes::{EXIT_SUCCESS, EXIT_VALIDATION_FAILURE};
+use crate::settings::settings::Settings;
+use crate::{log_error, log_info};
+use clap::ArgMatches;
+use colored::Colorize;
+
+/// Reads the config file with every profile, or only the one given, and resolves the
+/// settings the way a run would, without connecting anywhere.
+pub async fn check_config(cli: &Cli, matches: &ArgMatches) -> i32 {
+    log_info!("Checking Config File: <{}> ", cli.config_file.yellow());
+    let config_provider = ConfigProvider::new(&cli.config_file);
+    let profiles = match &cli.profile {
+        Some(profile) => vec![Some(profile.clone())],
+        None => match config_provider.read_config(None).await {
+            Ok(config) => std::iter::once(None)
+                .chain(config.get_profile_names().into_iter().map(Some))
+                .collect(),
+            Err(e) => {
+                log_error!("{}", e.to_string().red());
+                return EXIT_VALIDATION_FAILURE;
+            }
+        },
+    };
+    let mut exit_code = EXIT_SUCCESS;
+    for profile in profiles {
+        let name = profile.as_deref().unwrap_or("<none>");
+        let result = match config_provider.read_config(profile.as_deref()).await {
+            Ok(config) => Settings::from_args(cli, matches, &config)
+                .map(|_| ())
+                .map_err(|e| config.describe_error(&e)),
+            Err(e) => Err(e.to_string()),
+        };
+        match result {
+            Ok(()) => log_info!("Profile {}: {}", name.yellow(), "OK".green()),
+            Err(e) => {
+                log_error!("Profile {}: {}", name.yellow(), e.red());
+                exit_code = EXIT_VALIDATION_FAILURE;
+            }
+        }
+    }
+    if exit_code == EXIT_SUCCESS {
+        log_info!("{}", "DONE Checking Config File".green());
+    }
+    exit_code
+}
@@ -0,0 +1,207 @@
+use crate::commands::run_utils::{get_file_timestamp, write_run_report};
+use crate::commands::verify::{TableComparison, compare_tables};
+use crate::config_provider::Config;
+use crate::ddl_parser::ddl_parser::{parse_statement, quote_identifier};
+use crate::exit_codes::EXIT_VALIDATION_FAILURE;
+use crate::helpers::print_separator;
+use crate::import_summary::import_summary::{ImportSummary, IndexImportResult};
+use crate::importer::importer::IndexTask;
+use crate::index_verifier::index_verifier::VerifyStatus;
+use crate::settings::settings::Settings;
+use crate::shared::file_utils::{ensure_directory_exists, write_index_to_file};
+use crate::translator::translator::{Translation, translate_statement};
+use crate::{log_error, log_info};
+use colored::Colorize;
+use std::env;
+use std::path::Path;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use tokio::time::Instant;
+
+const SYNC_FILE_PREFIX: &str = "pg_index_import_sync";
+
+/// Compares like `verify` and writes the statements that remove the differences:
+/// missing indexes are created, changed ones dropped and created again and, with
+/// `--drop-extra`, target-only ones dropped. Create statements go through the translator,
+/// the same way `import` runs exported files.
+pub async fn diff_indexes(settings: Arc<Settings>, config: &Config) -> i32 {
+    let started_at = OffsetDateTime::now_utc();
+    let started = Instant::now();
+    let mut diff_summary = ImportSummary::new();
+    let mut sync_dir = env::current_dir().unwrap();
+    if let Some(output_dir) = settings.get_output_dir_as_ref() {
+        sync_dir.push(output_dir);
+        if let Err(e) = ensure_directory_exists(&sync_dir).await {
+            log_error!("{}", format!("{:#}", e).red());
+            return EXIT_VALIDATION_FAILURE;
+        }
+    }
+    let mut drop_statements = Vec::new();
+    let mut create_statements = Vec::new();
+    let mut kept_indexes = Vec::new();
+    let mut unsupported_indexes = 0;
+    let on_table = |table: TableComparison, summary: &mut ImportSummary| {
+        for comparison in table.comparisons {
+            let status = comparison.get_status();
+            if status == VerifyStatus::Matched {
+                continue;
+            }
+            let mut statements = Vec::new();
+            let mut unsupported = None;
+            let drop_statement = comparison.target.as_ref().map(|t| {
+                t.get_drop_statement(&table.quoted_target_schema, &table.quoted_target_table)
+            });
+            match status {
+                VerifyStatus::Extra if !settings.get_drop_extra() => {
+                    kept_indexes.push(format!(
+                        "{}.{}.{}",
+                        table.quoted_target_schema,
+                        table.quoted_target_table,
+                        quote_identifier(comparison.get_name())
+                    ));
+                }
+                VerifyStatus::Extra => statements.extend(drop_statement.clone()),
+                _ => {
+                    // the export names its statements by the bracketed index name
+                    let source_statement = table
+                        .index_statements
+                        .iter()
+                        .find(|(name, _)| {
+                            name.trim_start_matches('[').trim_end_matches(']')
+                                == comparison.get_name()
+                        })
+                        .map(|(_, statement)| statement.clone())
+                        .unwrap_or_default();
+                    let create_statement = match translate_statement(&source_statement) {
+                        Translation::Translated(translated) => Some(translated),
+                        Translation::Unchanged => Some(source_statement).filter(|s| !s.is_empty()),
+                        Translation::Unsupported(reason) => {
+                            unsupported = Some(reason);
+                            None
+                        }
+                    };
+                    if let Some(create_statement) = create_statement {
+                        if status == VerifyStatus::Mismatched {
+                            statements.extend(drop_statement.clone());
+                        }
+                        statements.push(create_statement);
+                    }
+                }
+            }
+            log_info!(
+                "{} <{}>",
+                format!("{:<10}", status.to_string()).yellow(),
+                comparison.get_name().yellow()
+            );
+            for mismatch in &comparison.mismatches {
+                log_info!("           {}", mismatch);
+            }
+            for statement in &statements {
+                log_info!("           {}", statement);
+            }
+            if let Some(reason) = &unsupported {
+                log_info!("           {}: {}", "Unsupported".yellow(), reason);
+                unsupported_indexes += 1;
+            }
+            let mut result = IndexImportResult::new(&IndexTask::new(
+                comparison.get_name(),
+                Some(table.schema_table.clone()),
+                statements.join("\n"),
+            ));
+            result.target_name = comparison.target.as_ref().map(|d| d.name.clone());
+            result.unsupported = unsupported.clone();
+            if unsupported.is_none() {
+                result.add_attempt(std::time::Duration::ZERO, None);
+            }
+            summary.add_results(vec![result]);
+            for statement in statements {
+                if drop_statement.as_ref() == Some(&statement) {
+                    drop_statements.push(statement);
+                } else {
+                    create_statements.push(statement);
+                }
+            }
+        }
+    };
+    let failed_tables =
+        match compare_tables(&settings, config, &mut diff_summary, true, on_table).await {
+            Ok(failed_tables) => failed_tables,
+            Err(exit_code) => return exit_code,
+        };
+    print_separator();
+    // keys before the indexes that may depend on them, like import schedules them
+    create_statements.sort_by_key(|statement| parse_statement(statement).kind.get_priority());
+    log_info!(
+        "Drop statements: <{}>",
+        drop_statements.len().to_string().green()
+    );
+    log_info!(
+        "Create statements: <{}>",
+        create_statements.len().to_string().green()
+    );
+    log_info!(
+        "Unsupported indexes: <{}>",
+        unsupported_indexes.to_string().yellow()
+    );
+    log_info!(
+        "Extra indexes kept: <{}>",
+        kept_indexes.len().to_string().yellow()
+    );
+    log_info!("Failed tables: <{}>", failed_tables.to_string().red());
+    write_sync_script(
+        &sync_dir,
+        &drop_statements,
+        &create_statements,
+        &kept_indexes,
+        started_at,
+    )
+    .await;
+    diff_summary.set_elapsed(started.elapsed());
+    write_run_report("diff", started_at, &settings, config, &diff_summary).await;
+    diff_summary.get_exit_code()
+}
+
+/// Drops come first, so a changed index is gone before it is created again.
+async fn write_sync_script(
+    sync_dir: &Path,
+    drop_statements: &[String],
+    create_statements: &[String],
+    kept_indexes: &[String],
+    started_at: OffsetDateTime,
+) {
+    if drop_statements.is_empty() && create_statements.is_empty() {
+        log_info!(
+            "{}",
+            "Nothing to synchronize, no sync script written".green()
+        );
+        return;
+    }
+    let sync_file = sync_dir.join(format!(
+        "{}_{}.sql",
+        SYNC_FILE_PREFIX,
+        get_file_timestamp(started_at)
+    ));
+    let mut sync_script = format!(
+        "-- Brings the Postgres indexes in line with SQL Server as of {}\n",
+        started_at.format(&Rfc3339).unwrap_or_default()
+    );
+    for kept_index in kept_indexes {
+        sync_script.push_str(&format!(
+            "-- {} is not in SQL Server, kept without --drop-extra\n",
+            kept_index
+        ));
+    }
+    for statement in drop_statements.iter().chain(create_statements) {
+        sync_script.push_str(statement);
+        sync_script.push('\n');
+    }
+    match write_index_to_file(&sync_file, &sync_script).await {
+        Ok(_) => log_info!(
+            "Sync script: <{}> ({} statements)",
+            sync_file.to_str().unwrap().yellow(),
+            drop_statements.len() + create_statements.len()
+        ),
+        Err(e) => log_error!("{}", e.to_string().red()),
+    }
+}
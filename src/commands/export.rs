@@ -0,0 +1,187 @@
+use crate::commands::run_utils::write_run_report;
+use crate::commands::source_reader::{
+    create_sql_server_provider, get_source_schemas, get_source_tables, get_table_statements,
+};
+use crate::config_provider::Config;
+use crate::exit_codes::EXIT_VALIDATION_FAILURE;
+use crate::helpers::print_separator;
+use crate::import_summary::import_summary::{ImportStatus, ImportSummary, IndexImportResult};
+use crate::importer::importer::IndexTask;
+use crate::settings::settings::Settings;
+use crate::shared::file_utils::{ensure_directory_exists_and_empty, write_index_to_file};
+use crate::sql_server_provider::sql_server_provider::TableObjectType;
+use crate::translator::translator::{Translation, translate_statement};
+use crate::{log_error, log_info, log_warn};
+use colored::Colorize;
+use std::collections::HashMap;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tokio::time::Instant;
+
+pub async fn export_indexes(settings: Arc<Settings>, config: &Config) -> i32 {
+    let started_at = OffsetDateTime::now_utc();
+    let started = Instant::now();
+    let mut export_summary = ImportSummary::new();
+    let source_db_provider = match create_sql_server_provider(config) {
+        Ok(source_db_provider) => source_db_provider,
+        Err(exit_code) => return exit_code,
+    };
+    let schema_vec = match get_source_schemas(&settings, &source_db_provider).await {
+        Ok(schemas) => schemas,
+        Err(exit_code) => return exit_code,
+    };
+    let mut current_dir: PathBuf = env::current_dir().unwrap();
+    current_dir.push(settings.get_output_dir_as_ref().clone().unwrap());
+    log_info!(
+        "Output directory: <{}>",
+        current_dir.to_str().unwrap().yellow()
+    );
+    let mut exported_indexes = 0;
+    // exported and unsupported statements per object type
+    let mut object_counts: HashMap<TableObjectType, (usize, usize)> = HashMap::new();
+    let mut failed_tables = 0;
+    let mut failed_indexes = 0;
+    for schema in schema_vec {
+        print_separator();
+        log_info!("Source Schema: <{}>", schema.yellow());
+        let Some(table_vec) =
+            get_source_tables(&settings, &source_db_provider, &schema, &mut export_summary).await
+        else {
+            failed_tables += 1;
+            continue;
+        };
+        let mut current_dir = current_dir.clone();
+        current_dir.push(&schema);
+        if let Err(e) = ensure_directory_exists_and_empty(&current_dir).await {
+            log_error!("{}", format!("{:#}", e).red());
+            return EXIT_VALIDATION_FAILURE;
+        }
+        for table in table_vec {
+            print_separator();
+            log_info!("Source Table: <{}>", table.yellow());
+            print_separator();
+            let mut current_dir = current_dir.clone();
+            current_dir.push(&table);
+            if let Err(e) = ensure_directory_exists_and_empty(&current_dir).await {
+                log_error!("{}", format!("{:#}", e).red());
+                return EXIT_VALIDATION_FAILURE;
+            }
+            let schema_table = format!("{}.{}", schema, table);
+            let (statements, complete) =
+                get_table_statements(&source_db_provider, &schema, &table, &mut export_summary)
+                    .await;
+            if !complete {
+                failed_tables += 1;
+            }
+            for statement in statements {
+                let Some(object_type) = statement.object_type else {
+                    let result = export_statement(
+                        "Index",
+                        &current_dir,
+                        &statement.name,
+                        statement.definition,
+                        &schema_table,
+                    )
+                    .await;
+                    if result.get_status() == ImportStatus::Succeeded {
+                        exported_indexes += 1;
+                    } else {
+                        failed_indexes += 1;
+                    }
+                    export_summary.add_results(vec![result]);
+                    continue;
+                };
+                let (exported, unsupported) = object_counts.entry(object_type).or_default();
+                // what import could not translate is reported now instead of written
+                if let Translation::Unsupported(reason) = translate_statement(&statement.definition)
+                {
+                    log_warn!(
+                        "{}: {}",
+                        format!("Skipping {}", object_type).yellow(),
+                        reason.yellow()
+                    );
+                    let mut result = IndexImportResult::new(&IndexTask::new(
+                        &statement.name,
+                        Some(schema_table.clone()),
+                        statement.definition,
+                    ));
+                    result.unsupported = Some(reason);
+                    *unsupported += 1;
+                    export_summary.add_results(vec![result]);
+                    continue;
+                }
+                let result = export_statement(
+                    &object_type.to_string(),
+                    &current_dir,
+                    &statement.name,
+                    statement.definition,
+                    &schema_table,
+                )
+                .await;
+                if result.get_status() == ImportStatus::Succeeded {
+                    *exported += 1;
+                } else {
+                    failed_indexes += 1;
+                }
+                export_summary.add_results(vec![result]);
+            }
+        }
+    }
+    print_separator();
+    log_info!(
+        "Exported indexes: <{}>",
+        exported_indexes.to_string().green()
+    );
+    for object_type in TableObjectType::ALL {
+        let (exported, unsupported) = object_counts.get(&object_type).copied().unwrap_or_default();
+        log_info!(
+            "Exported {}: <{}>",
+            object_type.get_plural(),
+            exported.to_string().green()
+        );
+        log_info!(
+            "Unsupported {}: <{}>",
+            object_type.get_plural(),
+            unsupported.to_string().yellow()
+        );
+    }
+    log_info!("Failed tables: <{}>", failed_tables.to_string().red());
+    log_info!("Failed indexes: <{}>", failed_indexes.to_string().red());
+    export_summary.set_elapsed(started.elapsed());
+    write_run_report("export", started_at, &settings, config, &export_summary).await;
+    export_summary.get_exit_code()
+}
+
+/// Writes one exported statement to `<name>.sql` and records it like an import attempt.
+pub async fn export_statement(
+    object_type: &str,
+    current_dir: &Path,
+    name: &str,
+    definition: String,
+    schema_table: &str,
+) -> IndexImportResult {
+    let now = Instant::now();
+    let file_name = &current_dir.join(name.replace("[", "").replace("]", "").to_string() + ".sql");
+    log_info!(
+        "Exporting {}: <{}> to <{}>",
+        object_type,
+        name.yellow(),
+        file_name.to_str().unwrap().yellow()
+    );
+    let write_result = write_index_to_file(file_name, &definition).await;
+    let mut result = IndexImportResult::new(&IndexTask::new(
+        file_name.to_str().unwrap(),
+        Some(schema_table.to_string()),
+        definition,
+    ));
+    result.add_attempt(
+        now.elapsed(),
+        write_result.as_ref().err().map(|e| e.to_string()),
+    );
+    if let Err(e) = write_result {
+        log_error!("{}", e.to_string().red());
+    }
+    result
+}
@@ -0,0 +1,184 @@
+use crate::build_registry::build_registry::BuildRegistry;
+use crate::commands::run_utils::{
+    check_tablespaces, create_postgres_pool, print_shutdown_cleanup, run_index_tasks,
+    write_rollback_script, write_run_report,
+};
+use crate::config_provider::Config;
+use crate::ddl_parser::ddl_parser::make_concurrent;
+use crate::exit_codes::EXIT_VALIDATION_FAILURE;
+use crate::helpers::print_separator;
+use crate::import_summary::import_summary::IndexImportResult;
+use crate::importer::importer::{IndexTask, TransactionMode};
+use crate::postgres_provider::postgres_provider::get_table_sizes;
+use crate::scheduler::scheduler::Scheduler;
+use crate::settings::settings::Settings;
+use crate::shared::file_utils::{get_schema_table_from_path, list_files, read_file};
+use crate::translator::translator::{Translation, translate_statement};
+use crate::{log_error, log_info, log_warn};
+use colored::Colorize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use time::OffsetDateTime;
+
+/// Translates a statement the way import runs it; one without a translation comes back as
+/// an unsupported result.
+pub fn prepare_index_task(
+    settings: &Settings,
+    file_name: &str,
+    path_schema_table: Option<String>,
+    statement: String,
+) -> Result<IndexTask, Box<IndexImportResult>> {
+    let statement = match translate_statement(&statement) {
+        Translation::Translated(translated) => translated,
+        Translation::Unchanged => statement,
+        Translation::Unsupported(reason) => {
+            log_warn!(
+                "{} <{}>: {}",
+                "Unsupported".yellow(),
+                file_name.yellow(),
+                reason
+            );
+            let mut result =
+                IndexImportResult::new(&IndexTask::new(file_name, path_schema_table, statement));
+            result.unsupported = Some(reason);
+            return Err(Box::new(result));
+        }
+    };
+    let statement = if settings.get_concurrently() {
+        make_concurrent(&statement).unwrap_or(statement)
+    } else {
+        statement
+    };
+    Ok(IndexTask::new(file_name, path_schema_table, statement))
+}
+
+pub async fn import_indexes(settings: Arc<Settings>, config: &Config) -> i32 {
+    let started_at = OffsetDateTime::now_utc();
+    // region Get a file list
+    log_info!("Getting a list of files in input directory ...");
+    let input_dir_name = settings.get_input_dir_as_ref().clone().unwrap();
+    let input_dir = PathBuf::from(input_dir_name);
+    let mut files_list: Vec<PathBuf> = Vec::new();
+    if let Err(e) = list_files(&input_dir, &mut files_list).await {
+        log_error!("{}", e.to_string().red());
+        return EXIT_VALIDATION_FAILURE;
+    }
+    files_list.retain(|f| f.extension().is_some_and(|e| e == "sql"));
+    log_info!(
+        "{}",
+        "DONE Getting a list of files in input directory".green()
+    );
+    // endregion
+    print_separator();
+    // region Postgres Connection Pool
+    let postgres_pool = match create_postgres_pool(&settings, config).await {
+        Ok(postgres_pool) => postgres_pool,
+        Err(exit_code) => return exit_code,
+    };
+    if let Err(exit_code) = check_tablespaces(&settings, config, &postgres_pool).await {
+        return exit_code;
+    }
+    // endregion
+    print_separator();
+    // region Schedule
+    log_info!("Scheduling Indexes ...");
+    let mut index_tasks = Vec::new();
+    let mut unsupported_results = Vec::new();
+    for file in files_list {
+        let file_content = match read_file(&file).await {
+            Ok(file_content) => file_content,
+            Err(e) => {
+                log_error!("{}", e.to_string().red());
+                return EXIT_VALIDATION_FAILURE;
+            }
+        };
+        let index_task = match prepare_index_task(
+            &settings,
+            file.to_str().unwrap(),
+            get_schema_table_from_path(&input_dir, &file),
+            file_content,
+        ) {
+            Ok(index_task) => index_task,
+            Err(result) => {
+                unsupported_results.push(*result);
+                continue;
+            }
+        };
+        if settings.get_transaction_mode().is_some() && index_task.concurrently {
+            log_error!(
+                "{}: <{}>",
+                "CONCURRENTLY cannot run inside a transaction, remove it or drop --transactional"
+                    .red(),
+                index_task.file_name
+            );
+            return EXIT_VALIDATION_FAILURE;
+        }
+        index_tasks.push(index_task);
+    }
+    let mut schema_tables: Vec<String> = index_tasks
+        .iter()
+        .filter_map(|t| t.schema_table.clone())
+        .collect();
+    schema_tables.sort();
+    schema_tables.dedup();
+    let table_sizes = get_table_sizes(&postgres_pool, &schema_tables)
+        .await
+        .unwrap_or_else(|e| {
+            log_warn!(
+                "{}: {}",
+                "Failed to read table sizes, scheduling in file order".yellow(),
+                e.to_string().yellow()
+            );
+            HashMap::new()
+        });
+    let mut scheduler = Scheduler::new(settings.get_schedule_strategy());
+    scheduler
+        .set_group_by_table(settings.get_transaction_mode() == Some(TransactionMode::PerTable));
+    scheduler.set_analyze_tables(settings.get_analyze());
+    for index_task in index_tasks {
+        let table_size = index_task
+            .schema_table
+            .as_ref()
+            .and_then(|t| table_sizes.get(t))
+            .copied()
+            .unwrap_or(0);
+        scheduler.add_task(index_task, table_size);
+    }
+    log_info!(
+        "Tables: <{}>, strategy: <{}>",
+        schema_tables.len(),
+        settings.get_schedule_strategy()
+    );
+    log_info!("{}", "DONE Scheduling Indexes".green());
+    // endregion
+    print_separator();
+    // region Indexes Import
+    log_info!("Importing Indexes ...");
+    let build_registry = Arc::new(BuildRegistry::new(
+        scheduler.get_task_count(),
+        postgres_pool.get_tls(),
+    ));
+    let (mut import_summary, interrupted) = run_index_tasks(
+        &postgres_pool,
+        &settings,
+        scheduler,
+        build_registry.clone(),
+        None,
+    )
+    .await;
+    import_summary.add_results(unsupported_results);
+    print_separator();
+    log_info!("{}", import_summary);
+    if interrupted {
+        print_separator();
+        print_shutdown_cleanup(&postgres_pool, &build_registry).await;
+    }
+    print_separator();
+    write_rollback_script(&import_summary, started_at).await;
+    print_separator();
+    log_info!("{}", "DONE Importing Indexes".green());
+    //endregion
+    write_run_report("import", started_at, &settings, config, &import_summary).await;
+    import_summary.get_exit_code()
+}
@@ -0,0 +1,186 @@
+use crate::build_registry::build_registry::BuildRegistry;
+use crate::commands::export::export_statement;
+use crate::commands::import::prepare_index_task;
+use crate::commands::run_utils::{
+    check_tablespaces, create_postgres_pool, print_shutdown_cleanup, run_index_tasks,
+    write_rollback_script, write_run_report,
+};
+use crate::commands::source_reader::{
+    create_sql_server_provider, get_source_schemas, get_source_tables, get_table_statements,
+};
+use crate::config_provider::Config;
+use crate::helpers::print_separator;
+use crate::import_summary::import_summary::{ImportStatus, ImportSummary, IndexImportResult};
+use crate::importer::importer::{IndexTask, TransactionMode};
+use crate::postgres_provider::postgres_provider::get_table_sizes;
+use crate::scheduler::scheduler::{Scheduler, TableTasks};
+use crate::settings::settings::Settings;
+use crate::shared::file_utils::ensure_directory_exists_and_empty;
+use crate::{log_error, log_info};
+use colored::Colorize;
+use std::env;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tokio::time::Instant;
+
+/// Streams every table from SQL Server through the translator to the import workers as
+/// soon as it is read, so builds start while the export is still running. With
+/// `--output-dir` the statements are also written the way `export` writes them.
+pub async fn migrate_indexes(settings: Arc<Settings>, config: &Config) -> i32 {
+    let started_at = OffsetDateTime::now_utc();
+    let mut export_summary = ImportSummary::new();
+    let source_db_provider = match create_sql_server_provider(config) {
+        Ok(source_db_provider) => source_db_provider,
+        Err(exit_code) => return exit_code,
+    };
+    let postgres_pool = match create_postgres_pool(&settings, config).await {
+        Ok(postgres_pool) => postgres_pool,
+        Err(exit_code) => return exit_code,
+    };
+    if let Err(exit_code) = check_tablespaces(&settings, config, &postgres_pool).await {
+        return exit_code;
+    }
+    let schema_vec = match get_source_schemas(&settings, &source_db_provider).await {
+        Ok(schemas) => schemas,
+        Err(exit_code) => return exit_code,
+    };
+    let output_dir = settings
+        .get_output_dir_as_ref()
+        .as_ref()
+        .map(|output_dir| env::current_dir().unwrap().join(output_dir));
+    if let Some(output_dir) = &output_dir {
+        log_info!(
+            "Output directory: <{}>",
+            output_dir.to_str().unwrap().yellow()
+        );
+    }
+    print_separator();
+    // region Migrate
+    log_info!("Migrating Indexes ...");
+    let build_registry = Arc::new(BuildRegistry::new(0, postgres_pool.get_tls()));
+    let mut scheduler = Scheduler::new(settings.get_schedule_strategy());
+    scheduler
+        .set_group_by_table(settings.get_transaction_mode() == Some(TransactionMode::PerTable));
+    scheduler.set_analyze_tables(settings.get_analyze());
+    let (table_tx, table_rx) = flume::unbounded::<TableTasks>();
+    let export = async {
+        'schemas: for schema in schema_vec {
+            let Some(table_vec) =
+                get_source_tables(&settings, &source_db_provider, &schema, &mut export_summary)
+                    .await
+            else {
+                continue;
+            };
+            for table in table_vec {
+                let schema_table = format!("{}.{}", schema, table);
+                log_info!("Reading Source Table: <{}>", schema_table.yellow());
+                let (statements, _) =
+                    get_table_statements(&source_db_provider, &schema, &table, &mut export_summary)
+                        .await;
+                let mut table_dir = output_dir
+                    .as_ref()
+                    .map(|output_dir| output_dir.join(&schema).join(&table));
+                if let Some(dir) = &table_dir {
+                    let now = Instant::now();
+                    if let Err(e) = ensure_directory_exists_and_empty(dir).await {
+                        // the table is still migrated, only its audit files are missing
+                        log_error!("{}", format!("{:#}", e).red());
+                        let mut result = IndexImportResult::new(&IndexTask::new(
+                            dir.to_str().unwrap_or_default(),
+                            Some(schema_table.clone()),
+                            String::new(),
+                        ));
+                        result.add_attempt(now.elapsed(), Some(format!("{:#}", e)));
+                        export_summary.add_results(vec![result]);
+                        table_dir = None;
+                    }
+                }
+                let mut index_tasks = Vec::new();
+                for statement in statements {
+                    let file_name = match &table_dir {
+                        Some(table_dir) => {
+                            let object_type = statement
+                                .object_type
+                                .map(|object_type| object_type.to_string())
+                                .unwrap_or_else(|| "Index".to_string());
+                            let result = export_statement(
+                                &object_type,
+                                table_dir,
+                                &statement.name,
+                                statement.definition.clone(),
+                                &schema_table,
+                            )
+                            .await;
+                            let file_name = result.file_name.clone();
+                            // the audit trail only shows up in the summary when it failed
+                            if result.get_status() != ImportStatus::Succeeded {
+                                export_summary.add_results(vec![result]);
+                            }
+                            file_name
+                        }
+                        None => format!(
+                            "{}/{}/{}.sql",
+                            schema,
+                            table,
+                            statement.name.replace(['[', ']'], "")
+                        ),
+                    };
+                    match prepare_index_task(
+                        &settings,
+                        &file_name,
+                        Some(schema_table.clone()),
+                        statement.definition,
+                    ) {
+                        Ok(index_task) => index_tasks.push(index_task),
+                        Err(result) => export_summary.add_results(vec![*result]),
+                    }
+                }
+                if index_tasks.is_empty() {
+                    continue;
+                }
+                let table_size =
+                    get_table_sizes(&postgres_pool, std::slice::from_ref(&schema_table))
+                        .await
+                        .ok()
+                        .and_then(|table_sizes| table_sizes.get(&schema_table).copied())
+                        .unwrap_or(0);
+                for _ in &index_tasks {
+                    build_registry.add_task();
+                }
+                if table_tx
+                    .send_async((index_tasks, table_size))
+                    .await
+                    .is_err()
+                {
+                    // the run was interrupted, nothing is scheduled anymore
+                    break 'schemas;
+                }
+            }
+        }
+        drop(table_tx);
+    };
+    let (_, (mut migrate_summary, interrupted)) = tokio::join!(
+        export,
+        run_index_tasks(
+            &postgres_pool,
+            &settings,
+            scheduler,
+            build_registry.clone(),
+            Some(table_rx)
+        )
+    );
+    migrate_summary.add_results(export_summary.into_results());
+    print_separator();
+    log_info!("{}", migrate_summary);
+    if interrupted {
+        print_separator();
+        print_shutdown_cleanup(&postgres_pool, &build_registry).await;
+    }
+    print_separator();
+    write_rollback_script(&migrate_summary, started_at).await;
+    print_separator();
+    log_info!("{}", "DONE Migrating Indexes".green());
+    // endregion
+    write_run_report("migrate", started_at, &settings, config, &migrate_summary).await;
+    migrate_summary.get_exit_code()
+}
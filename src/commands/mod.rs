@@ -0,0 +1,9 @@
+pub mod check_config;
+pub mod diff;
+pub mod export;
+pub mod import;
+pub mod migrate;
+pub mod rollback;
+pub mod run_utils;
+pub mod source_reader;
+pub mod verify;
@@ -0,0 +1,62 @@
+use crate::build_registry::build_registry::BuildRegistry;
+use crate::commands::run_utils::{create_postgres_pool, run_index_tasks, write_run_report};
+use crate::config_provider::Config;
+use crate::ddl_parser::ddl_parser::split_statements;
+use crate::exit_codes::EXIT_VALIDATION_FAILURE;
+use crate::helpers::print_separator;
+use crate::importer::importer::IndexTask;
+use crate::scheduler::scheduler::{ScheduleStrategy, Scheduler};
+use crate::settings::settings::Settings;
+use crate::shared::file_utils::read_file;
+use crate::{log_error, log_info};
+use colored::Colorize;
+use std::path::PathBuf;
+use std::sync::Arc;
+use time::OffsetDateTime;
+
+pub async fn rollback_indexes(
+    settings: Arc<Settings>,
+    config: &Config,
+    rollback_file: &str,
+) -> i32 {
+    let started_at = OffsetDateTime::now_utc();
+    // region Rollback Script
+    log_info!("Reading Rollback Script: <{}> ...", rollback_file.yellow());
+    let script = match read_file(&PathBuf::from(rollback_file)).await {
+        Ok(script) => script,
+        Err(e) => {
+            log_error!("{}", e.to_string().red());
+            return EXIT_VALIDATION_FAILURE;
+        }
+    };
+    // statements run in file order, the script already lists the last created object first
+    let mut scheduler = Scheduler::new(ScheduleStrategy::FileOrder);
+    for (line, statement) in split_statements(&script) {
+        scheduler.add_task(IndexTask::from_script(rollback_file, line, statement), 0);
+    }
+    log_info!("{}", "DONE Reading Rollback Script".green());
+    // endregion
+    print_separator();
+    // region Postgres Connection Pool
+    let postgres_pool = match create_postgres_pool(&settings, config).await {
+        Ok(postgres_pool) => postgres_pool,
+        Err(exit_code) => return exit_code,
+    };
+    // endregion
+    print_separator();
+    // region Rollback
+    log_info!("Rolling Back Indexes ...");
+    let build_registry = Arc::new(BuildRegistry::new(
+        scheduler.get_task_count(),
+        postgres_pool.get_tls(),
+    ));
+    let (rollback_summary, _) =
+        run_index_tasks(&postgres_pool, &settings, scheduler, build_registry, None).await;
+    print_separator();
+    log_info!("{}", rollback_summary);
+    print_separator();
+    log_info!("{}", "DONE Rolling Back Indexes".green());
+    // endregion
+    write_run_report("rollback", started_at, &settings, config, &rollback_summary).await;
+    rollback_summary.get_exit_code()
+}
@@ -0,0 +1,307 @@
+use crate::build_registry::build_registry::BuildRegistry;
+use crate::config_provider::{Config, config_error};
+use crate::ddl_parser::ddl_parser::StatementKind;
+use crate::exit_codes::{EXIT_CONNECTION_FAILURE, EXIT_VALIDATION_FAILURE};
+use crate::helpers::print_separator;
+use crate::import_summary::import_summary::{ImportStatus, ImportSummary, IndexImportResult};
+use crate::importer::importer::{
+    ImportContext, IndexTask, TransactionMode, import_index, import_table_group,
+};
+use crate::postgres_provider::postgres_provider::{
+    PostgresPool, PostgresProvider, get_invalid_indexes, get_missing_tablespaces,
+};
+use crate::progress_monitor::progress_monitor::start_progress_monitor;
+use crate::run_report::run_report::RunReport;
+use crate::scheduler::scheduler::{Scheduler, TableTasks};
+use crate::settings::settings::Settings;
+use crate::shared::file_utils::write_index_to_file;
+use crate::shutdown::shutdown::Shutdown;
+use crate::{log_debug, log_error, log_info, log_warn};
+use colored::Colorize;
+use futures_util::future::join_all;
+use std::path::PathBuf;
+use std::sync::Arc;
+use time::format_description::well_known::Rfc3339;
+use time::{OffsetDateTime, format_description};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+const CLEANUP_FILE_NAME: &str = "pg_index_import_cleanup.sql";
+const ROLLBACK_FILE_PREFIX: &str = "pg_index_import_rollback";
+
+pub async fn create_postgres_pool(
+    settings: &Settings,
+    config: &Config,
+) -> Result<PostgresPool, i32> {
+    log_info!("Creating Postgres Connection Pool ...");
+    let postgres_provider = match PostgresProvider::new(config.get_target_database_as_ref()) {
+        Ok(postgres_provider) => postgres_provider,
+        Err(e) => {
+            log_error!("{}", format!("{:#}", e).red());
+            return Err(EXIT_VALIDATION_FAILURE);
+        }
+    };
+    let postgres_pool_result = postgres_provider
+        .create_connection_pool(settings.get_threads() + 1) // + progress monitor
+        .await;
+    if postgres_pool_result.is_err() {
+        log_error!("{}", postgres_pool_result.err().unwrap().to_string().red());
+        return Err(EXIT_CONNECTION_FAILURE);
+    }
+    let postgres_pool = postgres_pool_result.ok().unwrap();
+    if let Err(e) = postgres_provider.check_connection().await {
+        // the TLS and server details are in the error sources
+        log_error!("{}", format!("{:#}", e).red());
+        return Err(EXIT_CONNECTION_FAILURE);
+    }
+    log_info!("{}", "DONE Creating Postgres Connection Pool".green());
+    Ok(postgres_pool)
+}
+
+/// The `default_tablespace` session settings must name tablespaces of the target, which
+/// `config check` cannot see without connecting.
+pub async fn check_tablespaces(
+    settings: &Settings,
+    config: &Config,
+    postgres_pool: &PostgresPool,
+) -> Result<(), i32> {
+    let tablespaces = settings.get_session_settings_as_ref().get_tablespaces();
+    if tablespaces.is_empty() {
+        return Ok(());
+    }
+    let names: Vec<String> = tablespaces.iter().map(|(_, name)| name.clone()).collect();
+    let missing = match get_missing_tablespaces(postgres_pool, &names).await {
+        Ok(missing) => missing,
+        Err(e) => {
+            log_error!("{}", format!("{:#}", e).red());
+            return Err(EXIT_CONNECTION_FAILURE);
+        }
+    };
+    let mut result = Ok(());
+    for (section, name) in tablespaces
+        .iter()
+        .filter(|(_, name)| missing.contains(name))
+    {
+        let e = config_error(
+            section,
+            "default_tablespace",
+            format!("tablespace {} does not exist in the target database", name),
+        );
+        log_error!("{}", config.describe_error(&e).red());
+        result = Err(EXIT_VALIDATION_FAILURE);
+    }
+    result
+}
+
+/// Runs the scheduled statements on `threads` workers until all are done or a shutdown
+/// is requested, and with `incoming` the tables streamed in until the sender is dropped.
+/// Returns the results and whether the run was interrupted.
+pub async fn run_index_tasks(
+    postgres_pool: &PostgresPool,
+    settings: &Arc<Settings>,
+    scheduler: Scheduler,
+    build_registry: Arc<BuildRegistry>,
+    incoming: Option<flume::Receiver<TableTasks>>,
+) -> (ImportSummary, bool) {
+    let shutdown = Arc::new(Shutdown::new());
+    shutdown.clone().listen_for_signals(build_registry.clone());
+    let import_context = ImportContext {
+        postgres_pool: postgres_pool.clone(),
+        settings: settings.clone(),
+        build_registry: build_registry.clone(),
+        shutdown: shutdown.clone(),
+        worker: 0,
+    };
+    let mut handles = Vec::new();
+    let (tx, rx) = flume::unbounded::<Vec<IndexTask>>();
+    let (done_tx, done_rx) = flume::unbounded::<(String, bool)>();
+    for worker in 1..=settings.get_threads() as usize {
+        let rx = rx.clone();
+        let done_tx = done_tx.clone();
+        let import_context = ImportContext {
+            worker,
+            ..import_context.clone()
+        };
+        let handle: JoinHandle<anyhow::Result<Vec<IndexImportResult>>> = tokio::spawn(async move {
+            let mut results = Vec::new();
+            while let Ok(index_tasks) = rx.recv_async().await {
+                print_separator();
+                let now = Instant::now();
+                if index_tasks[0].kind == StatementKind::Analyze {
+                    // queued by the scheduler during the run, not counted up front
+                    import_context.build_registry.add_task();
+                }
+                for index_task in &index_tasks {
+                    let log_context = import_context.get_log_context(index_task);
+                    log_info!(
+                        context: &log_context,
+                        "Importing Index: <{}> ({})",
+                        index_task.file_name,
+                        index_task.kind
+                    );
+                    log_debug!(context: &log_context, "{}", index_task.query);
+                }
+
+                let group_results = match import_context.settings.get_transaction_mode() {
+                    Some(TransactionMode::PerTable) => {
+                        import_table_group(&import_context, &index_tasks).await
+                    }
+                    None => vec![import_index(&import_context, &index_tasks[0]).await],
+                };
+                let succeeded = group_results
+                    .iter()
+                    .any(|r| r.get_status() == ImportStatus::Succeeded);
+                for mut result in group_results {
+                    result.finish_order = import_context.build_registry.finish_task();
+                    results.push(result);
+                }
+
+                let elapsed = now.elapsed();
+                let mut log_context = import_context
+                    .get_log_context(&index_tasks[0])
+                    .with_elapsed(elapsed);
+                if index_tasks.len() > 1 {
+                    // a transaction group, the elapsed time is the one of the whole table
+                    log_context.index = None;
+                }
+                log_info!(context: &log_context, "Elapsed: {:.2?}", elapsed);
+                let table_key = index_tasks[0].table_key.clone();
+                let _ = done_tx.send_async((table_key, succeeded)).await;
+            }
+            Ok(results)
+        });
+        handles.push(handle);
+    }
+    drop(rx);
+    drop(done_tx);
+    let progress_monitor = start_progress_monitor(
+        postgres_pool.clone(),
+        build_registry.clone(),
+        settings.get_progress_interval(),
+    );
+    // drops tx when all work is handed out
+    let not_started = scheduler
+        .run(settings.get_threads(), &shutdown, tx, done_rx, incoming)
+        .await;
+
+    let mut import_summary = ImportSummary::new();
+    let thread_results = join_all(handles).await;
+    if let Some(progress_monitor) = progress_monitor {
+        progress_monitor.abort();
+    }
+    for thread_result in thread_results {
+        match thread_result {
+            Ok(Ok(results)) => import_summary.add_results(results),
+            Ok(Err(e)) => log_error!("Error in thread: {}", e.to_string().red()),
+            Err(e) => log_error!("Error in thread: {}", e.to_string().red()),
+        }
+    }
+    import_summary.add_results(not_started.iter().map(IndexImportResult::new).collect());
+    import_summary.set_elapsed(build_registry.get_started().elapsed());
+    (import_summary, shutdown.is_stopping())
+}
+
+/// Written after every import that created something, `rollback <file>` runs it.
+pub async fn write_rollback_script(import_summary: &ImportSummary, started_at: OffsetDateTime) {
+    let rollback_statements = import_summary.get_rollback_statements();
+    if rollback_statements.is_empty() {
+        log_info!("{}", "Nothing created, no rollback script written".yellow());
+        return;
+    }
+    let rollback_file = PathBuf::from(format!(
+        "{}_{}.sql",
+        ROLLBACK_FILE_PREFIX,
+        get_file_timestamp(started_at)
+    ));
+    let mut rollback_script = format!(
+        "-- Drops the objects created by the import started at {}, last created first\n",
+        started_at.format(&Rfc3339).unwrap_or_default()
+    );
+    for statement in &rollback_statements {
+        rollback_script.push_str(statement);
+        rollback_script.push('\n');
+    }
+    match write_index_to_file(&rollback_file, &rollback_script).await {
+        Ok(_) => log_info!(
+            "Rollback script: <{}> ({} statements), run it with the rollback command",
+            rollback_file.to_str().unwrap().yellow(),
+            rollback_statements.len()
+        ),
+        Err(e) => log_error!("{}", e.to_string().red()),
+    }
+}
+
+pub fn get_file_timestamp(started_at: OffsetDateTime) -> String {
+    format_description::parse("[year][month][day]_[hour][minute][second]")
+        .ok()
+        .and_then(|format| started_at.format(&format).ok())
+        .unwrap_or_default()
+}
+
+pub async fn write_run_report(
+    command: &str,
+    started_at: OffsetDateTime,
+    settings: &Settings,
+    config: &Config,
+    summary: &ImportSummary,
+) {
+    let Some(report_file) = settings.get_report_file_as_ref() else {
+        return;
+    };
+    let run_report = RunReport {
+        command,
+        started_at,
+        settings,
+        config,
+        summary,
+    };
+    match run_report
+        .write(report_file, settings.get_report_format())
+        .await
+    {
+        Ok(_) => log_info!(
+            "Report: <{}> ({})",
+            report_file.yellow(),
+            settings.get_report_format()
+        ),
+        Err(e) => log_error!("{}", e.to_string().red()),
+    }
+}
+
+/// INVALID indexes are what an interrupted `CREATE INDEX CONCURRENTLY` leaves behind;
+/// a plain `CREATE INDEX` is rolled back by the server and needs no cleanup.
+/// Only the concurrent builds this run started are checked: indexes that were INVALID
+/// before, or concurrent builds of other sessions, are none of its business.
+pub async fn print_shutdown_cleanup(postgres_pool: &PostgresPool, build_registry: &BuildRegistry) {
+    log_warn!("{}", "Import was interrupted".yellow());
+    let concurrent_indexes = build_registry.get_concurrent_indexes();
+    let invalid_indexes = match get_invalid_indexes(postgres_pool, &concurrent_indexes).await {
+        Ok(invalid_indexes) => invalid_indexes,
+        Err(e) => {
+            log_error!(
+                "{}: {}",
+                "Failed to check for INVALID indexes".red(),
+                e.to_string().red()
+            );
+            return;
+        }
+    };
+    if invalid_indexes.is_empty() {
+        log_info!("{}", "No cleanup needed, no INVALID indexes left".green());
+        return;
+    }
+    let cleanup_script: String = invalid_indexes
+        .iter()
+        .map(|index| format!("DROP INDEX CONCURRENTLY IF EXISTS {};\n", index))
+        .collect();
+    log_warn!(
+        "{}",
+        "INVALID indexes left behind, drop them before a rerun:".yellow()
+    );
+    log_info!("{}", cleanup_script);
+    let cleanup_file = PathBuf::from(CLEANUP_FILE_NAME);
+    match write_index_to_file(&cleanup_file, &cleanup_script).await {
+        Ok(_) => log_info!("Cleanup script: <{}>", CLEANUP_FILE_NAME.yellow()),
+        Err(e) => log_error!("{}", e.to_string().red()),
+    }
+}
@@ -0,0 +1,136 @@
+use crate::config_provider::Config;
+use crate::exit_codes::{EXIT_CONNECTION_FAILURE, EXIT_VALIDATION_FAILURE};
+use crate::import_summary::import_summary::{ImportSummary, IndexImportResult};
+use crate::importer::importer::IndexTask;
+use crate::retry_policy::retry_policy::classify_query_error;
+use crate::settings::settings::Settings;
+use crate::sql_server_provider::sql_server_provider::{SqlServerProvider, TableObjectType};
+use crate::{log_error, log_info};
+use colored::Colorize;
+use tokio::time::Instant;
+
+pub fn create_sql_server_provider(config: &Config) -> Result<SqlServerProvider, i32> {
+    log_info!("Creating Sql Server Provider ...");
+    let source_db_provider = match SqlServerProvider::new(config.get_source_database_as_ref()) {
+        Ok(source_db_provider) => source_db_provider,
+        Err(e) => {
+            log_error!("{}", e.to_string().red());
+            return Err(EXIT_VALIDATION_FAILURE);
+        }
+    };
+    log_info!("{}", "DONE Creating Sql Server Provider".green());
+    Ok(source_db_provider)
+}
+
+/// The schema given with `--source-schema`, or every schema for `*`.
+pub async fn get_source_schemas(
+    settings: &Settings,
+    source_db_provider: &SqlServerProvider,
+) -> Result<Vec<String>, i32> {
+    if settings.get_source_schema_name_as_ref().ne("*") {
+        return Ok(vec![settings.get_source_schema_name_as_ref().to_string()]);
+    }
+    source_db_provider.get_all_schemas().await.map_err(|e| {
+        log_error!("{}", e.to_string().red());
+        EXIT_CONNECTION_FAILURE
+    })
+}
+
+/// The table given with `--source-table`, or every table of the schema for `*`. A schema
+/// whose tables cannot be listed is recorded in `summary` as failed.
+pub async fn get_source_tables(
+    settings: &Settings,
+    source_db_provider: &SqlServerProvider,
+    schema: &str,
+    summary: &mut ImportSummary,
+) -> Option<Vec<String>> {
+    if settings.get_source_table_name_as_ref().ne("*") {
+        return Some(vec![settings.get_source_table_name_as_ref().to_string()]);
+    }
+    let now = Instant::now();
+    match source_db_provider.get_all_tables_in_schema(schema).await {
+        Ok(tables) => Some(tables),
+        Err(e) => {
+            log_error!("{}", e.to_string().red());
+            let mut result = IndexImportResult::new(&IndexTask::new(schema, None, String::new()));
+            result.add_failed_attempt(now.elapsed(), e.to_string(), classify_query_error(&e));
+            summary.add_results(vec![result]);
+            None
+        }
+    }
+}
+
+/// A statement read from SQL Server, `object_type` is `None` for indexes and primary keys.
+pub struct SourceStatement {
+    pub object_type: Option<TableObjectType>,
+    pub name: String,
+    pub definition: String,
+}
+
+/// Indexes and primary keys of a table, then its other objects type by type. A failed
+/// query is recorded in `summary` and ends the table; the statements read before it are
+/// returned with `false`.
+pub async fn get_table_statements(
+    source_db_provider: &SqlServerProvider,
+    schema: &str,
+    table: &str,
+    summary: &mut ImportSummary,
+) -> (Vec<SourceStatement>, bool) {
+    let schema_table = format!("{}.{}", schema, table);
+    let mut statements = Vec::new();
+    let mut record_failure = |now: Instant, e: anyhow::Error| {
+        log_error!("{}", e.to_string().red());
+        let mut result = IndexImportResult::new(&IndexTask::new(
+            table,
+            Some(schema_table.clone()),
+            String::new(),
+        ));
+        result.add_failed_attempt(now.elapsed(), e.to_string(), classify_query_error(&e));
+        summary.add_results(vec![result]);
+    };
+    let now = Instant::now();
+    match source_db_provider
+        .get_all_indexes_in_table(schema, table)
+        .await
+    {
+        Ok(indexes) => {
+            statements.extend(
+                indexes
+                    .into_iter()
+                    .map(|(name, definition)| SourceStatement {
+                        object_type: None,
+                        name,
+                        definition,
+                    }),
+            )
+        }
+        Err(e) => {
+            record_failure(now, e);
+            return (statements, false);
+        }
+    }
+    for object_type in TableObjectType::ALL {
+        let now = Instant::now();
+        match source_db_provider
+            .get_all_objects_in_table(object_type, schema, table)
+            .await
+        {
+            Ok(objects) => {
+                statements.extend(
+                    objects
+                        .into_iter()
+                        .map(|(name, definition)| SourceStatement {
+                            object_type: Some(object_type),
+                            name,
+                            definition,
+                        }),
+                )
+            }
+            Err(e) => {
+                record_failure(now, e);
+                return (statements, false);
+            }
+        }
+    }
+    (statements, true)
+}
@@ -0,0 +1,193 @@
+use crate::commands::run_utils::{create_postgres_pool, write_run_report};
+use crate::commands::source_reader::{
+    create_sql_server_provider, get_source_schemas, get_source_tables,
+};
+use crate::config_provider::Config;
+use crate::ddl_parser::ddl_parser::quote_identifier;
+use crate::helpers::print_separator;
+use crate::import_summary::import_summary::{ImportSummary, IndexImportResult};
+use crate::importer::importer::IndexTask;
+use crate::index_verifier::index_verifier::{IndexComparison, VerifyStatus, compare_indexes};
+use crate::postgres_provider::postgres_provider::get_index_definitions;
+use crate::retry_policy::retry_policy::classify_query_error;
+use crate::settings::settings::Settings;
+use crate::translator::translator::map_name;
+use crate::{log_error, log_info};
+use colored::Colorize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tokio::time::Instant;
+
+/// The indexes of one source table next to those of the target table the name mapping
+/// leads to.
+pub struct TableComparison {
+    pub schema_table: String,
+    pub quoted_target_schema: String,
+    pub quoted_target_table: String,
+    /// the statements `export` writes for the table, only read with `with_statements`
+    pub index_statements: Vec<(String, String)>,
+    pub comparisons: Vec<IndexComparison>,
+}
+
+/// Connects to both databases and hands every selected source table, compared with its
+/// target table, to `on_table`. A table whose indexes cannot be read on either side is
+/// recorded in `summary` as failed. Returns the number of failed schemas and tables, or
+/// the exit code when a database cannot be reached.
+pub async fn compare_tables(
+    settings: &Settings,
+    config: &Config,
+    summary: &mut ImportSummary,
+    with_statements: bool,
+    mut on_table: impl FnMut(TableComparison, &mut ImportSummary),
+) -> Result<usize, i32> {
+    let source_db_provider = create_sql_server_provider(config)?;
+    let postgres_pool = create_postgres_pool(settings, config).await?;
+    let schema_vec = get_source_schemas(settings, &source_db_provider).await?;
+    let mut failed_tables = 0;
+    for schema in schema_vec {
+        print_separator();
+        log_info!("Source Schema: <{}>", schema.yellow());
+        let Some(table_vec) =
+            get_source_tables(settings, &source_db_provider, &schema, summary).await
+        else {
+            failed_tables += 1;
+            continue;
+        };
+        for table in table_vec {
+            print_separator();
+            let schema_table = format!("{}.{}", schema, table);
+            let quoted_target_schema = quote_identifier(&map_name(&schema));
+            let quoted_target_table = quote_identifier(&map_name(&table));
+            let target_table = format!("{}.{}", quoted_target_schema, quoted_target_table);
+            log_info!(
+                "Source Table: <{}>, Target Table: <{}>",
+                schema_table.yellow(),
+                target_table.yellow()
+            );
+            let now = Instant::now();
+            let index_statements = if with_statements {
+                source_db_provider
+                    .get_all_indexes_in_table(&schema, &table)
+                    .await
+            } else {
+                Ok(Vec::new())
+            };
+            let indexes = match index_statements {
+                Ok(index_statements) => match source_db_provider
+                    .get_index_definitions_in_table(&schema, &table)
+                    .await
+                {
+                    Ok(source_indexes) => get_index_definitions(&postgres_pool, &target_table)
+                        .await
+                        .map(|target_indexes| (index_statements, source_indexes, target_indexes)),
+                    Err(e) => Err(e),
+                },
+                Err(e) => Err(e),
+            };
+            let (index_statements, source_indexes, target_indexes) = match indexes {
+                Ok(indexes) => indexes,
+                Err(e) => {
+                    log_error!("{}", e.to_string().red());
+                    failed_tables += 1;
+                    let mut result = IndexImportResult::new(&IndexTask::new(
+                        &table,
+                        Some(schema_table),
+                        String::new(),
+                    ));
+                    result.add_failed_attempt(
+                        now.elapsed(),
+                        e.to_string(),
+                        classify_query_error(&e),
+                    );
+                    summary.add_results(vec![result]);
+                    continue;
+                }
+            };
+            if target_indexes.is_none() {
+                log_error!("{}", "Target table does not exist".red());
+            }
+            let comparisons = compare_indexes(&source_indexes, &target_indexes.unwrap_or_default());
+            on_table(
+                TableComparison {
+                    schema_table,
+                    quoted_target_schema,
+                    quoted_target_table,
+                    index_statements,
+                    comparisons,
+                },
+                summary,
+            );
+        }
+    }
+    Ok(failed_tables)
+}
+
+/// Compares every source table's indexes with the target table the name mapping leads to.
+pub async fn verify_indexes(settings: Arc<Settings>, config: &Config) -> i32 {
+    let started_at = OffsetDateTime::now_utc();
+    let started = Instant::now();
+    let mut verify_summary = ImportSummary::new();
+    let mut counts: HashMap<VerifyStatus, usize> = HashMap::new();
+    let on_table = |table: TableComparison, summary: &mut ImportSummary| {
+        for comparison in table.comparisons {
+            let status = comparison.get_status();
+            *counts.entry(status).or_default() += 1;
+            let status_text = format!("{:<10}", status.to_string());
+            log_info!(
+                "{} <{}>",
+                if status == VerifyStatus::Matched {
+                    status_text.green()
+                } else {
+                    status_text.red()
+                },
+                comparison.get_name().yellow()
+            );
+            for mismatch in &comparison.mismatches {
+                log_info!("           {}", mismatch);
+            }
+            let definition = comparison.source.as_ref().or(comparison.target.as_ref());
+            let mut result = IndexImportResult::new(&IndexTask::new(
+                comparison.get_name(),
+                Some(table.schema_table.clone()),
+                definition.map(|d| d.to_string()).unwrap_or_default(),
+            ));
+            result.target_name = comparison.target.as_ref().map(|d| d.name.clone());
+            let error = match status {
+                VerifyStatus::Matched => None,
+                VerifyStatus::Missing => Some("missing in the target".to_string()),
+                VerifyStatus::Extra => Some("not in the source".to_string()),
+                VerifyStatus::Mismatched => Some(comparison.mismatches.join("; ")),
+            };
+            result.add_attempt(std::time::Duration::ZERO, error);
+            summary.add_results(vec![result]);
+        }
+    };
+    let failed_tables =
+        match compare_tables(&settings, config, &mut verify_summary, false, on_table).await {
+            Ok(failed_tables) => failed_tables,
+            Err(exit_code) => return exit_code,
+        };
+    print_separator();
+    let count = |status: VerifyStatus| counts.get(&status).copied().unwrap_or_default();
+    log_info!(
+        "Matched indexes: <{}>",
+        count(VerifyStatus::Matched).to_string().green()
+    );
+    log_info!(
+        "Missing indexes: <{}>",
+        count(VerifyStatus::Missing).to_string().red()
+    );
+    log_info!(
+        "Extra indexes: <{}>",
+        count(VerifyStatus::Extra).to_string().red()
+    );
+    log_info!(
+        "Mismatched indexes: <{}>",
+        count(VerifyStatus::Mismatched).to_string().red()
+    );
+    log_info!("Failed tables: <{}>", failed_tables.to_string().red());
+    verify_summary.set_elapsed(started.elapsed());
+    write_run_report("verify", started_at, &settings, config, &verify_summary).await;
+    verify_summary.get_exit_code()
+}
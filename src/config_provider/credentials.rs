@@ -0,0 +1,144 @@
+use crate::log_warn;
+use anyhow::{Context, Result};
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+
+/// The first line of the file, without the line break.
+pub fn read_password_file(password_file: &str) -> Result<String> {
+    let content = std::fs::read_to_string(password_file)
+        .with_context(|| format!("Failed to read password file: {}", password_file))?;
+    Ok(content.lines().next().unwrap_or_default().to_string())
+}
+
+/// Reads a password from the terminal without echoing it.
+pub fn prompt_password(prompt: &str) -> Result<String> {
+    eprint!("{}", prompt);
+    std::io::stderr().flush()?;
+    let echo = EchoGuard::disable();
+    let mut password = String::new();
+    std::io::stdin().lock().read_line(&mut password)?;
+    drop(echo);
+    eprintln!();
+    Ok(password.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Turns terminal echo back on when dropped.
+struct EchoGuard {
+    #[cfg(unix)]
+    original: Option<libc::termios>,
+}
+
+impl EchoGuard {
+    #[cfg(unix)]
+    fn disable() -> Self {
+        // SAFETY: termios is plain data, filled in by tcgetattr before it is used
+        unsafe {
+            let mut termios: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut termios) != 0 {
+                // not a terminal, e.g. a piped password
+                return EchoGuard { original: None };
+            }
+            let original = termios;
+            termios.c_lflag &= !libc::ECHO;
+            termios.c_lflag |= libc::ECHONL;
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios);
+            EchoGuard {
+                original: Some(original),
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn disable() -> Self {
+        EchoGuard {}
+    }
+}
+
+impl Drop for EchoGuard {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(original) = &self.original {
+            // SAFETY: restores the attributes read in `disable`
+            unsafe {
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, original);
+            }
+        }
+    }
+}
+
+/// Looks the password up in `PGPASSFILE` or `~/.pgpass` the way libpq does: lines of
+/// `host:port:database:user:password`, `*` matches anything, `\` escapes `:` and `\`,
+/// the first matching line wins. Like libpq, a file others can read is ignored.
+pub fn find_pgpass_password(host: &str, port: u16, database: &str, user: &str) -> Option<String> {
+    let pgpass_file = std::env::var_os("PGPASSFILE")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".pgpass")))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&pgpass_file).ok()?.permissions().mode();
+        if mode & 0o077 != 0 {
+            log_warn!(
+                "WARNING: password file {} has group or world access; permissions should be u=rw (0600) or less",
+                pgpass_file.display()
+            );
+            return None;
+        }
+    }
+    let content = std::fs::read_to_string(&pgpass_file).ok()?;
+    let port = port.to_string();
+    let values = [host, port.as_str(), database, user];
+    content
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(split_pgpass_line)
+        .filter(|fields| fields.len() == 5)
+        .find(|fields| {
+            values
+                .iter()
+                .zip(fields.iter())
+                .all(|(value, field)| field == "*" || field == value)
+        })
+        .map(|mut fields| fields.remove(4))
+}
+
+fn split_pgpass_line(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => fields.last_mut().unwrap().extend(chars.next()),
+            // the password is the rest of the line
+            ':' if fields.len() < 5 => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_pgpass_line_unescapes_fields() {
+        assert_eq!(
+            split_pgpass_line("db.example.com:5432:sales:app:secret"),
+            ["db.example.com", "5432", "sales", "app", "secret"]
+        );
+        assert_eq!(
+            split_pgpass_line("*:*:*:a\\:b:pa\\\\ss"),
+            ["*", "*", "*", "a:b", "pa\\ss"]
+        );
+    }
+
+    #[test]
+    fn split_pgpass_line_keeps_colons_in_the_password() {
+        assert_eq!(
+            split_pgpass_line("h:1:d:u:p:a:ss"),
+            ["h", "1", "d", "u", "p:a:ss"]
+        );
+        // fewer fields than needed, the line is skipped by the caller
+        assert_eq!(split_pgpass_line("h:1"), ["h", "1"]);
+    }
+}
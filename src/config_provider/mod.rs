@@ -1,11 +1,35 @@
-use anyhow::{Context, Result};
+mod connection_string;
+mod credentials;
+
+use anyhow::{Context, Result, anyhow, bail};
+use connection_string::{interpolate_env_vars, parse_ado_string, parse_postgres_url};
+use credentials::{find_pgpass_password, prompt_password, read_password_file};
 use serde::Deserialize;
 use serde::Serialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::path::Path;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
     source_database: SourceDatabase,
     target_database: TargetDatabase,
+    #[serde(default)]
+    session_settings: SessionSettings,
+    #[serde(default)]
+    settings: FileSettings,
+    /// only read to check every profile, `read_config` applies the one picked
+    #[serde(default, skip_serializing)]
+    profiles: BTreeMap<String, FileSettings>,
+    /// name and settings of the profile picked with `--profile`
+    #[serde(skip)]
+    profile: Option<(String, FileSettings)>,
+    #[serde(skip)]
+    file_name: String,
+    #[serde(skip)]
+    content: String,
 }
 
 impl Config {
@@ -16,15 +40,246 @@ impl Config {
     pub fn get_target_database_as_ref(&self) -> &TargetDatabase {
         &self.target_database
     }
+
+    pub fn get_session_settings_as_ref(&self) -> &SessionSettings {
+        &self.session_settings
+    }
+
+    pub fn get_settings_as_ref(&self) -> &FileSettings {
+        &self.settings
+    }
+
+    pub fn get_profile_as_ref(&self) -> &Option<(String, FileSettings)> {
+        &self.profile
+    }
+
+    pub fn get_profile_names(&self) -> Vec<String> {
+        self.profiles.keys().cloned().collect()
+    }
+
+    /// Prefixes an error with the file name and, for a `ConfigError`, the line of its key.
+    pub fn describe_error(&self, e: &anyhow::Error) -> String {
+        describe_error(&self.file_name, &self.content, self.get_profile_name(), e)
+    }
+
+    fn get_profile_name(&self) -> Option<&str> {
+        self.profile.as_ref().map(|(name, _)| name.as_str())
+    }
+}
+
+fn describe_error(
+    file_name: &str,
+    content: &str,
+    profile: Option<&str>,
+    e: &anyhow::Error,
+) -> String {
+    let Some(config_error) = e.downcast_ref::<ConfigError>() else {
+        return format!("{}: {:#}", file_name, e);
+    };
+    // a database key may come from the profile that overrides the section
+    let line = profile
+        .and_then(|profile| {
+            let section = format!("profiles.{}.{}", profile, config_error.section);
+            find_key_line(content, &section, &config_error.key)
+        })
+        .or_else(|| find_key_line(content, &config_error.section, &config_error.key));
+    match line {
+        Some(line) => format!("{}, line {}: {}", file_name, line, config_error),
+        None => format!("{}: {}", file_name, config_error),
+    }
+}
+
+/// 0 is rejected instead of being taken for "not set".
+fn deserialize_port<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<u16, D::Error> {
+    let port = u16::deserialize(deserializer)?;
+    if port == 0 {
+        return Err(serde::de::Error::custom("port must be from 1 to 65535"));
+    }
+    Ok(port)
+}
+
+/// The command line settings as `[settings]` and `[profiles.<name>]` set them. Durations
+/// and choices are written the way the command line takes them.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct FileSettings {
+    pub source_schema: Option<String>,
+    pub source_table: Option<String>,
+    pub output_dir: Option<String>,
+    pub input_dir: Option<String>,
+    pub threads: Option<u32>,
+    pub build_timeout: Option<String>,
+    pub lock_timeout: Option<String>,
+    pub max_retries: Option<u32>,
+    pub retry_delay_in_ms: Option<u64>,
+    pub schedule: Option<String>,
+    pub progress_interval: Option<String>,
+    pub concurrently: Option<bool>,
+    /// `per-table`, or `off` to turn off what `[settings]` turned on
+    pub transactional: Option<String>,
+    pub analyze: Option<bool>,
+    pub drop_extra: Option<bool>,
+    pub report: Option<String>,
+    pub report_format: Option<String>,
+    // only in profiles, `read_config` merges them into the top-level sections
+    #[serde(default, skip_serializing)]
+    pub source_database: Option<SourceDatabase>,
+    #[serde(default, skip_serializing)]
+    pub target_database: Option<TargetDatabase>,
+    #[serde(default, skip_serializing)]
+    pub session_settings: Option<SessionSettings>,
+}
+
+/// PostgreSQL session parameters applied to a pooled connection before every index build.
+/// Per-table overrides live under `[session_settings.tables."schema.table"]`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct SessionSettings {
+    maintenance_work_mem: Option<String>,
+    max_parallel_maintenance_workers: Option<u32>,
+    work_mem: Option<String>,
+    statement_timeout: Option<String>,
+    lock_timeout: Option<String>,
+    idle_in_transaction_session_timeout: Option<String>,
+    /// tablespace of the indexes built without a TABLESPACE clause
+    default_tablespace: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    tables: BTreeMap<String, SessionSettings>,
+}
+
+impl SessionSettings {
+    fn get_values(&self) -> Vec<(&'static str, String)> {
+        let mut values = Vec::new();
+        if let Some(v) = &self.maintenance_work_mem {
+            values.push(("maintenance_work_mem", v.clone()));
+        }
+        if let Some(v) = &self.max_parallel_maintenance_workers {
+            values.push(("max_parallel_maintenance_workers", v.to_string()));
+        }
+        if let Some(v) = &self.work_mem {
+            values.push(("work_mem", v.clone()));
+        }
+        if let Some(v) = &self.statement_timeout {
+            values.push(("statement_timeout", v.clone()));
+        }
+        if let Some(v) = &self.lock_timeout {
+            values.push(("lock_timeout", v.clone()));
+        }
+        if let Some(v) = &self.idle_in_transaction_session_timeout {
+            values.push(("idle_in_transaction_session_timeout", v.clone()));
+        }
+        if let Some(v) = &self.default_tablespace {
+            values.push(("default_tablespace", v.clone()));
+        }
+        values
+    }
+
+    /// Tablespaces named by `default_tablespace`, with the section setting them. An empty
+    /// name stands for the database default and is left out.
+    pub fn get_tablespaces(&self) -> Vec<(String, String)> {
+        let tables = self.tables.iter().map(|(table, table_settings)| {
+            (format!("session_settings.tables.{}", table), table_settings)
+        });
+        std::iter::once(("session_settings".to_string(), self))
+            .chain(tables)
+            .filter_map(|(section, session_settings)| {
+                session_settings
+                    .default_tablespace
+                    .clone()
+                    .filter(|tablespace| !tablespace.is_empty())
+                    .map(|tablespace| (section, tablespace))
+            })
+            .collect()
+    }
+
+    /// Returns the effective settings for a table: global values overridden by the
+    /// `tables` entry matching `schema.table` (case-insensitive).
+    pub fn get_values_for_table(&self, schema_table: Option<&str>) -> Vec<(&'static str, String)> {
+        let mut values = self.get_values();
+        let table_override = schema_table.and_then(|name| {
+            self.tables
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value)
+        });
+        if let Some(table_override) = table_override {
+            for (name, value) in table_override.get_values() {
+                match values.iter_mut().find(|(n, _)| *n == name) {
+                    Some(existing) => existing.1 = value,
+                    None => values.push((name, value)),
+                }
+            }
+        }
+        values
+    }
+}
+
+impl fmt::Display for SessionSettings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let values = self.get_values();
+        if values.is_empty() {
+            write!(f, "<server defaults>")?;
+        }
+        for (name, value) in values {
+            write!(f, "\n  {} = <{}>", name, value)?;
+        }
+        for (table, table_settings) in &self.tables {
+            write!(f, "\n  [{}]", table)?;
+            for (name, value) in table_settings.get_values() {
+                write!(f, "\n    {} = <{}>", name, value)?;
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct SourceDatabase {
+    /// ADO.NET connection string, the fields below override its parts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(default)]
     host: String,
+    #[serde(default, deserialize_with = "deserialize_port")]
     port: u16,
+    #[serde(default)]
     database: String,
+    #[serde(default)]
     user: String,
+    #[serde(default)]
     password: String,
+    password_file: Option<String>,
+    #[serde(default)]
+    password_prompt: bool,
+    encrypt: Option<Encryption>,
+    /// accepts any server certificate, for test servers only
+    trust_server_certificate: Option<bool>,
+    /// PEM or DER certificate of a CA trusted in addition to the system ones
+    trust_cert_ca: Option<String>,
+}
+
+/// TLS for SQL Server connections, as in the `Encrypt` connection string keyword.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum Encryption {
+    /// only the login is encrypted
+    Off,
+    /// encrypted when the server supports it
+    On,
+    /// the connection fails unless it is encrypted
+    #[default]
+    Required,
+}
+
+impl fmt::Display for Encryption {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Encryption::Off => write!(f, "off"),
+            Encryption::On => write!(f, "on"),
+            Encryption::Required => write!(f, "required"),
+        }
+    }
 }
 
 impl SourceDatabase {
@@ -44,18 +299,81 @@ impl SourceDatabase {
         &self.user
     }
 
-    pub fn get_password_as_ref(&self) -> &String {
-        &self.password
+    /// Asks for the password when `password_prompt` is set and nothing else provided one.
+    pub fn get_password(&self) -> Result<String> {
+        if self.password_prompt && self.password.is_empty() {
+            return prompt_password(&format!("Password for {}@{}: ", self.user, self.host));
+        }
+        Ok(self.password.clone())
+    }
+
+    pub fn get_encrypt(&self) -> Encryption {
+        self.encrypt.unwrap_or_default()
+    }
+
+    pub fn get_trust_server_certificate(&self) -> bool {
+        self.trust_server_certificate.unwrap_or_default()
+    }
+
+    pub fn get_trust_cert_ca_as_ref(&self) -> &Option<String> {
+        &self.trust_cert_ca
     }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct TargetDatabase {
+    /// `postgres://` URL, the fields below override its parts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(default)]
     host: String,
+    #[serde(default, deserialize_with = "deserialize_port")]
     port: u16,
+    #[serde(default)]
     database: String,
+    #[serde(default)]
     user: String,
+    #[serde(default)]
     password: String,
+    password_file: Option<String>,
+    #[serde(default)]
+    password_prompt: bool,
+    sslmode: Option<SslMode>,
+    /// PEM file with the certificates of the trusted CAs, replaces the system ones
+    sslrootcert: Option<String>,
+    /// PEM client certificate, together with `sslkey`
+    sslcert: Option<String>,
+    /// PEM private key of the client certificate, PKCS#8
+    sslkey: Option<String>,
+}
+
+/// Same modes and meaning as libpq's `sslmode`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum SslMode {
+    Disable,
+    /// TLS when the server offers it, without checking the certificate
+    #[default]
+    Prefer,
+    /// TLS without checking the certificate, unless `sslrootcert` is set
+    Require,
+    /// TLS with a certificate signed by a trusted CA
+    VerifyCa,
+    /// `verify-ca` and the certificate must name the host
+    VerifyFull,
+}
+
+impl fmt::Display for SslMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SslMode::Disable => write!(f, "disable"),
+            SslMode::Prefer => write!(f, "prefer"),
+            SslMode::Require => write!(f, "require"),
+            SslMode::VerifyCa => write!(f, "verify-ca"),
+            SslMode::VerifyFull => write!(f, "verify-full"),
+        }
+    }
 }
 
 impl TargetDatabase {
@@ -75,8 +393,312 @@ impl TargetDatabase {
         &self.user
     }
 
-    pub fn get_password_as_ref(&self) -> &String {
-        &self.password
+    /// Asks for the password when `password_prompt` is set and nothing else provided one.
+    pub fn get_password(&self) -> Result<String> {
+        if self.password_prompt && self.password.is_empty() {
+            return prompt_password(&format!("Password for {}@{}: ", self.user, self.host));
+        }
+        Ok(self.password.clone())
+    }
+
+    pub fn get_sslmode(&self) -> SslMode {
+        self.sslmode.unwrap_or_default()
+    }
+
+    pub fn get_sslrootcert_as_ref(&self) -> &Option<String> {
+        &self.sslrootcert
+    }
+
+    pub fn get_sslcert_as_ref(&self) -> &Option<String> {
+        &self.sslcert
+    }
+
+    pub fn get_sslkey_as_ref(&self) -> &Option<String> {
+        &self.sslkey
+    }
+}
+
+const DEFAULT_SQL_SERVER_PORT: u16 = 1433;
+const DEFAULT_POSTGRES_PORT: u16 = 5432;
+
+impl SourceDatabase {
+    /// Fills the fields from the connection string and the password file.
+    fn resolve(&mut self) -> Result<()> {
+        if let Some(url) = self.url.take() {
+            let parameters = parse_ado_string(&url)
+                .map_err(|e| config_error("source_database", "url", format!("{:#}", e)))?;
+            fill(&mut self.host, parameters.host);
+            fill(&mut self.database, parameters.database);
+            fill(&mut self.user, parameters.user);
+            fill(&mut self.password, parameters.password);
+            if self.port == 0 {
+                self.port = parameters.port.unwrap_or_default();
+            }
+            if let Some(encrypt) = parameters.options.get("encrypt") {
+                self.encrypt = self.encrypt.or(Some(match encrypt.as_str() {
+                    "false" | "no" | "optional" => Encryption::Off,
+                    "true" | "yes" | "mandatory" | "strict" => Encryption::Required,
+                    _ => {
+                        return Err(config_error(
+                            "source_database",
+                            "url",
+                            format!("invalid Encrypt value: {}", encrypt),
+                        ));
+                    }
+                }));
+            }
+            if let Some(trust) = parameters.options.get("trustservercertificate") {
+                self.trust_server_certificate = self
+                    .trust_server_certificate
+                    .or(Some(matches!(trust.as_str(), "true" | "yes")));
+            }
+        }
+        if self.port == 0 {
+            self.port = DEFAULT_SQL_SERVER_PORT;
+        }
+        resolve_password(
+            "source_database",
+            &mut self.password,
+            &self.password_file,
+            self.password_prompt,
+        )?;
+        require_fields(
+            "source_database",
+            &[
+                ("host", &self.host),
+                ("database", &self.database),
+                ("user", &self.user),
+            ],
+        )?;
+        if self.trust_server_certificate == Some(true) && self.trust_cert_ca.is_some() {
+            return Err(config_error(
+                "source_database",
+                "trust_cert_ca",
+                "cannot be combined with trust_server_certificate",
+            ));
+        }
+        require_file("source_database", "trust_cert_ca", &self.trust_cert_ca)
+    }
+}
+
+impl TargetDatabase {
+    /// Fills the fields from the URL, the password file, the PG* environment variables and
+    /// `.pgpass`, in this order, the way libpq does.
+    fn resolve(&mut self) -> Result<()> {
+        if let Some(url) = self.url.take() {
+            let parameters = parse_postgres_url(&url)
+                .map_err(|e| config_error("target_database", "url", format!("{:#}", e)))?;
+            fill(&mut self.host, parameters.host);
+            fill(&mut self.database, parameters.database);
+            fill(&mut self.user, parameters.user);
+            fill(&mut self.password, parameters.password);
+            if self.port == 0 {
+                self.port = parameters.port.unwrap_or_default();
+            }
+            self.apply_ssl_options(|name| parameters.options.get(name).cloned())?;
+        }
+        fill(&mut self.host, env::var("PGHOST").ok());
+        fill(&mut self.database, env::var("PGDATABASE").ok());
+        fill(&mut self.user, env::var("PGUSER").ok());
+        if self.port == 0 {
+            self.port = match env::var("PGPORT") {
+                Ok(port) => port
+                    .parse()
+                    .with_context(|| format!("Invalid PGPORT: {}", port))?,
+                Err(_) => DEFAULT_POSTGRES_PORT,
+            };
+        }
+        self.apply_ssl_options(|name| env::var(format!("PG{}", name.to_uppercase())).ok())?;
+        fill(&mut self.host, Some("localhost".to_string()));
+        // libpq connects to the database named after the user by default
+        if self.database.is_empty() {
+            self.database = self.user.clone();
+        }
+        resolve_password(
+            "target_database",
+            &mut self.password,
+            &self.password_file,
+            self.password_prompt,
+        )?;
+        if !self.password_prompt {
+            fill(&mut self.password, env::var("PGPASSWORD").ok());
+            fill(
+                &mut self.password,
+                find_pgpass_password(&self.host, self.port, &self.database, &self.user),
+            );
+        }
+        require_fields("target_database", &[("user", &self.user)])?;
+        match (&self.sslcert, &self.sslkey) {
+            (Some(_), None) => {
+                return Err(config_error(
+                    "target_database",
+                    "sslcert",
+                    "needs sslkey, the key of the client certificate",
+                ));
+            }
+            (None, Some(_)) => {
+                return Err(config_error(
+                    "target_database",
+                    "sslkey",
+                    "needs sslcert, the client certificate",
+                ));
+            }
+            _ => {}
+        }
+        require_file("target_database", "sslrootcert", &self.sslrootcert)?;
+        require_file("target_database", "sslcert", &self.sslcert)?;
+        require_file("target_database", "sslkey", &self.sslkey)
+    }
+
+    /// Sets the TLS settings not set yet from `sslmode`, `sslrootcert`, `sslcert`, `sslkey`.
+    fn apply_ssl_options(&mut self, get_option: impl Fn(&str) -> Option<String>) -> Result<()> {
+        if self.sslmode.is_none()
+            && let Some(sslmode) = get_option("sslmode")
+        {
+            self.sslmode = Some(
+                SslMode::deserialize(toml::Value::String(sslmode.clone()))
+                    .map_err(|_| anyhow!("Invalid sslmode: {}", sslmode))?,
+            );
+        }
+        self.sslrootcert = self
+            .sslrootcert
+            .take()
+            .or_else(|| get_option("sslrootcert"));
+        self.sslcert = self.sslcert.take().or_else(|| get_option("sslcert"));
+        self.sslkey = self.sslkey.take().or_else(|| get_option("sslkey"));
+        Ok(())
+    }
+}
+
+/// Sets a field the configuration left empty.
+fn fill(field: &mut String, value: Option<String>) {
+    if field.is_empty()
+        && let Some(value) = value
+    {
+        *field = value;
+    }
+}
+
+fn resolve_password(
+    section: &str,
+    password: &mut String,
+    password_file: &Option<String>,
+    password_prompt: bool,
+) -> Result<()> {
+    let sources = [
+        !password.is_empty(),
+        password_file.is_some(),
+        password_prompt,
+    ];
+    if sources.iter().filter(|s| **s).count() > 1 {
+        return Err(config_error(
+            section,
+            if password_prompt {
+                "password_prompt"
+            } else {
+                "password_file"
+            },
+            "use only one of password, password_file and password_prompt",
+        ));
+    }
+    if let Some(password_file) = password_file {
+        *password = read_password_file(password_file)
+            .map_err(|e| config_error(section, "password_file", format!("{:#}", e)))?;
+    }
+    Ok(())
+}
+
+fn require_fields(section: &str, fields: &[(&str, &String)]) -> Result<()> {
+    for (name, value) in fields {
+        if value.trim().is_empty() {
+            return Err(config_error(section, name, "is not set"));
+        }
+    }
+    Ok(())
+}
+
+fn require_file(section: &str, key: &str, path: &Option<String>) -> Result<()> {
+    match path {
+        Some(path) if !Path::new(path).is_file() => Err(config_error(
+            section,
+            key,
+            format!("file not found: {}", path),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// A config value that is well-formed but unusable, located in the file by `read_config`.
+#[derive(Debug)]
+pub struct ConfigError {
+    section: String,
+    key: String,
+    message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.section, self.key, self.message)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+pub fn config_error(section: &str, key: &str, message: impl Into<String>) -> anyhow::Error {
+    ConfigError {
+        section: section.to_string(),
+        key: key.to_string(),
+        message: message.into(),
+    }
+    .into()
+}
+
+/// Line of `key` in `[section]`, or of the section header when the key is not written
+/// there, e.g. because it came from a URL. Dotted keys and inline tables are not followed.
+fn find_key_line(content: &str, section: &str, key: &str) -> Option<usize> {
+    let mut current_section = String::new();
+    let mut header_line = None;
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if let Some(header) = line.strip_prefix('[') {
+            current_section = header
+                .trim_end_matches(']')
+                .split('.')
+                .map(|part| part.trim().trim_matches('"'))
+                .collect::<Vec<_>>()
+                .join(".");
+            if current_section == section {
+                header_line = Some(number + 1);
+            }
+            continue;
+        }
+        if current_section == section
+            && let Some((name, _)) = line.split_once('=')
+            && name.trim().trim_matches('"') == key
+        {
+            return Some(number + 1);
+        }
+    }
+    header_line
+}
+
+/// Sections a profile overrides key by key instead of holding them as settings.
+const PROFILE_SECTIONS: [&str; 3] = ["source_database", "target_database", "session_settings"];
+
+/// Tables are merged recursively, anything else in `overlay` replaces what `base` has.
+fn merge_values(base: &mut toml::Value, overlay: toml::Value) {
+    match (base, overlay) {
+        (toml::Value::Table(base), toml::Value::Table(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_values(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
     }
 }
 
@@ -89,11 +711,196 @@ impl<'a> ConfigProvider<'a> {
         Self { config_file_name }
     }
 
-    pub async fn read_config(&self) -> Result<Config> {
+    /// With a profile, its database and session settings sections are merged key by key
+    /// into the top-level ones and its other keys are kept as the profile settings.
+    pub async fn read_config(&self, profile: Option<&str>) -> Result<Config> {
         let content = tokio::fs::read_to_string(self.config_file_name)
             .await
             .with_context(|| format!("Failed to read config file: {}", self.config_file_name))?;
-        let config: Config = toml::from_str(&content)?;
+        self.parse_config(content, profile)
+    }
+
+    /// `read_config` on a file already read.
+    pub fn parse_config(&self, content: String, profile: Option<&str>) -> Result<Config> {
+        // the whole file, every profile included, typed and with the line of any error
+        let checked: Config =
+            toml::from_str(&content).map_err(|e| anyhow!("{}: {}", self.config_file_name, e))?;
+        let settings = &checked.settings;
+        for (section, is_set) in [
+            ("source_database", settings.source_database.is_some()),
+            ("target_database", settings.target_database.is_some()),
+            ("session_settings", settings.session_settings.is_some()),
+        ] {
+            if is_set {
+                let e = config_error("settings", section, "only a profile can hold this section");
+                bail!(describe_error(self.config_file_name, &content, None, &e));
+            }
+        }
+        let describe = |e: anyhow::Error| {
+            anyhow!(describe_error(self.config_file_name, &content, profile, &e))
+        };
+        let mut table: toml::Table = toml::from_str(&content)?;
+        let profiles = match table.remove("profiles") {
+            Some(toml::Value::Table(profiles)) => profiles,
+            _ => toml::Table::new(),
+        };
+        let profile_settings = match profile {
+            Some(name) => {
+                let Some(toml::Value::Table(mut profile_table)) = profiles.get(name).cloned()
+                else {
+                    bail!(
+                        "Profile <{}> is not defined in {}, defined profiles: <{}>",
+                        name,
+                        self.config_file_name,
+                        profiles.keys().cloned().collect::<Vec<_>>().join(", ")
+                    );
+                };
+                for section in PROFILE_SECTIONS {
+                    if let Some(overlay) = profile_table.remove(section) {
+                        merge_values(
+                            table
+                                .entry(section)
+                                .or_insert_with(|| toml::Value::Table(toml::Table::new())),
+                            overlay,
+                        );
+                    }
+                }
+                let mut profile_settings = toml::Value::Table(profile_table);
+                interpolate_env_vars(&mut profile_settings, &format!("profiles.{}", name))
+                    .map_err(describe)?;
+                let profile_settings: FileSettings = profile_settings
+                    .try_into()
+                    .with_context(|| format!("Invalid [profiles.{}]", name))
+                    .map_err(describe)?;
+                Some((name.to_string(), profile_settings))
+            }
+            None => None,
+        };
+        let mut value = toml::Value::Table(table);
+        interpolate_env_vars(&mut value, "").map_err(describe)?;
+        let mut config: Config = value.try_into().map_err(|e| describe(e.into()))?;
+        config.profiles = checked.profiles;
+        config.profile = profile_settings;
+        config.file_name = self.config_file_name.to_string();
+        config.content = content.clone();
+        config.source_database.resolve().map_err(describe)?;
+        config.target_database.resolve().map_err(describe)?;
         Ok(config)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"[source_database]
+host = "mssql.example.com"
+database = "sales"
+user = "sa"
+password = "secret"
+
+[target_database]
+host = "pg.example.com"
+database = "sales"
+user = "postgres"
+password = "secret"
+
+[session_settings.tables."public.orders"]
+work_mem = "64MB"
+
+[profiles.night.target_database]
+"sslcert" = "client.crt"
+"#;
+
+    fn parse(content: &str, profile: Option<&str>) -> String {
+        match ConfigProvider::new("test.toml").parse_config(content.to_string(), profile) {
+            Ok(_) => panic!("{} parsed", content),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn find_key_line_finds_the_key_of_the_section() {
+        assert_eq!(find_key_line(CONFIG, "source_database", "host"), Some(2));
+        assert_eq!(find_key_line(CONFIG, "target_database", "host"), Some(8));
+        assert_eq!(
+            find_key_line(CONFIG, "session_settings.tables.public.orders", "work_mem"),
+            Some(14)
+        );
+        assert_eq!(
+            find_key_line(CONFIG, "profiles.night.target_database", "sslcert"),
+            Some(17)
+        );
+    }
+
+    #[test]
+    fn find_key_line_falls_back_to_the_section_header() {
+        assert_eq!(find_key_line(CONFIG, "target_database", "port"), Some(7));
+        assert_eq!(find_key_line(CONFIG, "settings", "threads"), None);
+    }
+
+    #[test]
+    fn describe_error_prefers_the_key_of_the_profile() {
+        let e = config_error("target_database", "sslcert", "needs sslkey");
+        assert_eq!(
+            describe_error("test.toml", CONFIG, Some("night"), &e),
+            "test.toml, line 17: [target_database] sslcert: needs sslkey"
+        );
+        assert_eq!(
+            describe_error("test.toml", CONFIG, None, &e),
+            "test.toml, line 7: [target_database] sslcert: needs sslkey"
+        );
+        let e = config_error("settings", "threads", "11 is not from 1 to 10");
+        assert_eq!(
+            describe_error("test.toml", CONFIG, None, &e),
+            "test.toml: [settings] threads: 11 is not from 1 to 10"
+        );
+        assert_eq!(
+            describe_error("test.toml", CONFIG, None, &anyhow!("other")),
+            "test.toml: other"
+        );
+    }
+
+    #[test]
+    fn parse_config_rejects_unknown_keys() {
+        let e = parse(&CONFIG.replacen("\ndatabase", "\ndatabse", 1), None);
+        assert!(e.contains("line 3"), "{}", e);
+        assert!(e.contains("unknown field `databse`"), "{}", e);
+        let e = parse(&format!("{}[settings]\nthread = 4\n", CONFIG), None);
+        assert!(e.contains("unknown field `thread`"), "{}", e);
+        let e = parse(&format!("{}[setting]\n", CONFIG), None);
+        assert!(e.contains("unknown field `setting`"), "{}", e);
+    }
+
+    #[test]
+    fn parse_config_locates_errors_of_the_profile() {
+        let e = parse(CONFIG, Some("night"));
+        assert_eq!(
+            e,
+            "test.toml, line 17: [target_database] sslcert: needs sslkey, the key of the client certificate"
+        );
+        let e = parse(CONFIG, Some("day"));
+        assert!(e.contains("defined profiles: <night>"), "{}", e);
+    }
+
+    #[test]
+    fn get_tablespaces_names_the_section_of_each_tablespace() {
+        let content = format!(
+            "{}default_tablespace = \"fast\"\n[session_settings]\ndefault_tablespace = \"\"\n",
+            CONFIG.replace(
+                "[profiles.night.target_database]\n\"sslcert\" = \"client.crt\"\n",
+                ""
+            )
+        );
+        let config = ConfigProvider::new("test.toml")
+            .parse_config(content, None)
+            .unwrap();
+        assert_eq!(
+            config.get_session_settings_as_ref().get_tablespaces(),
+            vec![(
+                "session_settings.tables.public.orders".to_string(),
+                "fast".to_string()
+            )]
+        );
+    }
+}
@@ -0,0 +1,898 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    Word(String),
+    QuotedIdentifier(String),
+    StringLiteral(String),
+    Symbol(char),
+}
+
+impl Token {
+    pub fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self, Token::Word(w) if w.eq_ignore_ascii_case(keyword))
+    }
+
+    pub fn as_identifier(&self) -> Option<&str> {
+        match self {
+            Token::Word(w) | Token::QuotedIdentifier(w) => Some(w),
+            _ => None,
+        }
+    }
+
+    /// The identifier the way PostgreSQL resolves it: unquoted names are folded to lower case.
+    fn as_folded_identifier(&self) -> Option<String> {
+        match self {
+            Token::Word(w) => Some(w.to_lowercase()),
+            Token::QuotedIdentifier(w) => Some(w.clone()),
+            _ => None,
+        }
+    }
+
+    fn as_quoted_identifier(&self) -> Option<String> {
+        self.as_folded_identifier().map(|w| quote_identifier(&w))
+    }
+}
+
+pub fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+pub fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Splits T-SQL and PostgreSQL text into tokens. Both `[bracketed]` and `"quoted"`
+/// identifiers are accepted, comments are dropped.
+pub fn tokenize(query: &str) -> Vec<Token> {
+    tokenize_with_offsets(query)
+        .into_iter()
+        .map(|(_, _, token)| token)
+        .collect()
+}
+
+/// Same as `tokenize`, every token comes with its start and end char position.
+fn tokenize_with_offsets(query: &str) -> Vec<(usize, usize, Token)> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        let token_count = tokens.len();
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '-' && chars.get(i + 1) == Some(&'-') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            i += 2;
+        } else if c == '[' || c == '"' {
+            let close = if c == '[' { ']' } else { '"' };
+            let (value, next) = read_delimited(&chars, i + 1, close);
+            tokens.push((start, 0, Token::QuotedIdentifier(value)));
+            i = next;
+        } else if c == '\'' {
+            let (value, next) = read_delimited(&chars, i + 1, '\'');
+            tokens.push((start, 0, Token::StringLiteral(value)));
+            i = next;
+        } else if (c == 'N' || c == 'n') && chars.get(i + 1) == Some(&'\'') {
+            let (value, next) = read_delimited(&chars, i + 2, '\'');
+            tokens.push((start, 0, Token::StringLiteral(value)));
+            i = next;
+        } else if is_word_char(c) {
+            while i < chars.len() && is_word_char(chars[i]) {
+                i += 1;
+            }
+            tokens.push((start, 0, Token::Word(chars[start..i].iter().collect())));
+        } else {
+            tokens.push((start, 0, Token::Symbol(c)));
+            i += 1;
+        }
+        if tokens.len() > token_count {
+            tokens.last_mut().unwrap().1 = i;
+        }
+    }
+    tokens
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '@' || c == '#' || c == '$'
+}
+
+/// Reads up to the closing delimiter, a doubled delimiter is an escaped one.
+fn read_delimited(chars: &[char], start: usize, close: char) -> (String, usize) {
+    let mut value = String::new();
+    let mut i = start;
+    while i < chars.len() {
+        if chars[i] == close {
+            if chars.get(i + 1) == Some(&close) {
+                value.push(close);
+                i += 2;
+                continue;
+            }
+            return (value, i + 1);
+        }
+        value.push(chars[i]);
+        i += 1;
+    }
+    (value, i)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QualifiedName {
+    pub schema: Option<String>,
+    pub name: String,
+    /// `"schema"."name"` as PostgreSQL resolves it
+    pub quoted: String,
+    pub quoted_schema: Option<String>,
+    /// name as PostgreSQL stores it in the catalog
+    pub folded_name: String,
+}
+
+impl fmt::Display for QualifiedName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.schema {
+            Some(schema) => write!(f, "{}.{}", schema, self.name),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StatementKind {
+    /// `CREATE MATERIALIZED VIEW`, built before the indexes on it
+    MaterializedView,
+    PrimaryKey,
+    UniqueConstraint,
+    UniqueIndex,
+    Index,
+    /// `ALTER TABLE ... ADD CONSTRAINT ... FOREIGN KEY`, built once the referenced key exists
+    ForeignKey,
+    /// `ALTER TABLE ... ADD CONSTRAINT ... CHECK`
+    CheckConstraint,
+    /// `ALTER TABLE ... ALTER COLUMN ... SET DEFAULT`
+    Default,
+    /// `CREATE STATISTICS`, extended statistics over several columns
+    Statistics,
+    /// `ANALYZE` of a table whose statements are all done
+    Analyze,
+    /// `DROP INDEX` or `DROP STATISTICS`, as found in rollback scripts
+    Drop,
+    /// `DROP MATERIALIZED VIEW`, which goes after the drops of the indexes on the view
+    DropMaterializedView,
+    /// `ALTER TABLE ... DROP CONSTRAINT`; a foreign key has to go before the key it points to
+    DropConstraint,
+    #[default]
+    Other,
+}
+
+impl StatementKind {
+    /// What a foreign key can point to.
+    pub fn is_key(&self) -> bool {
+        matches!(
+            self,
+            StatementKind::PrimaryKey
+                | StatementKind::UniqueConstraint
+                | StatementKind::UniqueIndex
+        )
+    }
+
+    /// Primary keys and unique constraints are built before secondary indexes.
+    pub fn get_priority(&self) -> u8 {
+        match self {
+            // a view has no primary key
+            StatementKind::MaterializedView | StatementKind::PrimaryKey => 0,
+            StatementKind::UniqueConstraint | StatementKind::UniqueIndex => 1,
+            StatementKind::Index => 2,
+            StatementKind::ForeignKey
+            | StatementKind::CheckConstraint
+            | StatementKind::Default
+            | StatementKind::Statistics
+            | StatementKind::Drop
+            | StatementKind::DropMaterializedView
+            | StatementKind::DropConstraint
+            | StatementKind::Other => 3,
+            StatementKind::Analyze => 4,
+        }
+    }
+}
+
+impl fmt::Display for StatementKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StatementKind::MaterializedView => write!(f, "materialized view"),
+            StatementKind::PrimaryKey => write!(f, "primary key"),
+            StatementKind::UniqueConstraint => write!(f, "unique constraint"),
+            StatementKind::UniqueIndex => write!(f, "unique index"),
+            StatementKind::Index => write!(f, "index"),
+            StatementKind::ForeignKey => write!(f, "foreign key"),
+            StatementKind::CheckConstraint => write!(f, "check constraint"),
+            StatementKind::Default => write!(f, "default"),
+            StatementKind::Statistics => write!(f, "statistics"),
+            StatementKind::Analyze => write!(f, "analyze"),
+            StatementKind::Drop => write!(f, "drop"),
+            StatementKind::DropMaterializedView => write!(f, "drop materialized view"),
+            StatementKind::DropConstraint => write!(f, "drop constraint"),
+            StatementKind::Other => write!(f, "other"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ParsedStatement {
+    pub kind: StatementKind,
+    /// index, constraint or statistics name
+    pub name: Option<String>,
+    /// `"schema"."name"` of a created index, in the schema of its table
+    pub quoted_index_name: Option<String>,
+    pub table: Option<QualifiedName>,
+    /// table a foreign key points to
+    pub referenced_table: Option<QualifiedName>,
+    /// `IF NOT EXISTS` statements succeed without creating anything when the object exists;
+    /// this query returns whether it does. For `SET DEFAULT` it returns whether the column
+    /// had a default, which dropping the new one would not bring back
+    pub existence_query: Option<String>,
+    /// returns whether the foreign key or check constraint added by the first statement
+    /// exists, so a retry after a failed `VALIDATE CONSTRAINT` does not add it again
+    pub constraint_query: Option<String>,
+    /// statement that drops exactly the object this one creates
+    pub rollback: Option<String>,
+    /// `CREATE INDEX CONCURRENTLY` or `DROP INDEX CONCURRENTLY`, which cannot run in a transaction
+    pub concurrently: bool,
+}
+
+pub fn parse_statement(query: &str) -> ParsedStatement {
+    let tokens = tokenize(query);
+    let keyword = |i: usize, keyword: &str| tokens.get(i).is_some_and(|t| t.is_keyword(keyword));
+    let parsed_statement = if keyword(0, "CREATE") && keyword(1, "STATISTICS") {
+        parse_create_statistics(&tokens[2..])
+    } else if keyword(0, "CREATE") && keyword(1, "MATERIALIZED") && keyword(2, "VIEW") {
+        parse_create_materialized_view(&tokens[3..])
+    } else if keyword(0, "CREATE") {
+        parse_create_index(&tokens[1..])
+    } else if keyword(0, "ALTER") && keyword(1, "TABLE") {
+        parse_alter_table(&tokens[2..])
+    } else if keyword(0, "DROP") && keyword(1, "INDEX") {
+        parse_drop_index(&tokens[2..])
+    } else if keyword(0, "DROP") && keyword(1, "STATISTICS") {
+        parse_drop(&tokens[2..], StatementKind::Drop)
+    } else if keyword(0, "DROP") && keyword(1, "MATERIALIZED") && keyword(2, "VIEW") {
+        parse_drop(&tokens[3..], StatementKind::DropMaterializedView)
+    } else if keyword(0, "ANALYZE") {
+        parse_qualified_name(&tokens, 1).map(|(table, _)| ParsedStatement {
+            kind: StatementKind::Analyze,
+            table: Some(table),
+            ..Default::default()
+        })
+    } else {
+        None
+    };
+    parsed_statement.unwrap_or_default()
+}
+
+/// `[UNIQUE] [CLUSTERED | NONCLUSTERED] INDEX [CONCURRENTLY] [IF NOT EXISTS] [name] ON [ONLY] table`
+fn parse_create_index(tokens: &[Token]) -> Option<ParsedStatement> {
+    let mut i = 0;
+    let mut unique = false;
+    while i < tokens.len() && !tokens[i].is_keyword("INDEX") {
+        if tokens[i].is_keyword("UNIQUE") {
+            unique = true;
+        } else if !tokens[i].is_keyword("CLUSTERED") && !tokens[i].is_keyword("NONCLUSTERED") {
+            return None;
+        }
+        i += 1;
+    }
+    i += 1;
+    let concurrently = tokens.get(i).is_some_and(|t| t.is_keyword("CONCURRENTLY"));
+    i = skip_keywords(tokens, i, &["CONCURRENTLY"]);
+    let after_if_not_exists = skip_keywords(tokens, i, &["IF", "NOT", "EXISTS"]);
+    let if_not_exists = after_if_not_exists != i;
+    i = after_if_not_exists;
+    let mut name = None;
+    let mut quoted_name = None;
+    if !tokens.get(i)?.is_keyword("ON") {
+        name = Some(tokens.get(i)?.as_identifier()?.to_string());
+        quoted_name = tokens.get(i)?.as_quoted_identifier();
+        i += 1;
+    }
+    if !tokens.get(i)?.is_keyword("ON") {
+        return None;
+    }
+    i = skip_keywords(tokens, i + 1, &["ONLY"]);
+    let (table, _) = parse_qualified_name(tokens, i)?;
+    // an index always lives in the schema of its table
+    let quoted_index_name = quoted_name.map(|quoted_name| match &table.quoted_schema {
+        Some(quoted_schema) => format!("{}.{}", quoted_schema, quoted_name),
+        None => quoted_name,
+    });
+    // an INVALID index left by a failed concurrent build does not count as existing
+    let existence_query = quoted_index_name
+        .as_ref()
+        .filter(|_| if_not_exists)
+        .map(|index_name| {
+            format!(
+                "SELECT EXISTS (SELECT 1 FROM pg_index WHERE indexrelid = to_regclass({}) AND indisvalid)",
+                quote_literal(index_name)
+            )
+        });
+    let rollback = quoted_index_name
+        .as_ref()
+        .map(|index_name| format!("DROP INDEX CONCURRENTLY IF EXISTS {};", index_name));
+    Some(ParsedStatement {
+        kind: if unique {
+            StatementKind::UniqueIndex
+        } else {
+            StatementKind::Index
+        },
+        name,
+        quoted_index_name,
+        table: Some(table),
+        existence_query,
+        rollback,
+        concurrently,
+        ..Default::default()
+    })
+}
+
+/// `[IF NOT EXISTS] name [(column, ...)] AS query`; the view is the table of its indexes.
+fn parse_create_materialized_view(tokens: &[Token]) -> Option<ParsedStatement> {
+    let i = skip_keywords(tokens, 0, &["IF", "NOT", "EXISTS"]);
+    let if_not_exists = i != 0;
+    let (view_name, _) = parse_qualified_name(tokens, i)?;
+    let existence_query = if_not_exists.then(|| {
+        format!(
+            "SELECT to_regclass({}) IS NOT NULL",
+            quote_literal(&view_name.quoted)
+        )
+    });
+    let rollback = format!("DROP MATERIALIZED VIEW IF EXISTS {};", view_name.quoted);
+    Some(ParsedStatement {
+        kind: StatementKind::MaterializedView,
+        name: Some(view_name.name.clone()),
+        table: Some(view_name),
+        existence_query,
+        rollback: Some(rollback),
+        ..Default::default()
+    })
+}
+
+/// `[IF NOT EXISTS] name [(kind, ...)] ON column, ... FROM table`
+fn parse_create_statistics(tokens: &[Token]) -> Option<ParsedStatement> {
+    let i = skip_keywords(tokens, 0, &["IF", "NOT", "EXISTS"]);
+    let if_not_exists = i != 0;
+    let (statistics_name, _) = parse_qualified_name(tokens, i)?;
+    let from = tokens.iter().position(|t| t.is_keyword("FROM"))?;
+    let (table, _) = parse_qualified_name(tokens, from + 1)?;
+    // without a schema the statistics object goes to the current schema, not the table's
+    let namespace = statistics_name
+        .quoted_schema
+        .as_deref()
+        .map_or("current_schema()::regnamespace".to_string(), |schema| {
+            format!("{}::regnamespace", quote_literal(schema))
+        });
+    let existence_query = if_not_exists.then(|| {
+        format!(
+            "SELECT EXISTS (SELECT 1 FROM pg_statistic_ext WHERE stxname = {} AND stxnamespace = {})",
+            quote_literal(&statistics_name.folded_name),
+            namespace
+        )
+    });
+    Some(ParsedStatement {
+        kind: StatementKind::Statistics,
+        rollback: Some(format!(
+            "DROP STATISTICS IF EXISTS {};",
+            statistics_name.quoted
+        )),
+        name: Some(statistics_name.name),
+        table: Some(table),
+        existence_query,
+        ..Default::default()
+    })
+}
+
+/// `[IF EXISTS] [ONLY] table ADD CONSTRAINT name PRIMARY KEY | UNIQUE | FOREIGN KEY | CHECK ...`
+/// or `table ALTER [COLUMN] column SET DEFAULT ...`
+fn parse_alter_table(tokens: &[Token]) -> Option<ParsedStatement> {
+    let mut i = skip_keywords(tokens, 0, &["IF", "EXISTS"]);
+    i = skip_keywords(tokens, i, &["ONLY"]);
+    let (table, next) = parse_qualified_name(tokens, i)?;
+    i = next;
+    let mut name = None;
+    let mut kind = StatementKind::Other;
+    let mut rollback = None;
+    let mut referenced_table = None;
+    let mut constraint_query = None;
+    let mut existence_query = None;
+    if tokens.get(i)?.is_keyword("ADD") {
+        i += 1;
+        if tokens.get(i)?.is_keyword("CONSTRAINT") {
+            name = Some(tokens.get(i + 1)?.as_identifier()?.to_string());
+            constraint_query = Some(format!(
+                "SELECT EXISTS (SELECT 1 FROM pg_constraint WHERE conrelid = to_regclass({}) AND conname = {})",
+                quote_literal(&table.quoted),
+                quote_literal(&tokens.get(i + 1)?.as_folded_identifier()?)
+            ));
+            rollback = Some(format!(
+                "ALTER TABLE IF EXISTS {} DROP CONSTRAINT IF EXISTS {};",
+                table.quoted,
+                tokens.get(i + 1)?.as_quoted_identifier()?
+            ));
+            i += 2;
+        }
+        kind = match tokens.get(i) {
+            Some(t) if t.is_keyword("PRIMARY") => StatementKind::PrimaryKey,
+            Some(t) if t.is_keyword("UNIQUE") => StatementKind::UniqueConstraint,
+            Some(t) if t.is_keyword("FOREIGN") => StatementKind::ForeignKey,
+            Some(t) if t.is_keyword("CHECK") => StatementKind::CheckConstraint,
+            _ => StatementKind::Other,
+        };
+        if kind == StatementKind::ForeignKey {
+            let references = i + tokens[i..]
+                .iter()
+                .position(|t| t.is_keyword("REFERENCES"))?;
+            referenced_table = Some(parse_qualified_name(tokens, references + 1)?.0);
+        }
+        if kind == StatementKind::Other {
+            rollback = None;
+        }
+        if !matches!(
+            kind,
+            StatementKind::ForeignKey | StatementKind::CheckConstraint
+        ) {
+            constraint_query = None;
+        }
+    } else if tokens.get(i)?.is_keyword("DROP") && tokens.get(i + 1)?.is_keyword("CONSTRAINT") {
+        i = skip_keywords(tokens, i + 2, &["IF", "EXISTS"]);
+        name = Some(tokens.get(i)?.as_identifier()?.to_string());
+        kind = StatementKind::DropConstraint;
+    } else if tokens.get(i)?.is_keyword("ALTER") {
+        i = skip_keywords(tokens, i + 1, &["COLUMN"]);
+        let column = tokens.get(i)?.as_quoted_identifier()?;
+        if tokens.get(i + 1)?.is_keyword("SET") && tokens.get(i + 2)?.is_keyword("DEFAULT") {
+            name = Some(tokens.get(i)?.as_identifier()?.to_string());
+            kind = StatementKind::Default;
+            existence_query = Some(format!(
+                "SELECT EXISTS (SELECT 1 FROM pg_attrdef d JOIN pg_attribute a ON a.attrelid = d.adrelid AND a.attnum = d.adnum WHERE d.adrelid = to_regclass({}) AND a.attname = {})",
+                quote_literal(&table.quoted),
+                quote_literal(&tokens.get(i)?.as_folded_identifier()?)
+            ));
+            rollback = Some(format!(
+                "ALTER TABLE IF EXISTS {} ALTER COLUMN {} DROP DEFAULT;",
+                table.quoted, column
+            ));
+        }
+    }
+    Some(ParsedStatement {
+        kind,
+        name,
+        table: Some(table),
+        referenced_table,
+        existence_query,
+        constraint_query,
+        rollback,
+        ..Default::default()
+    })
+}
+
+/// `[CONCURRENTLY] [IF EXISTS] name`
+fn parse_drop_index(tokens: &[Token]) -> Option<ParsedStatement> {
+    let concurrently = tokens.first().is_some_and(|t| t.is_keyword("CONCURRENTLY"));
+    let mut i = skip_keywords(tokens, 0, &["CONCURRENTLY"]);
+    i = skip_keywords(tokens, i, &["IF", "EXISTS"]);
+    let (index_name, _) = parse_qualified_name(tokens, i)?;
+    Some(ParsedStatement {
+        kind: StatementKind::Drop,
+        name: Some(index_name.name),
+        concurrently,
+        ..Default::default()
+    })
+}
+
+/// `[IF EXISTS] name` of `DROP STATISTICS` or `DROP MATERIALIZED VIEW`
+fn parse_drop(tokens: &[Token], kind: StatementKind) -> Option<ParsedStatement> {
+    let i = skip_keywords(tokens, 0, &["IF", "EXISTS"]);
+    let (object_name, _) = parse_qualified_name(tokens, i)?;
+    Some(ParsedStatement {
+        kind,
+        name: Some(object_name.name),
+        ..Default::default()
+    })
+}
+
+/// Turns `CREATE [UNIQUE] INDEX` into `CREATE [UNIQUE] INDEX CONCURRENTLY`.
+/// Returns `None` for anything that is not a plain `CREATE INDEX`.
+pub fn make_concurrent(query: &str) -> Option<String> {
+    let tokens = tokenize_with_offsets(query);
+    if !tokens.first()?.2.is_keyword("CREATE") {
+        return None;
+    }
+    let index_position = tokens
+        .iter()
+        .take(3)
+        .position(|(_, _, t)| t.is_keyword("INDEX"))?;
+    if !tokens[1..index_position]
+        .iter()
+        .all(|(_, _, t)| t.is_keyword("UNIQUE"))
+    {
+        return None;
+    }
+    if tokens
+        .get(index_position + 1)
+        .is_some_and(|(_, _, t)| t.is_keyword("CONCURRENTLY"))
+    {
+        return Some(query.to_string());
+    }
+    let (_, index_end, _) = tokens[index_position];
+    let split_at = query
+        .char_indices()
+        .nth(index_end)
+        .map_or(query.len(), |(byte, _)| byte);
+    Some(format!(
+        "{} CONCURRENTLY{}",
+        &query[..split_at],
+        &query[split_at..]
+    ))
+}
+
+pub fn skip_keywords(tokens: &[Token], start: usize, keywords: &[&str]) -> usize {
+    let matches = keywords.iter().enumerate().all(|(offset, keyword)| {
+        tokens
+            .get(start + offset)
+            .is_some_and(|t| t.is_keyword(keyword))
+    });
+    if matches {
+        start + keywords.len()
+    } else {
+        start
+    }
+}
+
+pub fn parse_qualified_name(tokens: &[Token], start: usize) -> Option<(QualifiedName, usize)> {
+    let first = tokens.get(start)?.as_identifier()?.to_string();
+    let first_quoted = tokens.get(start)?.as_quoted_identifier()?;
+    if tokens.get(start + 1) == Some(&Token::Symbol('.')) {
+        let second = tokens.get(start + 2)?.as_identifier()?.to_string();
+        let second_quoted = tokens.get(start + 2)?.as_quoted_identifier()?;
+        return Some((
+            QualifiedName {
+                schema: Some(first),
+                name: second,
+                quoted: format!("{}.{}", first_quoted, second_quoted),
+                quoted_schema: Some(first_quoted),
+                folded_name: tokens.get(start + 2)?.as_folded_identifier()?,
+            },
+            start + 3,
+        ));
+    }
+    Some((
+        QualifiedName {
+            schema: None,
+            name: first,
+            quoted: first_quoted,
+            quoted_schema: None,
+            folded_name: tokens.get(start)?.as_folded_identifier()?,
+        },
+        start + 1,
+    ))
+}
+
+/// Splits a script into statements on `;` outside of quotes and comments.
+/// Returns the line each statement starts on with the statement text.
+pub fn split_statements(script: &str) -> Vec<(usize, String)> {
+    let chars: Vec<char> = script.chars().collect();
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    let mut line = 1;
+    let mut start_line = None;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let end = if c == '-' && chars.get(i + 1) == Some(&'-') {
+            chars[i..]
+                .iter()
+                .position(|&c| c == '\n')
+                .map_or(chars.len(), |p| i + p)
+        } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+            (i + 2..chars.len())
+                .find(|&j| chars[j] == '*' && chars.get(j + 1) == Some(&'/'))
+                .map_or(chars.len(), |j| j + 2)
+        } else if c == '\'' || c == '"' || c == '[' {
+            let close = if c == '[' { ']' } else { c };
+            let (_, next) = read_delimited(&chars, i + 1, close);
+            start_line.get_or_insert(line);
+            next
+        } else if c == ';' {
+            if let Some(start_line) = start_line.take() {
+                current.push(';');
+                statements.push((start_line, current.trim().to_string()));
+            }
+            current.clear();
+            i += 1;
+            continue;
+        } else {
+            if !c.is_whitespace() {
+                start_line.get_or_insert(line);
+            }
+            i + 1
+        };
+        // comments before a statement are not part of it
+        if start_line.is_some() {
+            current.extend(&chars[i..end]);
+        }
+        line += chars[i..end].iter().filter(|&&c| c == '\n').count();
+        i = end;
+    }
+    if let Some(start_line) = start_line {
+        statements.push((start_line, current.trim().to_string()));
+    }
+    statements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenize_reads_quoted_identifiers_and_literals() {
+        assert_eq!(
+            tokenize("[My ]]Table] \"a\"\"b\" N'it''s' 'x' -- comment\n/* block */ ;"),
+            vec![
+                Token::QuotedIdentifier("My ]Table".to_string()),
+                Token::QuotedIdentifier("a\"b".to_string()),
+                Token::StringLiteral("it's".to_string()),
+                Token::StringLiteral("x".to_string()),
+                Token::Symbol(';'),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_keeps_t_sql_word_characters() {
+        assert_eq!(
+            tokenize("@var #temp a$b"),
+            vec![
+                Token::Word("@var".to_string()),
+                Token::Word("#temp".to_string()),
+                Token::Word("a$b".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn split_statements_ignores_semicolons_in_quotes_and_comments() {
+        let script = "-- header; comment\nCREATE INDEX a ON t (x) WHERE y = ';';\n\n/* ; */ DROP INDEX \"b;c\";\nANALYZE t";
+        assert_eq!(
+            split_statements(script),
+            vec![
+                (2, "CREATE INDEX a ON t (x) WHERE y = ';';".to_string()),
+                (4, "DROP INDEX \"b;c\";".to_string()),
+                (5, "ANALYZE t".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn split_statements_skips_empty_statements() {
+        assert_eq!(
+            split_statements(";;\n  ;\nANALYZE t;"),
+            vec![(3, "ANALYZE t;".to_string())]
+        );
+    }
+
+    #[test]
+    fn parse_create_index() {
+        let parsed = parse_statement("CREATE INDEX ix_a ON dbo.t (a);");
+        assert_eq!(parsed.kind, StatementKind::Index);
+        assert_eq!(parsed.name.as_deref(), Some("ix_a"));
+        assert_eq!(parsed.table.unwrap().to_string(), "dbo.t");
+        assert_eq!(parsed.existence_query, None);
+        assert_eq!(
+            parsed.quoted_index_name.as_deref(),
+            Some("\"dbo\".\"ix_a\"")
+        );
+        assert_eq!(
+            parsed.rollback.as_deref(),
+            Some("DROP INDEX CONCURRENTLY IF EXISTS \"dbo\".\"ix_a\";")
+        );
+        assert!(!parsed.concurrently);
+    }
+
+    #[test]
+    fn parse_create_unique_index_concurrently_if_not_exists() {
+        let parsed = parse_statement(
+            "CREATE UNIQUE INDEX CONCURRENTLY IF NOT EXISTS \"Ix_A\" ON ONLY \"Sales\".Orders (a);",
+        );
+        assert_eq!(parsed.kind, StatementKind::UniqueIndex);
+        assert!(parsed.concurrently);
+        let table = parsed.table.unwrap();
+        assert_eq!(table.quoted, "\"Sales\".\"orders\"");
+        assert_eq!(table.folded_name, "orders");
+        assert_eq!(
+            parsed.existence_query.as_deref(),
+            Some(
+                "SELECT EXISTS (SELECT 1 FROM pg_index WHERE indexrelid = to_regclass('\"Sales\".\"Ix_A\"') AND indisvalid)"
+            )
+        );
+    }
+
+    #[test]
+    fn parse_t_sql_clustered_index() {
+        let parsed = parse_statement("CREATE UNIQUE NONCLUSTERED INDEX [IX b] ON [dbo].[T] ([b])");
+        assert_eq!(parsed.kind, StatementKind::UniqueIndex);
+        assert_eq!(parsed.name.as_deref(), Some("IX b"));
+        assert_eq!(parsed.table.unwrap().to_string(), "dbo.T");
+    }
+
+    #[test]
+    fn parse_create_index_without_name() {
+        let parsed = parse_statement("CREATE INDEX ON t (a);");
+        assert_eq!(parsed.kind, StatementKind::Index);
+        assert_eq!(parsed.name, None);
+        assert_eq!(parsed.rollback, None);
+    }
+
+    #[test]
+    fn parse_add_constraints() {
+        let primary_key =
+            parse_statement("ALTER TABLE dbo.t ADD CONSTRAINT pk_t PRIMARY KEY (id);");
+        assert_eq!(primary_key.kind, StatementKind::PrimaryKey);
+        assert_eq!(
+            primary_key.rollback.as_deref(),
+            Some("ALTER TABLE IF EXISTS \"dbo\".\"t\" DROP CONSTRAINT IF EXISTS \"pk_t\";")
+        );
+        let unique = parse_statement("ALTER TABLE IF EXISTS ONLY t ADD CONSTRAINT uq UNIQUE (a);");
+        assert_eq!(unique.kind, StatementKind::UniqueConstraint);
+        assert_eq!(unique.constraint_query, None);
+        let check = parse_statement("ALTER TABLE t ADD CONSTRAINT ck CHECK (a > 0);");
+        assert_eq!(check.kind, StatementKind::CheckConstraint);
+        assert_eq!(check.name.as_deref(), Some("ck"));
+        assert_eq!(
+            check.constraint_query.as_deref(),
+            Some(
+                "SELECT EXISTS (SELECT 1 FROM pg_constraint WHERE conrelid = to_regclass('\"t\"') AND conname = 'ck')"
+            )
+        );
+    }
+
+    #[test]
+    fn parse_foreign_key() {
+        let parsed = parse_statement(
+            "ALTER TABLE dbo.orders ADD CONSTRAINT \"FK_Customer\" FOREIGN KEY (customer_id) REFERENCES dbo.Customers (id) NOT VALID;\nALTER TABLE dbo.orders VALIDATE CONSTRAINT \"FK_Customer\";",
+        );
+        assert_eq!(parsed.kind, StatementKind::ForeignKey);
+        assert_eq!(parsed.table.unwrap().to_string(), "dbo.orders");
+        assert_eq!(
+            parsed.referenced_table.unwrap().to_string(),
+            "dbo.Customers"
+        );
+        assert_eq!(
+            parsed.constraint_query.as_deref(),
+            Some(
+                "SELECT EXISTS (SELECT 1 FROM pg_constraint WHERE conrelid = to_regclass('\"dbo\".\"orders\"') AND conname = 'FK_Customer')"
+            )
+        );
+    }
+
+    #[test]
+    fn parse_set_default() {
+        let parsed = parse_statement("ALTER TABLE t ALTER COLUMN \"A\" SET DEFAULT 0;");
+        assert_eq!(parsed.kind, StatementKind::Default);
+        assert_eq!(parsed.name.as_deref(), Some("A"));
+        assert_eq!(
+            parsed.rollback.as_deref(),
+            Some("ALTER TABLE IF EXISTS \"t\" ALTER COLUMN \"A\" DROP DEFAULT;")
+        );
+        assert_eq!(
+            parsed.existence_query.as_deref(),
+            Some(
+                "SELECT EXISTS (SELECT 1 FROM pg_attrdef d JOIN pg_attribute a ON a.attrelid = d.adrelid AND a.attnum = d.adnum WHERE d.adrelid = to_regclass('\"t\"') AND a.attname = 'A')"
+            )
+        );
+    }
+
+    #[test]
+    fn parse_other_alter_table_has_no_rollback() {
+        let parsed = parse_statement("ALTER TABLE t ADD COLUMN a int;");
+        assert_eq!(parsed.kind, StatementKind::Other);
+        assert_eq!(parsed.rollback, None);
+    }
+
+    #[test]
+    fn parse_drops() {
+        let drop_index = parse_statement("DROP INDEX CONCURRENTLY IF EXISTS \"dbo\".\"ix_a\";");
+        assert_eq!(drop_index.kind, StatementKind::Drop);
+        assert_eq!(drop_index.name.as_deref(), Some("ix_a"));
+        assert!(drop_index.concurrently);
+        let drop_constraint =
+            parse_statement("ALTER TABLE IF EXISTS t DROP CONSTRAINT IF EXISTS fk_a;");
+        assert_eq!(drop_constraint.kind, StatementKind::DropConstraint);
+        assert_eq!(drop_constraint.name.as_deref(), Some("fk_a"));
+        let drop_statistics = parse_statement("DROP STATISTICS IF EXISTS s.st;");
+        assert_eq!(drop_statistics.kind, StatementKind::Drop);
+        let drop_view = parse_statement("DROP MATERIALIZED VIEW IF EXISTS s.v;");
+        assert_eq!(drop_view.kind, StatementKind::DropMaterializedView);
+        assert_eq!(drop_view.name.as_deref(), Some("v"));
+    }
+
+    #[test]
+    fn parse_create_statistics() {
+        let parsed =
+            parse_statement("CREATE STATISTICS IF NOT EXISTS St_A (ndistinct) ON a, b FROM dbo.t;");
+        assert_eq!(parsed.kind, StatementKind::Statistics);
+        assert_eq!(parsed.table.unwrap().to_string(), "dbo.t");
+        assert_eq!(
+            parsed.existence_query.as_deref(),
+            Some(
+                "SELECT EXISTS (SELECT 1 FROM pg_statistic_ext WHERE stxname = 'st_a' AND stxnamespace = current_schema()::regnamespace)"
+            )
+        );
+        assert_eq!(
+            parsed.rollback.as_deref(),
+            Some("DROP STATISTICS IF EXISTS \"st_a\";")
+        );
+    }
+
+    #[test]
+    fn parse_create_materialized_view() {
+        let parsed =
+            parse_statement("CREATE MATERIALIZED VIEW IF NOT EXISTS s.v AS SELECT 1 FROM t;");
+        assert_eq!(parsed.kind, StatementKind::MaterializedView);
+        assert_eq!(parsed.table.unwrap().quoted, "\"s\".\"v\"");
+        assert_eq!(
+            parsed.existence_query.as_deref(),
+            Some("SELECT to_regclass('\"s\".\"v\"') IS NOT NULL")
+        );
+        assert_eq!(
+            parsed.rollback.as_deref(),
+            Some("DROP MATERIALIZED VIEW IF EXISTS \"s\".\"v\";")
+        );
+    }
+
+    #[test]
+    fn parse_analyze_and_unknown() {
+        let analyze = parse_statement("ANALYZE \"s\".\"t\";");
+        assert_eq!(analyze.kind, StatementKind::Analyze);
+        assert_eq!(analyze.table.unwrap().quoted, "\"s\".\"t\"");
+        assert_eq!(parse_statement("SELECT 1;").kind, StatementKind::Other);
+        assert_eq!(parse_statement("").kind, StatementKind::Other);
+    }
+
+    #[test]
+    fn make_concurrent_adds_concurrently_to_plain_indexes() {
+        assert_eq!(
+            make_concurrent("CREATE INDEX ix ON t (a);").as_deref(),
+            Some("CREATE INDEX CONCURRENTLY ix ON t (a);")
+        );
+        assert_eq!(
+            make_concurrent("create unique index \"ä\" ON t (a);").as_deref(),
+            Some("create unique index CONCURRENTLY \"ä\" ON t (a);")
+        );
+        assert_eq!(
+            make_concurrent("CREATE INDEX CONCURRENTLY ix ON t (a);").as_deref(),
+            Some("CREATE INDEX CONCURRENTLY ix ON t (a);")
+        );
+    }
+
+    #[test]
+    fn make_concurrent_leaves_other_statements_alone() {
+        assert_eq!(
+            make_concurrent("ALTER TABLE t ADD CONSTRAINT pk PRIMARY KEY (a);"),
+            None
+        );
+        assert_eq!(make_concurrent("CREATE STATISTICS s ON a, b FROM t;"), None);
+        assert_eq!(make_concurrent("CREATE CLUSTERED INDEX ix ON t (a);"), None);
+    }
+
+    #[test]
+    fn skip_keywords_only_skips_the_whole_sequence() {
+        let tokens = tokenize("IF NOT EXISTS x");
+        assert_eq!(skip_keywords(&tokens, 0, &["IF", "NOT", "EXISTS"]), 3);
+        assert_eq!(skip_keywords(&tokens, 0, &["IF", "EXISTS"]), 0);
+    }
+}
@@ -0,0 +1 @@
+pub mod ddl_parser;
@@ -0,0 +1,9 @@
+pub const EXIT_SUCCESS: i32 = 0;
+/// Some indexes failed, timed out, were cancelled or never started
+pub const EXIT_PARTIAL_FAILURE: i32 = 1;
+/// Bad arguments, configuration or input; same code clap uses for usage errors
+pub const EXIT_VALIDATION_FAILURE: i32 = 2;
+/// A database could not be reached or went away during the run
+pub const EXIT_CONNECTION_FAILURE: i32 = 3;
+/// Nothing succeeded: every index failed, timed out, was cancelled or never started
+pub const EXIT_FAILURE: i32 = 4;
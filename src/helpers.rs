@@ -1,21 +1,25 @@
+use crate::log_info;
+use crate::logger::logger::print_interactive;
 use crate::version::{
     COPYRIGHT, COPYRIGHT_YEARS, LICENSE, LINK, PRODUCT_NAME, VERSION_ALIAS, VERSION_MAJOR,
     VERSION_MINOR, VERSION_PATCH,
 };
 
 pub fn print_separator() {
-    println!("{}", "*".repeat(80));
+    print_interactive("*".repeat(80));
 }
 
 pub fn print_banner() {
-    println!(
-        "{}",
-        format!(
-            "{} version {}.{}.{} ({})",
-            PRODUCT_NAME, VERSION_MAJOR, VERSION_MINOR, VERSION_PATCH, VERSION_ALIAS
-        )
+    // the version also goes to the log file and JSON output
+    log_info!(
+        "{} version {}.{}.{} ({})",
+        PRODUCT_NAME,
+        VERSION_MAJOR,
+        VERSION_MINOR,
+        VERSION_PATCH,
+        VERSION_ALIAS
     );
-    println!("License: {}", LICENSE);
-    println!("Link: {}", LINK);
-    println!("Copyright © {}. {}", COPYRIGHT, COPYRIGHT_YEARS);
+    print_interactive(format!("License: {}", LICENSE));
+    print_interactive(format!("Link: {}", LINK));
+    print_interactive(format!("Copyright © {}. {}", COPYRIGHT, COPYRIGHT_YEARS));
 }
@@ -0,0 +1,453 @@
+use crate::ddl_parser::ddl_parser::StatementKind;
+use crate::exit_codes::{
+    EXIT_CONNECTION_FAILURE, EXIT_FAILURE, EXIT_PARTIAL_FAILURE, EXIT_SUCCESS,
+};
+use crate::importer::importer::IndexTask;
+use crate::retry_policy::retry_policy::{ErrorClass, TimeoutKind};
+use colored::Colorize;
+use std::fmt;
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+pub struct AttemptRecord {
+    pub attempt: u32,
+    pub elapsed: Duration,
+    pub error: Option<String>,
+    pub sqlstate: Option<String>,
+    pub error_class: Option<ErrorClass>,
+    pub timeout: Option<TimeoutKind>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportStatus {
+    Succeeded,
+    Failed,
+    TimedOut,
+    Cancelled,
+    /// never started because of a shutdown request
+    Skipped,
+    /// has no PostgreSQL equivalent, reported but not run
+    Unsupported,
+}
+
+impl fmt::Display for ImportStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImportStatus::Succeeded => write!(f, "succeeded"),
+            ImportStatus::Failed => write!(f, "failed"),
+            ImportStatus::TimedOut => write!(f, "timed out"),
+            ImportStatus::Cancelled => write!(f, "cancelled"),
+            ImportStatus::Skipped => write!(f, "skipped"),
+            ImportStatus::Unsupported => write!(f, "unsupported"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IndexImportResult {
+    pub file_name: String,
+    /// index name in the source database, the exported file name
+    pub source_name: String,
+    pub target_name: Option<String>,
+    pub schema_table: Option<String>,
+    pub kind: StatementKind,
+    pub statement: String,
+    pub attempts: Vec<AttemptRecord>,
+    pub cancelled: bool,
+    /// wall time including retry backoff
+    pub elapsed: Duration,
+    /// set only when the statement created its object
+    pub rollback: Option<String>,
+    /// position among finished tasks, the rollback script drops in reverse of it
+    pub finish_order: usize,
+    /// table key of the transaction the statement ran in, `--transactional per-table` only
+    pub transaction_group: Option<String>,
+    /// why the statement was not run
+    pub unsupported: Option<String>,
+}
+
+impl IndexImportResult {
+    pub fn new(index_task: &IndexTask) -> Self {
+        IndexImportResult {
+            file_name: index_task.file_name.clone(),
+            source_name: index_task.get_label(),
+            target_name: index_task.target_name.clone(),
+            schema_table: index_task.schema_table.clone(),
+            kind: index_task.kind,
+            statement: index_task.query.clone(),
+            attempts: Vec::new(),
+            cancelled: false,
+            elapsed: Duration::ZERO,
+            rollback: None,
+            finish_order: 0,
+            transaction_group: None,
+            unsupported: None,
+        }
+    }
+
+    /// Records an attempt that has no PostgreSQL error details, e.g. writing an exported file.
+    pub fn add_attempt(&mut self, elapsed: Duration, error: Option<String>) {
+        match error {
+            Some(message) => self.add_failed_attempt(elapsed, message, ErrorClass::Permanent),
+            None => self.push_attempt(elapsed, None, None),
+        }
+    }
+
+    /// Records a failed attempt whose error class is known, e.g. a SQL Server query.
+    pub fn add_failed_attempt(
+        &mut self,
+        elapsed: Duration,
+        message: String,
+        error_class: ErrorClass,
+    ) {
+        self.push_attempt(elapsed, Some(message), Some(error_class));
+    }
+
+    fn push_attempt(
+        &mut self,
+        elapsed: Duration,
+        error: Option<String>,
+        error_class: Option<ErrorClass>,
+    ) {
+        self.attempts.push(AttemptRecord {
+            attempt: self.attempts.len() as u32 + 1,
+            elapsed,
+            error,
+            sqlstate: None,
+            error_class,
+            timeout: None,
+        });
+        self.elapsed += elapsed;
+    }
+
+    pub fn get_status(&self) -> ImportStatus {
+        match self.attempts.last() {
+            None if self.unsupported.is_some() => ImportStatus::Unsupported,
+            None => ImportStatus::Skipped,
+            Some(_) if self.cancelled => ImportStatus::Cancelled,
+            Some(a) if a.error.is_none() => ImportStatus::Succeeded,
+            Some(a) if a.timeout.is_some() => ImportStatus::TimedOut,
+            Some(_) => ImportStatus::Failed,
+        }
+    }
+
+    pub fn get_retries(&self) -> u32 {
+        self.attempts.len().saturating_sub(1) as u32
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    results: Vec<IndexImportResult>,
+    elapsed: Duration,
+}
+
+impl ImportSummary {
+    pub fn new() -> Self {
+        ImportSummary::default()
+    }
+
+    pub fn add_results(&mut self, results: Vec<IndexImportResult>) {
+        self.results.extend(results);
+    }
+
+    pub fn set_elapsed(&mut self, elapsed: Duration) {
+        self.elapsed = elapsed;
+    }
+
+    /// Unsupported statements are reported, but do not fail the run.
+    pub fn is_success(&self) -> bool {
+        self.results.iter().all(|r| {
+            matches!(
+                r.get_status(),
+                ImportStatus::Succeeded | ImportStatus::Unsupported
+            )
+        })
+    }
+
+    /// A connection error wins over a partial failure, the rest of the run may have
+    /// failed for the same reason; `EXIT_FAILURE` only when nothing succeeded at all.
+    pub fn get_exit_code(&self) -> i32 {
+        if self.is_success() {
+            return EXIT_SUCCESS;
+        }
+        let connection_lost = self.results.iter().any(|r| {
+            r.get_status() != ImportStatus::Succeeded
+                && r.attempts
+                    .last()
+                    .is_some_and(|a| a.error_class == Some(ErrorClass::Connection))
+        });
+        if connection_lost {
+            EXIT_CONNECTION_FAILURE
+        } else if self.get_count(ImportStatus::Succeeded) == 0 {
+            EXIT_FAILURE
+        } else {
+            EXIT_PARTIAL_FAILURE
+        }
+    }
+
+    pub fn get_results_as_ref(&self) -> &Vec<IndexImportResult> {
+        &self.results
+    }
+
+    pub fn into_results(self) -> Vec<IndexImportResult> {
+        self.results
+    }
+
+    /// Drop statements for everything this run created, last created first.
+    pub fn get_rollback_statements(&self) -> Vec<&str> {
+        let mut created: Vec<&IndexImportResult> = self
+            .results
+            .iter()
+            .filter(|r| r.rollback.is_some())
+            .collect();
+        created.sort_by_key(|r| std::cmp::Reverse(r.finish_order));
+        created
+            .iter()
+            .filter_map(|r| r.rollback.as_deref())
+            .collect()
+    }
+
+    /// Tables whose transaction was rolled back, `--transactional per-table` only.
+    pub fn get_failed_transaction_groups(&self) -> Vec<&str> {
+        let mut failed_groups: Vec<&str> = self
+            .results
+            .iter()
+            .filter(|r| r.get_status() != ImportStatus::Succeeded)
+            .filter_map(|r| r.transaction_group.as_deref())
+            .collect();
+        failed_groups.sort();
+        failed_groups.dedup();
+        failed_groups
+    }
+
+    pub fn get_elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn get_count(&self, status: ImportStatus) -> usize {
+        self.results
+            .iter()
+            .filter(|r| r.get_status() == status)
+            .count()
+    }
+}
+
+impl fmt::Display for ImportSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<12} {:>8} {:>12}  Index",
+            "Status", "Attempts", "Elapsed"
+        )?;
+        for result in &self.results {
+            let status = result.get_status();
+            let status_text = format!("{:<12}", status.to_string());
+            let status_text = match status {
+                ImportStatus::Succeeded => status_text.green(),
+                ImportStatus::Skipped | ImportStatus::Unsupported => status_text.yellow(),
+                _ => status_text.red(),
+            };
+            writeln!(
+                f,
+                "{} {:>8} {:>12}  {}",
+                status_text,
+                result.attempts.len(),
+                format!("{:.2?}", result.elapsed),
+                result.file_name
+            )?;
+        }
+        for result in self.results.iter().filter(|r| {
+            r.get_retries() > 0
+                || !matches!(
+                    r.get_status(),
+                    ImportStatus::Succeeded | ImportStatus::Skipped
+                )
+        }) {
+            if let Some(reason) = &result.unsupported {
+                writeln!(
+                    f,
+                    "Index: <{}> unsupported: {}",
+                    result.file_name.yellow(),
+                    reason
+                )?;
+                continue;
+            }
+            writeln!(
+                f,
+                "Index: <{}> {}",
+                result.file_name.yellow(),
+                result.get_status()
+            )?;
+            for attempt in &result.attempts {
+                match &attempt.error {
+                    None => writeln!(
+                        f,
+                        "  Attempt {}: {} ({:.2?})",
+                        attempt.attempt,
+                        "OK".green(),
+                        attempt.elapsed
+                    )?,
+                    Some(error) => writeln!(
+                        f,
+                        "  Attempt {}: {} [{}] {} ({:.2?})",
+                        attempt.attempt,
+                        attempt
+                            .timeout
+                            .map(|t| t.to_string())
+                            .or(attempt.error_class.map(|c| c.to_string()))
+                            .unwrap_or_default()
+                            .red(),
+                        attempt.sqlstate.as_deref().unwrap_or("-----"),
+                        error.red(),
+                        attempt.elapsed
+                    )?,
+                }
+            }
+        }
+        for failed_group in self.get_failed_transaction_groups() {
+            let statements = self
+                .results
+                .iter()
+                .filter(|r| r.transaction_group.as_deref() == Some(failed_group))
+                .count();
+            writeln!(
+                f,
+                "Table: <{}> rolled back, {} statements",
+                failed_group.yellow(),
+                statements
+            )?;
+        }
+        let retried = self.results.iter().filter(|r| r.get_retries() > 0).count();
+        writeln!(f, "Indexes: <{}>", self.results.len())?;
+        writeln!(
+            f,
+            "Succeeded: <{}>",
+            self.get_count(ImportStatus::Succeeded).to_string().green()
+        )?;
+        writeln!(
+            f,
+            "Failed: <{}>",
+            self.get_count(ImportStatus::Failed).to_string().red()
+        )?;
+        writeln!(
+            f,
+            "Timed out: <{}>",
+            self.get_count(ImportStatus::TimedOut).to_string().red()
+        )?;
+        writeln!(
+            f,
+            "Cancelled: <{}>",
+            self.get_count(ImportStatus::Cancelled).to_string().red()
+        )?;
+        writeln!(
+            f,
+            "Skipped: <{}>",
+            self.get_count(ImportStatus::Skipped).to_string().yellow()
+        )?;
+        writeln!(
+            f,
+            "Unsupported: <{}>",
+            self.get_count(ImportStatus::Unsupported)
+                .to_string()
+                .yellow()
+        )?;
+        writeln!(f, "Retried: <{}>", retried.to_string().yellow())?;
+        if self.results.iter().any(|r| r.transaction_group.is_some()) {
+            writeln!(
+                f,
+                "Failed tables: <{}>",
+                self.get_failed_transaction_groups().len().to_string().red()
+            )?;
+        }
+        writeln!(f, "Elapsed: <{:.2?}>", self.elapsed)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(query: &str, finish_order: usize, created: bool) -> IndexImportResult {
+        let index_task = IndexTask::new("INPUT/public/t/x.sql", None, query.to_string());
+        let mut result = IndexImportResult::new(&index_task);
+        result.add_attempt(Duration::from_millis(1), None);
+        result.finish_order = finish_order;
+        if created {
+            result.rollback = index_task.rollback.clone();
+        }
+        result
+    }
+
+    #[test]
+    fn rollback_statements_drop_the_last_created_first() {
+        let mut summary = ImportSummary::new();
+        summary.add_results(vec![
+            result("CREATE INDEX b ON public.t (b);", 2, true),
+            result(
+                "ALTER TABLE public.t ADD CONSTRAINT pk PRIMARY KEY (a);",
+                0,
+                true,
+            ),
+            result("CREATE INDEX IF NOT EXISTS old ON public.t (c);", 3, false),
+        ]);
+        summary.add_results(vec![
+            result(
+                "ALTER TABLE public.u ADD CONSTRAINT fk FOREIGN KEY (a) REFERENCES public.t (a);",
+                4,
+                true,
+            ),
+            result("CREATE INDEX a ON public.t (a);", 1, true),
+        ]);
+        assert_eq!(
+            summary.get_rollback_statements(),
+            vec![
+                "ALTER TABLE IF EXISTS \"public\".\"u\" DROP CONSTRAINT IF EXISTS \"fk\";",
+                "DROP INDEX CONCURRENTLY IF EXISTS \"public\".\"b\";",
+                "DROP INDEX CONCURRENTLY IF EXISTS \"public\".\"a\";",
+                "ALTER TABLE IF EXISTS \"public\".\"t\" DROP CONSTRAINT IF EXISTS \"pk\";",
+            ]
+        );
+    }
+
+    fn failed_result(error_class: ErrorClass) -> IndexImportResult {
+        let index_task = IndexTask::new(
+            "INPUT/public/t/x.sql",
+            None,
+            "CREATE INDEX x ON public.t (a);".to_string(),
+        );
+        let mut result = IndexImportResult::new(&index_task);
+        result.add_failed_attempt(Duration::from_millis(1), "failed".to_string(), error_class);
+        result
+    }
+
+    #[test]
+    fn exit_code_tells_partial_from_total_and_connection_failures() {
+        let mut summary = ImportSummary::new();
+        summary.add_results(vec![result("CREATE INDEX a ON public.t (a);", 0, true)]);
+        assert_eq!(summary.get_exit_code(), EXIT_SUCCESS);
+        summary.add_results(vec![failed_result(ErrorClass::Permanent)]);
+        assert_eq!(summary.get_exit_code(), EXIT_PARTIAL_FAILURE);
+        summary.add_results(vec![failed_result(ErrorClass::Connection)]);
+        assert_eq!(summary.get_exit_code(), EXIT_CONNECTION_FAILURE);
+
+        let mut summary = ImportSummary::new();
+        summary.add_results(vec![
+            failed_result(ErrorClass::Permanent),
+            IndexImportResult::new(&IndexTask::new(
+                "INPUT/public/t/y.sql",
+                None,
+                "CREATE INDEX y ON public.t (b);".to_string(),
+            )),
+        ]);
+        assert_eq!(summary.get_exit_code(), EXIT_FAILURE);
+    }
+
+    #[test]
+    fn rollback_statements_are_empty_without_anything_created() {
+        let mut summary = ImportSummary::new();
+        summary.add_results(vec![result("ANALYZE public.t;", 0, true)]);
+        assert!(summary.get_rollback_statements().is_empty());
+    }
+}
@@ -0,0 +1,784 @@
+use crate::build_registry::build_registry::BuildRegistry;
+use crate::ddl_parser::ddl_parser::{StatementKind, parse_statement, split_statements};
+use crate::import_summary::import_summary::{AttemptRecord, IndexImportResult};
+use crate::logger::logger::LogContext;
+use crate::postgres_provider::postgres_provider::PostgresPool;
+use crate::retry_policy::retry_policy::{
+    ErrorClass, TimeoutKind, classify_pool_error, classify_postgres_error, classify_timeout,
+};
+use crate::settings::settings::Settings;
+use crate::shutdown::shutdown::Shutdown;
+use crate::{log_error, log_info, log_warn};
+use clap::ValueEnum;
+use colored::Colorize;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+use tokio_postgres::Client;
+
+// extra time the server gets to enforce statement_timeout before the client cancels the query
+const CLIENT_TIMEOUT_GRACE_IN_SECONDS: u64 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TransactionMode {
+    /// All statements of a table in one transaction, none of them is kept if one fails
+    PerTable,
+}
+
+impl fmt::Display for TransactionMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TransactionMode::PerTable => write!(f, "per-table"),
+        }
+    }
+}
+
+pub struct IndexTask {
+    pub file_name: String,
+    /// index name in the source database for exported files, the dropped object for scripts
+    pub source_name: String,
+    /// `schema.table` the index belongs to, used to pick per-table session settings
+    pub schema_table: Option<String>,
+    /// Lower-cased table name; the scheduler never runs two tasks with the same key at once
+    pub table_key: String,
+    pub kind: StatementKind,
+    /// index or constraint name as written in the statement
+    pub target_name: Option<String>,
+    /// `"schema"."name"` of the index the statement creates
+    pub quoted_index_name: Option<String>,
+    pub query: String,
+    /// drops exactly what `query` creates, written to the rollback script on success
+    pub rollback: Option<String>,
+    /// checks whether an `IF NOT EXISTS` statement has anything to create
+    pub existence_query: Option<String>,
+    /// checks whether the foreign key or check constraint of the first statement exists
+    pub constraint_query: Option<String>,
+    /// `"schema"."table"` when the statement names its table, what `--analyze` runs on
+    pub quoted_table: Option<String>,
+    /// table key of the table a foreign key points to, whose keys have to be built first
+    pub referenced_table_key: Option<String>,
+    pub concurrently: bool,
+}
+
+impl IndexTask {
+    /// The table named in the statement wins over the one derived from the file path.
+    pub fn new(file_name: &str, path_schema_table: Option<String>, query: String) -> Self {
+        let parsed_statement = parse_statement(&query);
+        let schema_table = match &parsed_statement.table {
+            Some(table) if table.schema.is_some() => Some(table.to_string()),
+            _ => path_schema_table,
+        };
+        let table_key = schema_table
+            .as_ref()
+            .map(|t| t.to_lowercase())
+            .unwrap_or_else(|| file_name.to_string());
+        // file name without directory and extension, the index name for exported files
+        let source_name = Path::new(file_name)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or(file_name)
+            .to_string();
+        IndexTask {
+            file_name: file_name.to_string(),
+            source_name,
+            schema_table,
+            table_key,
+            kind: parsed_statement.kind,
+            target_name: parsed_statement.name,
+            quoted_index_name: parsed_statement.quoted_index_name,
+            query,
+            rollback: parsed_statement.rollback,
+            existence_query: parsed_statement.existence_query,
+            constraint_query: parsed_statement.constraint_query,
+            quoted_table: parsed_statement.table.map(|t| t.quoted),
+            referenced_table_key: parsed_statement
+                .referenced_table
+                .map(|t| t.to_string().to_lowercase()),
+            concurrently: parsed_statement.concurrently,
+        }
+    }
+
+    /// A statement from a script such as a rollback file, named `<script>:<line>`.
+    pub fn from_script(script_file: &str, line: usize, query: String) -> Self {
+        let mut index_task = IndexTask::new(&format!("{}:{}", script_file, line), None, query);
+        if let Some(target_name) = &index_task.target_name {
+            index_task.source_name = target_name.clone();
+        }
+        index_task
+    }
+
+    /// `ANALYZE` of the table this task built an index on, under the same table key.
+    pub fn for_analyze(&self) -> Option<Self> {
+        if matches!(
+            self.kind,
+            StatementKind::Analyze
+                | StatementKind::Drop
+                | StatementKind::DropMaterializedView
+                | StatementKind::DropConstraint
+                | StatementKind::ForeignKey
+                | StatementKind::CheckConstraint
+                | StatementKind::Default
+        ) {
+            return None;
+        }
+        let quoted_table = self.quoted_table.as_ref()?;
+        let mut analyze_task = IndexTask::new(
+            &format!("analyze:{}", self.table_key),
+            self.schema_table.clone(),
+            format!("ANALYZE {};", quoted_table),
+        );
+        analyze_task.source_name = self
+            .schema_table
+            .clone()
+            .unwrap_or_else(|| self.table_key.clone());
+        analyze_task.table_key = self.table_key.clone();
+        Some(analyze_task)
+    }
+
+    pub fn get_label(&self) -> String {
+        self.source_name.clone()
+    }
+}
+
+struct BuildError {
+    message: String,
+    sqlstate: Option<String>,
+    error_class: ErrorClass,
+    timeout: Option<TimeoutKind>,
+}
+
+impl From<tokio_postgres::Error> for BuildError {
+    fn from(e: tokio_postgres::Error) -> Self {
+        BuildError {
+            message: e
+                .as_db_error()
+                .map(|db| db.message().to_string())
+                .unwrap_or_else(|| e.to_string()),
+            sqlstate: e.code().map(|c| c.code().to_string()),
+            error_class: classify_postgres_error(&e),
+            timeout: classify_timeout(&e),
+        }
+    }
+}
+
+/// Everything a worker needs besides the task itself; cheap to clone into every worker.
+#[derive(Clone)]
+pub struct ImportContext {
+    pub postgres_pool: PostgresPool,
+    pub settings: Arc<Settings>,
+    pub build_registry: Arc<BuildRegistry>,
+    pub shutdown: Arc<Shutdown>,
+    /// 1-based number of the worker running the task, 0 outside of the workers
+    pub worker: usize,
+}
+
+impl ImportContext {
+    /// Worker, table and index of a task for its log events.
+    pub fn get_log_context(&self, index_task: &IndexTask) -> LogContext {
+        LogContext {
+            worker: Some(self.worker),
+            table: Some(
+                index_task
+                    .schema_table
+                    .clone()
+                    .unwrap_or_else(|| index_task.table_key.clone()),
+            ),
+            index: Some(index_task.get_label()),
+            elapsed: None,
+        }
+    }
+}
+
+pub async fn import_index(context: &ImportContext, index_task: &IndexTask) -> IndexImportResult {
+    let settings = context.settings.as_ref();
+    let retry_policy = settings.get_retry_policy_as_ref();
+    let file_name = index_task.file_name.as_str();
+    let session_query = get_session_settings_query(settings, index_task.schema_table.as_deref());
+    let mut result = IndexImportResult::new(index_task);
+    let log_context = context.get_log_context(index_task);
+    let started = Instant::now();
+    if context.shutdown.is_stopping() {
+        // handed out right before the shutdown request, never started
+        return result;
+    }
+    let mut attempt = 1;
+    loop {
+        let now = Instant::now();
+        let outcome = execute_index_query(context, &session_query, index_task, attempt).await;
+        let elapsed = now.elapsed();
+        result.elapsed = started.elapsed();
+        let error = match outcome {
+            Ok(created) => {
+                result.attempts.push(AttemptRecord {
+                    attempt,
+                    elapsed,
+                    error: None,
+                    sqlstate: None,
+                    error_class: None,
+                    timeout: None,
+                });
+                let log_context = log_context.with_elapsed(elapsed);
+                if created {
+                    result.rollback = index_task.rollback.clone();
+                    log_info!(context: &log_context, "{}", "Index imported successfully".green());
+                } else {
+                    log_info!(
+                        context: &log_context,
+                        "{}",
+                        "Index already exists, nothing to roll back".yellow()
+                    );
+                }
+                return result;
+            }
+            Err(error) => error,
+        };
+        result.attempts.push(AttemptRecord {
+            attempt,
+            elapsed,
+            error: Some(error.message.clone()),
+            sqlstate: error.sqlstate.clone(),
+            error_class: Some(error.error_class),
+            timeout: error.timeout,
+        });
+        if context.shutdown.is_cancelling() {
+            result.cancelled = true;
+            log_error!(
+                context: &log_context.with_elapsed(elapsed),
+                "{}: {}",
+                "Index build cancelled".red(),
+                error.message.red()
+            );
+            return result;
+        }
+        match error.timeout {
+            Some(timeout) => log_error!(
+                context: &log_context.with_elapsed(elapsed),
+                "{} (attempt {}, {} after {:.2?}): {}",
+                "Index build timed out".red(),
+                attempt,
+                timeout,
+                elapsed,
+                error.message.red()
+            ),
+            None => log_error!(
+                context: &log_context.with_elapsed(elapsed),
+                "{} (attempt {}, {}): {}",
+                "Error importing index".red(),
+                attempt,
+                error.error_class,
+                error.message.red()
+            ),
+        }
+        if !retry_policy.should_retry(attempt, error.error_class) {
+            return result;
+        }
+        if context.shutdown.is_stopping() {
+            log_warn!(
+                context: &log_context,
+                "Not retrying index <{}>, shutdown requested",
+                file_name.yellow()
+            );
+            return result;
+        }
+        let backoff = retry_policy.get_backoff(attempt);
+        log_warn!(
+            context: &log_context,
+            "Retrying index <{}> in {:.2?} ({} of {})",
+            file_name.yellow(),
+            backoff,
+            attempt,
+            retry_policy.get_max_retries()
+        );
+        tokio::select! {
+            _ = tokio::time::sleep(backoff) => {}
+            _ = context.shutdown.stopping() => {
+                log_warn!(
+                    context: &log_context,
+                    "Not retrying index <{}>, shutdown requested",
+                    file_name.yellow()
+                );
+                return result;
+            }
+        }
+        attempt += 1;
+    }
+}
+
+/// Returns whether the statement created its object, `IF NOT EXISTS` may leave an existing one.
+async fn execute_index_query(
+    context: &ImportContext,
+    session_query: &str,
+    index_task: &IndexTask,
+    attempt: u32,
+) -> Result<bool, BuildError> {
+    // a fresh connection is taken for every attempt, so a dropped connection is not reused
+    let postgres_connection = context.postgres_pool.get().await.map_err(|e| BuildError {
+        message: e.to_string(),
+        sqlstate: None,
+        error_class: classify_pool_error(&e),
+        timeout: None,
+    })?;
+    // pooled connections are shared between tables, so settings of the previous build are reset
+    postgres_connection.batch_execute(session_query).await?;
+    if let Some(quoted_index_name) = &index_task.quoted_index_name {
+        check_invalid_index(
+            context,
+            &postgres_connection,
+            index_task,
+            quoted_index_name,
+            attempt,
+        )
+        .await?;
+    }
+    let existed = match &index_task.existence_query {
+        Some(existence_query) => postgres_connection
+            .query_one(existence_query.as_str(), &[])
+            .await?
+            .get(0),
+        None => false,
+    };
+    let (backend_pid, statement_timeout) = get_backend(&postgres_connection).await?;
+    context.build_registry.register(
+        backend_pid,
+        &index_task.get_label(),
+        postgres_connection.cancel_token(),
+    );
+    if index_task.concurrently
+        && !existed
+        && let Some(quoted_index_name) = &index_task.quoted_index_name
+    {
+        // an interrupted concurrent build leaves the index INVALID, see `get_invalid_indexes`
+        context
+            .build_registry
+            .add_concurrent_index(quoted_index_name);
+    }
+    // the second signal may have arrived before the build was registered
+    let result = if context.shutdown.is_cancelling() {
+        Err(BuildError {
+            message: "cancelled before start".to_string(),
+            sqlstate: None,
+            error_class: ErrorClass::Permanent,
+            timeout: None,
+        })
+    } else {
+        let skipped_statements = match &index_task.constraint_query {
+            Some(constraint_query) if attempt > 1 => {
+                check_added_constraint(context, &postgres_connection, index_task, constraint_query)
+                    .await?
+            }
+            _ => 0,
+        };
+        execute_with_timeout(
+            context,
+            &postgres_connection,
+            &index_task.query,
+            statement_timeout,
+            skipped_statements,
+        )
+        .await
+    };
+    context.build_registry.unregister(backend_pid);
+    result.map(|_| !existed)
+}
+
+/// A foreign key or check constraint is added `NOT VALID` and validated by a second
+/// statement. When the validation failed, the retry finds the constraint in place and
+/// only validates it; adding it again would fail as a duplicate.
+/// Returns the number of statements to skip.
+async fn check_added_constraint(
+    context: &ImportContext,
+    postgres_connection: &Client,
+    index_task: &IndexTask,
+    constraint_query: &str,
+) -> Result<usize, BuildError> {
+    let added: bool = postgres_connection
+        .query_one(constraint_query, &[])
+        .await?
+        .get(0);
+    if !added {
+        return Ok(0);
+    }
+    log_warn!(
+        context: &context.get_log_context(index_task),
+        "Constraint <{}> was added by the failed attempt, only validating it",
+        index_task.target_name.as_deref().unwrap_or_default().yellow()
+    );
+    Ok(1)
+}
+
+/// A failed concurrent build leaves its index behind INVALID, which would make the next
+/// build fail as a duplicate or, with `IF NOT EXISTS`, do nothing and report success.
+/// On a retry the index is the one the previous attempt left and is dropped; before the
+/// first attempt it may be another session's build in progress, so the task fails.
+async fn check_invalid_index(
+    context: &ImportContext,
+    postgres_connection: &Client,
+    index_task: &IndexTask,
+    quoted_index_name: &str,
+    attempt: u32,
+) -> Result<(), BuildError> {
+    let invalid = postgres_connection
+        .query_opt(
+            "SELECT NOT indisvalid FROM pg_index WHERE indexrelid = to_regclass($1)",
+            &[&quoted_index_name],
+        )
+        .await?
+        .is_some_and(|row| row.get::<_, bool>(0));
+    if invalid && attempt == 1 {
+        return Err(BuildError {
+            message: format!(
+                "INVALID index {} already exists, drop it before the import",
+                quoted_index_name
+            ),
+            sqlstate: None,
+            error_class: ErrorClass::Permanent,
+            timeout: None,
+        });
+    }
+    if invalid {
+        log_warn!(
+            context: &context.get_log_context(index_task),
+            "Dropping INVALID index <{}> left by the failed attempt",
+            quoted_index_name.yellow()
+        );
+        postgres_connection
+            .batch_execute(&format!(
+                "DROP INDEX CONCURRENTLY IF EXISTS {};",
+                quoted_index_name
+            ))
+            .await?;
+    }
+    Ok(())
+}
+
+/// Runs all statements of a table in one transaction on one connection. Any failure
+/// rolls back the whole group; transient failures retry the whole group.
+pub async fn import_table_group(
+    context: &ImportContext,
+    index_tasks: &[IndexTask],
+) -> Vec<IndexImportResult> {
+    let settings = context.settings.as_ref();
+    let retry_policy = settings.get_retry_policy_as_ref();
+    let table_key = index_tasks[0].table_key.as_str();
+    let session_query =
+        get_session_settings_query(settings, index_tasks[0].schema_table.as_deref());
+    let mut results: Vec<IndexImportResult> =
+        index_tasks.iter().map(IndexImportResult::new).collect();
+    for result in results.iter_mut() {
+        result.transaction_group = Some(table_key.to_string());
+    }
+    let log_context = LogContext {
+        index: None,
+        ..context.get_log_context(&index_tasks[0])
+    };
+    let started = Instant::now();
+    if context.shutdown.is_stopping() {
+        return results;
+    }
+    let mut attempt = 1;
+    loop {
+        let now = Instant::now();
+        let outcome = execute_table_group(context, &session_query, index_tasks).await;
+        let elapsed = now.elapsed();
+        let elapsed_total = started.elapsed();
+        let failed_position = match outcome {
+            Ok(statement_outcomes) => {
+                for ((result, index_task), (elapsed, created)) in
+                    results.iter_mut().zip(index_tasks).zip(statement_outcomes)
+                {
+                    result.attempts.push(AttemptRecord {
+                        attempt,
+                        elapsed,
+                        error: None,
+                        sqlstate: None,
+                        error_class: None,
+                        timeout: None,
+                    });
+                    result.elapsed = elapsed_total;
+                    if created {
+                        result.rollback = index_task.rollback.clone();
+                    }
+                }
+                log_info!(
+                    context: &log_context.with_elapsed(elapsed),
+                    "{} <{}>",
+                    "Transaction committed for table".green(),
+                    table_key.yellow()
+                );
+                return results;
+            }
+            Err((position, error)) => (position, error),
+        };
+        let (position, error) = failed_position;
+        let failed_file = index_tasks
+            .get(position)
+            .map_or("transaction", |t| t.file_name.as_str());
+        for (i, result) in results.iter_mut().enumerate() {
+            // connection, BEGIN or COMMIT failures belong to every statement of the group
+            let (message, sqlstate) = if i == position || position == index_tasks.len() {
+                (error.message.clone(), error.sqlstate.clone())
+            } else if i < position {
+                (format!("rolled back, <{}> failed", failed_file), None)
+            } else {
+                (format!("not run, <{}> failed", failed_file), None)
+            };
+            result.attempts.push(AttemptRecord {
+                attempt,
+                elapsed,
+                error: Some(message),
+                sqlstate,
+                error_class: Some(error.error_class),
+                timeout: if i == position || position == index_tasks.len() {
+                    error.timeout
+                } else {
+                    None
+                },
+            });
+            result.elapsed = elapsed_total;
+        }
+        if context.shutdown.is_cancelling() {
+            results.iter_mut().for_each(|r| r.cancelled = true);
+            log_error!(
+                context: &log_context.with_elapsed(elapsed),
+                "{}: {}",
+                "Transaction cancelled".red(),
+                error.message.red()
+            );
+            return results;
+        }
+        log_error!(
+            context: &log_context.with_elapsed(elapsed),
+            "{} <{}> (attempt {}, {}), <{}> failed: {}",
+            "Transaction rolled back for table".red(),
+            table_key.yellow(),
+            attempt,
+            error.error_class,
+            failed_file,
+            error.message.red()
+        );
+        if !retry_policy.should_retry(attempt, error.error_class) || context.shutdown.is_stopping()
+        {
+            return results;
+        }
+        let backoff = retry_policy.get_backoff(attempt);
+        log_warn!(
+            context: &log_context,
+            "Retrying table <{}> in {:.2?} ({} of {})",
+            table_key.yellow(),
+            backoff,
+            attempt,
+            retry_policy.get_max_retries()
+        );
+        tokio::select! {
+            _ = tokio::time::sleep(backoff) => {}
+            _ = context.shutdown.stopping() => {
+                log_warn!(
+                    context: &log_context,
+                    "Not retrying table <{}>, shutdown requested",
+                    table_key.yellow()
+                );
+                return results;
+            }
+        }
+        attempt += 1;
+    }
+}
+
+/// Returns the elapsed time of every statement and whether it created its object,
+/// or the position of the failed statement; `index_tasks.len()` stands for the
+/// transaction itself: connection, `BEGIN` or `COMMIT`.
+async fn execute_table_group(
+    context: &ImportContext,
+    session_query: &str,
+    index_tasks: &[IndexTask],
+) -> Result<Vec<(Duration, bool)>, (usize, BuildError)> {
+    let group_error = index_tasks.len();
+    let postgres_connection = context.postgres_pool.get().await.map_err(|e| {
+        (
+            group_error,
+            BuildError {
+                message: e.to_string(),
+                sqlstate: None,
+                error_class: classify_pool_error(&e),
+                timeout: None,
+            },
+        )
+    })?;
+    let group_failure = |e: tokio_postgres::Error| (group_error, BuildError::from(e));
+    postgres_connection
+        .batch_execute(session_query)
+        .await
+        .map_err(group_failure)?;
+    let (backend_pid, statement_timeout) = get_backend(&postgres_connection)
+        .await
+        .map_err(group_failure)?;
+    context.build_registry.register(
+        backend_pid,
+        &index_tasks[0].table_key,
+        postgres_connection.cancel_token(),
+    );
+    let mut outcome = postgres_connection
+        .batch_execute("BEGIN")
+        .await
+        .map(|_| Vec::new())
+        .map_err(group_failure);
+    for (position, index_task) in index_tasks.iter().enumerate() {
+        let Ok(statement_outcomes) = outcome.as_mut() else {
+            break;
+        };
+        if context.shutdown.is_cancelling() {
+            outcome = Err((
+                position,
+                BuildError {
+                    message: "cancelled before start".to_string(),
+                    sqlstate: None,
+                    error_class: ErrorClass::Permanent,
+                    timeout: None,
+                },
+            ));
+            break;
+        }
+        let now = Instant::now();
+        let existed = match &index_task.existence_query {
+            Some(existence_query) => postgres_connection
+                .query_one(existence_query.as_str(), &[])
+                .await
+                .map(|row| row.get(0))
+                .map_err(|e| (position, BuildError::from(e))),
+            None => Ok(false),
+        };
+        let statement_result = match existed {
+            Ok(existed) => execute_with_timeout(
+                context,
+                &postgres_connection,
+                &index_task.query,
+                statement_timeout,
+                0,
+            )
+            .await
+            .map(|_| !existed)
+            .map_err(|e| (position, e)),
+            Err(e) => Err(e),
+        };
+        match statement_result {
+            Ok(created) => statement_outcomes.push((now.elapsed(), created)),
+            Err(e) => outcome = Err(e),
+        }
+    }
+    let outcome = match outcome {
+        Ok(statement_outcomes) => postgres_connection
+            .batch_execute("COMMIT")
+            .await
+            .map(|_| statement_outcomes)
+            .map_err(|e| (group_error, BuildError::from(e))),
+        Err(e) => {
+            // a broken connection rolls back on its own
+            let _ = postgres_connection.batch_execute("ROLLBACK").await;
+            Err(e)
+        }
+    };
+    context.build_registry.unregister(backend_pid);
+    outcome
+}
+
+/// Backend pid for the build registry and the statement_timeout in effect, which
+/// `[session_settings]` may have set instead of `--build-timeout` for the table.
+async fn get_backend(
+    postgres_connection: &Client,
+) -> Result<(i32, Duration), tokio_postgres::Error> {
+    let row = postgres_connection
+        .query_one(
+            "SELECT pg_backend_pid(), setting::bigint FROM pg_settings \
+             WHERE name = 'statement_timeout'",
+            &[],
+        )
+        .await?;
+    let statement_timeout_in_ms: i64 = row.get(1);
+    Ok((
+        row.get(0),
+        Duration::from_millis(statement_timeout_in_ms as u64),
+    ))
+}
+
+/// Statements of a file run one after the other, each in a transaction of its own unless
+/// one is open, so a foreign key is validated after the transaction that added it.
+async fn execute_with_timeout(
+    context: &ImportContext,
+    postgres_connection: &Client,
+    index_query: &str,
+    statement_timeout: Duration,
+    skipped_statements: usize,
+) -> Result<(), BuildError> {
+    for (_, statement) in split_statements(index_query)
+        .into_iter()
+        .skip(skipped_statements)
+    {
+        execute_statement_with_timeout(context, postgres_connection, &statement, statement_timeout)
+            .await?;
+    }
+    Ok(())
+}
+
+async fn execute_statement_with_timeout(
+    context: &ImportContext,
+    postgres_connection: &Client,
+    index_query: &str,
+    statement_timeout: Duration,
+) -> Result<(), BuildError> {
+    if statement_timeout.is_zero() {
+        postgres_connection.execute(index_query, &[]).await?;
+        return Ok(());
+    }
+    // statement_timeout is enforced by the server; the client side limit only catches
+    // a backend that stopped responding, it cancels the query the same way psql does
+    let client_timeout = statement_timeout + Duration::from_secs(CLIENT_TIMEOUT_GRACE_IN_SECONDS);
+    let cancel_token = postgres_connection.cancel_token();
+    match tokio::time::timeout(
+        client_timeout,
+        postgres_connection.execute(index_query, &[]),
+    )
+    .await
+    {
+        Ok(execute_result) => {
+            execute_result?;
+            Ok(())
+        }
+        Err(_) => {
+            let _ = cancel_token
+                .cancel_query(context.postgres_pool.get_tls())
+                .await;
+            Err(BuildError {
+                message: format!(
+                    "no response from server {:.2?} after statement timeout, query cancelled",
+                    Duration::from_secs(CLIENT_TIMEOUT_GRACE_IN_SECONDS)
+                ),
+                sqlstate: None,
+                error_class: ErrorClass::Permanent,
+                timeout: Some(TimeoutKind::Build),
+            })
+        }
+    }
+}
+
+/// Timeouts from the command line come first, so `[session_settings]` can still
+/// override them for a single table.
+fn get_session_settings_query(settings: &Settings, schema_table: Option<&str>) -> String {
+    let mut query = String::from("RESET ALL;");
+    query.push_str(&format!(
+        " SET statement_timeout = '{}ms';",
+        settings.get_build_timeout().as_millis()
+    ));
+    query.push_str(&format!(
+        " SET lock_timeout = '{}ms';",
+        settings.get_lock_timeout().as_millis()
+    ));
+    let session_values = settings
+        .get_session_settings_as_ref()
+        .get_values_for_table(schema_table);
+    for (name, value) in session_values {
+        query.push_str(&format!(" SET {} = '{}';", name, value.replace('\'', "''")));
+    }
+    query
+}
@@ -0,0 +1,324 @@
+use crate::ddl_parser::ddl_parser::quote_identifier;
+use crate::translator::translator::map_name;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexColumn {
+    pub name: String,
+    pub descending: bool,
+}
+
+impl fmt::Display for IndexColumn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let direction = if self.descending { "DESC" } else { "ASC" };
+        write!(f, "{} {}", self.name, direction)
+    }
+}
+
+/// The parts of an index verification compares, read from either database.
+#[derive(Debug, Clone, Default)]
+pub struct IndexDefinition {
+    pub name: String,
+    pub is_unique: bool,
+    pub is_primary_key: bool,
+    pub key_columns: Vec<IndexColumn>,
+    pub include_columns: Vec<String>,
+    pub predicate: Option<String>,
+    /// backs a primary key or unique constraint, which owns the index
+    pub is_constraint: bool,
+}
+
+impl IndexDefinition {
+    /// Drops this target index, through its constraint when it has one.
+    pub fn get_drop_statement(&self, quoted_schema: &str, quoted_table: &str) -> String {
+        let name = quote_identifier(&self.name);
+        if self.is_constraint {
+            format!(
+                "ALTER TABLE IF EXISTS {}.{} DROP CONSTRAINT IF EXISTS {};",
+                quoted_schema, quoted_table, name
+            )
+        } else {
+            format!(
+                "DROP INDEX CONCURRENTLY IF EXISTS {}.{};",
+                quoted_schema, name
+            )
+        }
+    }
+
+    /// Names as they are expected in PostgreSQL. The translator drops `DESC` from primary
+    /// key and unique constraints, PostgreSQL has no syntax for it.
+    fn to_mapped(&self) -> IndexDefinition {
+        IndexDefinition {
+            name: map_name(&self.name),
+            key_columns: self
+                .key_columns
+                .iter()
+                .map(|c| IndexColumn {
+                    name: map_name(&c.name),
+                    descending: c.descending && !self.is_constraint,
+                })
+                .collect(),
+            include_columns: self.include_columns.iter().map(|c| map_name(c)).collect(),
+            ..self.clone()
+        }
+    }
+}
+
+impl fmt::Display for IndexDefinition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_primary_key {
+            write!(f, "PRIMARY KEY ")?;
+        } else if self.is_unique {
+            write!(f, "UNIQUE ")?;
+        }
+        write!(f, "({})", join(&self.key_columns))?;
+        if !self.include_columns.is_empty() {
+            write!(f, " INCLUDE ({})", self.include_columns.join(", "))?;
+        }
+        if let Some(predicate) = &self.predicate {
+            write!(f, " WHERE {}", predicate)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VerifyStatus {
+    Matched,
+    /// in the source only
+    Missing,
+    /// in the target only
+    Extra,
+    Mismatched,
+}
+
+impl fmt::Display for VerifyStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VerifyStatus::Matched => write!(f, "matched"),
+            VerifyStatus::Missing => write!(f, "missing"),
+            VerifyStatus::Extra => write!(f, "extra"),
+            VerifyStatus::Mismatched => write!(f, "mismatched"),
+        }
+    }
+}
+
+/// One source index and its target counterpart; either side may be absent.
+#[derive(Debug, Clone)]
+pub struct IndexComparison {
+    pub source: Option<IndexDefinition>,
+    pub target: Option<IndexDefinition>,
+    pub mismatches: Vec<String>,
+}
+
+impl IndexComparison {
+    pub fn get_status(&self) -> VerifyStatus {
+        match (&self.source, &self.target) {
+            (Some(_), None) => VerifyStatus::Missing,
+            (None, _) => VerifyStatus::Extra,
+            _ if self.mismatches.is_empty() => VerifyStatus::Matched,
+            _ => VerifyStatus::Mismatched,
+        }
+    }
+
+    /// The source name, or the target name of an extra index.
+    pub fn get_name(&self) -> &str {
+        self.source
+            .as_ref()
+            .or(self.target.as_ref())
+            .map(|d| d.name.as_str())
+            .unwrap_or_default()
+    }
+}
+
+/// Matches the indexes of one table through the name mapping. Source indexes come first
+/// in their order, followed by the extra target indexes.
+pub fn compare_indexes(
+    source_indexes: &[IndexDefinition],
+    target_indexes: &[IndexDefinition],
+) -> Vec<IndexComparison> {
+    let mut unmatched_targets: Vec<&IndexDefinition> = target_indexes.iter().collect();
+    let mut comparisons = Vec::new();
+    for source_index in source_indexes {
+        let mapped_index = source_index.to_mapped();
+        let target_index = unmatched_targets
+            .iter()
+            .position(|t| t.name == mapped_index.name)
+            .map(|i| unmatched_targets.remove(i));
+        let mismatches = target_index
+            .map(|t| get_mismatches(&mapped_index, t))
+            .unwrap_or_default();
+        comparisons.push(IndexComparison {
+            source: Some(source_index.clone()),
+            target: target_index.cloned(),
+            mismatches,
+        });
+    }
+    comparisons.extend(unmatched_targets.into_iter().map(|t| IndexComparison {
+        source: None,
+        target: Some(t.clone()),
+        mismatches: Vec::new(),
+    }));
+    comparisons
+}
+
+fn get_mismatches(source: &IndexDefinition, target: &IndexDefinition) -> Vec<String> {
+    let mut mismatches = Vec::new();
+    if source.is_primary_key != target.is_primary_key {
+        mismatches.push(format!(
+            "primary key: source {}, target {}",
+            yes_no(source.is_primary_key),
+            yes_no(target.is_primary_key)
+        ));
+    }
+    if source.is_unique != target.is_unique {
+        mismatches.push(format!(
+            "uniqueness: source {}, target {}",
+            yes_no(source.is_unique),
+            yes_no(target.is_unique)
+        ));
+    }
+    let source_keys: Vec<&str> = source.key_columns.iter().map(|c| c.name.as_str()).collect();
+    let target_keys: Vec<&str> = target.key_columns.iter().map(|c| c.name.as_str()).collect();
+    if source_keys != target_keys {
+        let what = if sorted(&source_keys) == sorted(&target_keys) {
+            "key order"
+        } else {
+            "key columns"
+        };
+        mismatches.push(format!(
+            "{}: source ({}), target ({})",
+            what,
+            source_keys.join(", "),
+            target_keys.join(", ")
+        ));
+    } else if source.key_columns != target.key_columns {
+        mismatches.push(format!(
+            "direction: source ({}), target ({})",
+            join(&source.key_columns),
+            join(&target.key_columns)
+        ));
+    }
+    // the order of included columns has no meaning
+    if sorted(&source.include_columns) != sorted(&target.include_columns) {
+        mismatches.push(format!(
+            "include columns: source ({}), target ({})",
+            source.include_columns.join(", "),
+            target.include_columns.join(", ")
+        ));
+    }
+    let source_predicate = source.predicate.as_deref().map(normalize_predicate);
+    let target_predicate = target.predicate.as_deref().map(normalize_predicate);
+    if source_predicate != target_predicate {
+        mismatches.push(format!(
+            "predicate: source {}, target {}",
+            source.predicate.as_deref().unwrap_or("none"),
+            target.predicate.as_deref().unwrap_or("none")
+        ));
+    }
+    mismatches
+}
+
+/// Reduces both spellings of a filter to something comparable: SQL Server stores
+/// `([Status]=N'A')`, PostgreSQL prints `(status = 'A'::text)`. Brackets, quotes,
+/// parentheses, white space, casts and `N` prefixes are dropped and names are lower-cased;
+/// string literals are kept as they are.
+fn normalize_predicate(predicate: &str) -> String {
+    let chars: Vec<char> = predicate.chars().collect();
+    let is_name_char = |c: char| c.is_alphanumeric() || c == '_';
+    let mut normalized = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\'' => {
+                normalized.push(c);
+                i += 1;
+                while i < chars.len() {
+                    normalized.push(chars[i]);
+                    i += 1;
+                    if chars[i - 1] == '\'' {
+                        if chars.get(i) == Some(&'\'') {
+                            normalized.push('\'');
+                            i += 1;
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                continue;
+            }
+            // N'...' is a national string literal
+            'N' | 'n'
+                if chars.get(i + 1) == Some(&'\'') && (i == 0 || !is_name_char(chars[i - 1])) => {}
+            ':' if chars.get(i + 1) == Some(&':') => {
+                i += 2;
+                while i < chars.len() && (is_name_char(chars[i]) || chars[i] == '"') {
+                    i += 1;
+                }
+                continue;
+            }
+            '[' | ']' | '"' | '(' | ')' => {}
+            c if c.is_whitespace() => {}
+            c => normalized.extend(c.to_lowercase()),
+        }
+        i += 1;
+    }
+    normalized
+}
+
+fn join(columns: &[IndexColumn]) -> String {
+    columns
+        .iter()
+        .map(|c| c.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn sorted<T: AsRef<str>>(values: &[T]) -> Vec<&str> {
+    let mut values: Vec<&str> = values.iter().map(|v| v.as_ref()).collect();
+    values.sort_unstable();
+    values
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value { "yes" } else { "no" }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(name: &str, column: &str, is_constraint: bool, descending: bool) -> IndexDefinition {
+        IndexDefinition {
+            name: name.to_string(),
+            is_unique: is_constraint,
+            key_columns: vec![IndexColumn {
+                name: column.to_string(),
+                descending,
+            }],
+            is_constraint,
+            ..IndexDefinition::default()
+        }
+    }
+
+    #[test]
+    fn ignores_direction_of_constraints() {
+        let source = [index("UQ_Id", "Id", true, true)];
+        let target = [index("uq_id", "id", true, false)];
+        let comparisons = compare_indexes(&source, &target);
+        assert_eq!(comparisons[0].get_status(), VerifyStatus::Matched);
+    }
+
+    #[test]
+    fn reports_direction_of_indexes() {
+        let source = [index("IX_Id", "Id", false, true)];
+        let target = [index("ix_id", "id", false, false)];
+        let comparisons = compare_indexes(&source, &target);
+        assert_eq!(comparisons[0].get_status(), VerifyStatus::Mismatched);
+        assert_eq!(
+            comparisons[0].mismatches,
+            ["direction: source (id DESC), target (id ASC)"]
+        );
+    }
+}
@@ -0,0 +1 @@
+pub mod index_verifier;
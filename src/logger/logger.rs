@@ -0,0 +1,424 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde_json::{Map, Value, json};
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use time::OffsetDateTime;
+use time::format_description::BorrowedFormatItem;
+use time::macros::format_description;
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+// RFC 3339 in UTC with a fixed number of digits, so the lines of a file stay aligned
+const TIMESTAMP_FORMAT: &[BorrowedFormatItem] =
+    format_description!("[year]-[month]-[day]T[hour]:[minute]:[second].[subsecond digits:3]Z");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    /// Also the statements as they are built
+    Debug,
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LogLevel::Error => write!(f, "error"),
+            LogLevel::Warn => write!(f, "warn"),
+            LogLevel::Info => write!(f, "info"),
+            LogLevel::Debug => write!(f, "debug"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Colored lines on the console, plain lines with a timestamp in the log file
+    Text,
+    /// One JSON object per event and line, on the console and in the log file
+    Json,
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LogFormat::Text => write!(f, "text"),
+            LogFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// Where an event happened, every field is optional.
+#[derive(Debug, Clone, Default)]
+pub struct LogContext {
+    pub worker: Option<usize>,
+    pub table: Option<String>,
+    pub index: Option<String>,
+    pub elapsed: Option<Duration>,
+}
+
+impl LogContext {
+    pub fn with_elapsed(&self, elapsed: Duration) -> Self {
+        LogContext {
+            elapsed: Some(elapsed),
+            ..self.clone()
+        }
+    }
+}
+
+pub struct LoggerOptions<'a> {
+    pub level: LogLevel,
+    pub format: LogFormat,
+    pub log_file: Option<&'a str>,
+    pub quiet: bool,
+    pub no_color: bool,
+}
+
+struct Logger {
+    level: LogLevel,
+    format: LogFormat,
+    quiet: bool,
+    log_file: Option<Mutex<File>>,
+}
+
+/// Sets up logging once at startup; until then events go to the console as text.
+pub fn init(options: LoggerOptions) -> Result<()> {
+    if options.no_color || options.format == LogFormat::Json {
+        colored::control::set_override(false);
+    }
+    let log_file = match options.log_file {
+        Some(log_file) => Some(Mutex::new(
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(log_file)
+                .with_context(|| format!("Cannot open log file <{}>", log_file))?,
+        )),
+        None => None,
+    };
+    let _ = LOGGER.set(Logger {
+        level: options.level,
+        format: options.format,
+        quiet: options.quiet,
+        log_file,
+    });
+    Ok(())
+}
+
+/// Banners, separators and multi-line reports are only printed for a person watching
+/// the console, not with `--quiet` or `--log-format json`.
+pub fn is_interactive() -> bool {
+    LOGGER
+        .get()
+        .is_none_or(|logger| !logger.quiet && logger.format == LogFormat::Text)
+}
+
+/// Console output that is not an event: banners and separators.
+pub fn print_interactive(text: impl fmt::Display) {
+    if is_interactive() {
+        println!("{}", text);
+    }
+}
+
+pub fn log(level: LogLevel, context: Option<&LogContext>, message: fmt::Arguments) {
+    let default_logger;
+    let logger = match LOGGER.get() {
+        Some(logger) => logger,
+        None => {
+            default_logger = Logger {
+                level: LogLevel::Info,
+                format: LogFormat::Text,
+                quiet: false,
+                log_file: None,
+            };
+            &default_logger
+        }
+    };
+    if level > logger.level {
+        return;
+    }
+    let message = message.to_string();
+    let message = message.trim_end_matches('\n');
+    let context = context.cloned().unwrap_or_default();
+    if let Some(line) = logger.get_console_line(level, &context, message) {
+        if level <= LogLevel::Warn {
+            eprintln!("{}", line);
+        } else {
+            println!("{}", line);
+        }
+    }
+    if let Some(log_file) = &logger.log_file
+        && let Some(line) = logger.get_file_line(level, &context, message)
+        && let Ok(mut log_file) = log_file.lock()
+    {
+        // one write per event, so lines of concurrent workers never mix
+        let _ = log_file.write_all(format!("{}\n", line).as_bytes());
+    }
+}
+
+impl Logger {
+    /// None for events above the level and, with `--quiet`, for everything but warnings
+    /// and errors.
+    fn get_console_line(
+        &self,
+        level: LogLevel,
+        context: &LogContext,
+        message: &str,
+    ) -> Option<String> {
+        if level > self.level || self.quiet && level > LogLevel::Warn {
+            return None;
+        }
+        Some(match self.format {
+            LogFormat::Text => match context.worker {
+                // the worker tells apart the lines of builds running side by side
+                Some(worker) => format!("[worker {}] {}", worker, message),
+                None => message.to_string(),
+            },
+            LogFormat::Json => format_json(level, context, message),
+        })
+    }
+
+    /// None for events above the level, `--quiet` only applies to the console.
+    fn get_file_line(
+        &self,
+        level: LogLevel,
+        context: &LogContext,
+        message: &str,
+    ) -> Option<String> {
+        if level > self.level {
+            return None;
+        }
+        Some(match self.format {
+            LogFormat::Text => format_text(level, context, message),
+            LogFormat::Json => format_json(level, context, message),
+        })
+    }
+}
+
+/// Every line of a multi-line message gets the header, so each one can be grepped for.
+fn format_text(level: LogLevel, context: &LogContext, message: &str) -> String {
+    let mut header = format!(
+        "{} {:<5}",
+        get_timestamp(),
+        level.to_string().to_uppercase()
+    );
+    if let Some(worker) = context.worker {
+        header.push_str(&format!(" worker={}", worker));
+    }
+    if let Some(table) = &context.table {
+        header.push_str(&format!(" table={}", table));
+    }
+    if let Some(index) = &context.index {
+        header.push_str(&format!(" index={}", index));
+    }
+    if let Some(elapsed) = context.elapsed {
+        header.push_str(&format!(" elapsed={:.2?}", elapsed));
+    }
+    strip_colors(message)
+        .lines()
+        .map(|line| format!("{} {}", header, line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn format_json(level: LogLevel, context: &LogContext, message: &str) -> String {
+    let mut event = Map::new();
+    event.insert("timestamp".to_string(), json!(get_timestamp()));
+    event.insert("level".to_string(), json!(level.to_string()));
+    if let Some(worker) = context.worker {
+        event.insert("worker".to_string(), json!(worker));
+    }
+    if let Some(table) = &context.table {
+        event.insert("table".to_string(), json!(table));
+    }
+    if let Some(index) = &context.index {
+        event.insert("index".to_string(), json!(index));
+    }
+    if let Some(elapsed) = context.elapsed {
+        event.insert("elapsed_ms".to_string(), json!(elapsed.as_millis() as u64));
+    }
+    event.insert("message".to_string(), json!(strip_colors(message)));
+    Value::Object(event).to_string()
+}
+
+fn get_timestamp() -> String {
+    OffsetDateTime::now_utc()
+        .format(TIMESTAMP_FORMAT)
+        .unwrap_or_default()
+}
+
+/// Messages are formatted with `colored` for the console; files get them without the
+/// ANSI escape sequences.
+fn strip_colors(message: &str) -> String {
+    let mut plain = String::with_capacity(message.len());
+    let mut chars = message.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            // CSI sequences like ESC[1;31m end with a letter
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            plain.push(c);
+        }
+    }
+    plain
+}
+
+#[macro_export]
+macro_rules! log_event {
+    ($level:expr, context: $context:expr, $($arg:tt)+) => {
+        $crate::logger::logger::log($level, Some($context), format_args!($($arg)+))
+    };
+    ($level:expr, $($arg:tt)+) => {
+        $crate::logger::logger::log($level, None, format_args!($($arg)+))
+    };
+}
+
+#[macro_export]
+macro_rules! log_error {
+    ($($arg:tt)+) => { $crate::log_event!($crate::logger::logger::LogLevel::Error, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! log_warn {
+    ($($arg:tt)+) => { $crate::log_event!($crate::logger::logger::LogLevel::Warn, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! log_info {
+    ($($arg:tt)+) => { $crate::log_event!($crate::logger::logger::LogLevel::Info, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! log_debug {
+    ($($arg:tt)+) => { $crate::log_event!($crate::logger::logger::LogLevel::Debug, $($arg)+) };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn logger(level: LogLevel, format: LogFormat, quiet: bool) -> Logger {
+        Logger {
+            level,
+            format,
+            quiet,
+            log_file: None,
+        }
+    }
+
+    fn context() -> LogContext {
+        LogContext {
+            worker: Some(2),
+            table: Some("public.t".to_string()),
+            index: Some("ix_t_b".to_string()),
+            elapsed: Some(Duration::from_millis(1500)),
+        }
+    }
+
+    #[test]
+    fn events_above_the_level_are_left_out() {
+        let logger = logger(LogLevel::Warn, LogFormat::Text, false);
+        let context = LogContext::default();
+        for (level, logged) in [
+            (LogLevel::Error, true),
+            (LogLevel::Warn, true),
+            (LogLevel::Info, false),
+            (LogLevel::Debug, false),
+        ] {
+            let console_line = logger.get_console_line(level, &context, "message");
+            let file_line = logger.get_file_line(level, &context, "message");
+            assert_eq!(console_line.is_some(), logged, "{}", level);
+            assert_eq!(file_line.is_some(), logged, "{}", level);
+        }
+        let logger = self::logger(LogLevel::Debug, LogFormat::Text, false);
+        assert!(
+            logger
+                .get_console_line(LogLevel::Debug, &context, "message")
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn quiet_only_keeps_warnings_and_errors_on_the_console() {
+        let logger = logger(LogLevel::Info, LogFormat::Text, true);
+        let context = LogContext::default();
+        assert_eq!(
+            logger.get_console_line(LogLevel::Warn, &context, "careful"),
+            Some("careful".to_string())
+        );
+        assert_eq!(
+            logger.get_console_line(LogLevel::Info, &context, "done"),
+            None
+        );
+        assert!(
+            logger
+                .get_file_line(LogLevel::Info, &context, "done")
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn text_format_prefixes_the_worker_on_the_console_and_every_field_in_the_file() {
+        let logger = logger(LogLevel::Info, LogFormat::Text, false);
+        assert_eq!(
+            logger.get_console_line(LogLevel::Info, &context(), "built"),
+            Some("[worker 2] built".to_string())
+        );
+        let file_line = logger
+            .get_file_line(
+                LogLevel::Info,
+                &context(),
+                "\u{1b}[32mfirst\u{1b}[0m\nsecond",
+            )
+            .unwrap();
+        let lines: Vec<&str> = file_line.lines().collect();
+        assert_eq!(lines.len(), 2);
+        for (line, message) in lines.iter().zip(["first", "second"]) {
+            // 2026-10-19T04:13:26.905Z
+            assert_eq!(line.as_bytes()[23], b'Z', "{}", line);
+            assert_eq!(
+                &line[24..],
+                format!(
+                    " INFO  worker=2 table=public.t index=ix_t_b elapsed=1.50s {}",
+                    message
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn json_format_writes_one_object_per_event() {
+        let logger = logger(LogLevel::Info, LogFormat::Json, false);
+        let console_line = logger
+            .get_console_line(LogLevel::Error, &context(), "\u{1b}[31mfailed\u{1b}[0m")
+            .unwrap();
+        let file_line = logger
+            .get_file_line(LogLevel::Error, &context(), "\u{1b}[31mfailed\u{1b}[0m")
+            .unwrap();
+        let file_event: Value = serde_json::from_str(&file_line).unwrap();
+        assert_eq!(file_event["message"], "failed");
+        let event: Value = serde_json::from_str(&console_line).unwrap();
+        assert_eq!(event["level"], "error");
+        assert_eq!(event["worker"], 2);
+        assert_eq!(event["table"], "public.t");
+        assert_eq!(event["index"], "ix_t_b");
+        assert_eq!(event["elapsed_ms"], 1500);
+        assert_eq!(event["message"], "failed");
+        assert!(event["timestamp"].as_str().unwrap().ends_with('Z'));
+
+        let event = logger
+            .get_console_line(LogLevel::Info, &LogContext::default(), "done")
+            .unwrap();
+        let event: Value = serde_json::from_str(&event).unwrap();
+        assert_eq!(event.as_object().unwrap().len(), 3);
+    }
+}
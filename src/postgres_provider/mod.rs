@@ -1 +1 @@
-pub mod postgres_provider;
\ No newline at end of file
+pub mod postgres_provider;
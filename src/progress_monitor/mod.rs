@@ -0,0 +1 @@
+pub mod progress_monitor;
@@ -0,0 +1,147 @@
+use crate::build_registry::build_registry::BuildRegistry;
+use crate::helpers::print_separator;
+use crate::logger::logger::LogContext;
+use crate::postgres_provider::postgres_provider::PostgresPool;
+use crate::shared::duration_utils::format_elapsed;
+use crate::{log_info, log_warn};
+use colored::Colorize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+const PROGRESS_BAR_WIDTH: usize = 40;
+
+const PROGRESS_QUERY: &str = "SELECT pid, phase, blocks_done, blocks_total, tuples_done, tuples_total \
+    FROM pg_stat_progress_create_index WHERE pid = ANY($1)";
+
+struct BuildProgress {
+    phase: String,
+    blocks_done: i64,
+    blocks_total: i64,
+    tuples_done: i64,
+    tuples_total: i64,
+}
+
+/// Periodically prints the state of every running build from
+/// `pg_stat_progress_create_index` and an overall progress bar.
+/// Needs one pool connection on top of the worker connections.
+pub fn start_progress_monitor(
+    postgres_pool: PostgresPool,
+    build_registry: Arc<BuildRegistry>,
+    interval: Duration,
+) -> Option<JoinHandle<()>> {
+    if interval.is_zero() {
+        return None;
+    }
+    Some(tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.tick().await; // first tick completes immediately
+        loop {
+            ticker.tick().await;
+            let running_builds = build_registry.get_running_builds();
+            let pids: Vec<i32> = running_builds.iter().map(|(pid, _)| *pid).collect();
+            let progress = match get_build_progress(&postgres_pool, &pids).await {
+                Ok(progress) => progress,
+                Err(e) => {
+                    log_warn!(
+                        "{}: {}",
+                        "Failed to read index build progress".yellow(),
+                        e.to_string().yellow()
+                    );
+                    HashMap::new()
+                }
+            };
+            print_separator();
+            log_info!("{}", format_overall_progress(&build_registry));
+            for (pid, running_build) in running_builds {
+                let log_context = LogContext {
+                    index: Some(running_build.label.clone()),
+                    elapsed: Some(running_build.started.elapsed()),
+                    ..LogContext::default()
+                };
+                let elapsed = format_elapsed(&running_build.started.elapsed());
+                match progress.get(&pid) {
+                    Some(p) => log_info!(
+                        context: &log_context,
+                        "  [{}] <{}> {}: blocks {}, tuples {}, elapsed {}",
+                        pid,
+                        running_build.label.yellow(),
+                        p.phase.cyan(),
+                        format_counter(p.blocks_done, p.blocks_total),
+                        format_counter(p.tuples_done, p.tuples_total),
+                        elapsed
+                    ),
+                    // ALTER TABLE ... ADD CONSTRAINT and lock waits do not show up in the view
+                    None => log_info!(
+                        context: &log_context,
+                        "  [{}] <{}> {}, elapsed {}",
+                        pid,
+                        running_build.label.yellow(),
+                        "waiting or not reporting progress".cyan(),
+                        elapsed
+                    ),
+                }
+            }
+            print_separator();
+        }
+    }))
+}
+
+async fn get_build_progress(
+    postgres_pool: &PostgresPool,
+    pids: &[i32],
+) -> anyhow::Result<HashMap<i32, BuildProgress>> {
+    let mut progress = HashMap::new();
+    if pids.is_empty() {
+        return Ok(progress);
+    }
+    let postgres_connection = postgres_pool.get().await?;
+    let rows = postgres_connection.query(PROGRESS_QUERY, &[&pids]).await?;
+    for row in rows {
+        progress.insert(
+            row.get(0),
+            BuildProgress {
+                phase: row.get(1),
+                blocks_done: row.get(2),
+                blocks_total: row.get(3),
+                tuples_done: row.get(4),
+                tuples_total: row.get(5),
+            },
+        );
+    }
+    Ok(progress)
+}
+
+fn format_overall_progress(build_registry: &BuildRegistry) -> String {
+    let total = build_registry.get_total_tasks();
+    let finished = build_registry.get_finished_tasks();
+    let ratio = if total == 0 {
+        1.0
+    } else {
+        finished as f64 / total as f64
+    };
+    let filled = (ratio * PROGRESS_BAR_WIDTH as f64).round() as usize;
+    let elapsed = format_elapsed(&build_registry.get_started().elapsed());
+    format!(
+        "Progress: [{}{}] {:>3.0}% {}/{} indexes, elapsed {}",
+        "#".repeat(filled).green(),
+        "-".repeat(PROGRESS_BAR_WIDTH - filled),
+        ratio * 100.0,
+        finished,
+        total,
+        elapsed
+    )
+}
+
+fn format_counter(done: i64, total: i64) -> String {
+    if total <= 0 {
+        return format!("{}", done);
+    }
+    format!(
+        "{}/{} ({})",
+        done,
+        total,
+        format!("{:.1}%", done as f64 * 100.0 / total as f64).green()
+    )
+}
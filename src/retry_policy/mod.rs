@@ -0,0 +1 @@
+pub mod retry_policy;
@@ -0,0 +1,291 @@
+use bb8::RunError;
+use rand::Rng;
+use std::fmt;
+use std::time::Duration;
+use tokio_postgres::error::SqlState;
+
+const MAX_BACKOFF_IN_SECONDS: u64 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    Transient,
+    /// the server went away or could not be reached, retried like a transient error
+    Connection,
+    Permanent,
+}
+
+impl fmt::Display for ErrorClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorClass::Transient => write!(f, "transient"),
+            ErrorClass::Connection => write!(f, "connection"),
+            ErrorClass::Permanent => write!(f, "permanent"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new(max_retries: u32, base_delay_in_ms: u64) -> Self {
+        RetryPolicy {
+            max_retries,
+            base_delay: Duration::from_millis(base_delay_in_ms),
+            max_delay: Duration::from_secs(MAX_BACKOFF_IN_SECONDS),
+        }
+    }
+
+    pub fn get_max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    pub fn get_base_delay(&self) -> Duration {
+        self.base_delay
+    }
+
+    /// `attempt` is 1-based: the first execution of a statement is attempt 1.
+    pub fn should_retry(&self, attempt: u32, error_class: ErrorClass) -> bool {
+        error_class != ErrorClass::Permanent && attempt <= self.max_retries
+    }
+
+    /// Exponential backoff with "equal jitter": half of the delay is fixed,
+    /// the other half is random, so workers that failed together (deadlock
+    /// victims) do not retry in lockstep.
+    pub fn get_backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(16);
+        let delay = self
+            .base_delay
+            .saturating_mul(1 << exponent)
+            .min(self.max_delay);
+        let half = delay / 2;
+        let jitter_in_ms = rand::rng().random_range(0..=half.as_millis() as u64);
+        half + Duration::from_millis(jitter_in_ms)
+    }
+}
+
+impl fmt::Display for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "max retries {}, base delay {:?}, max delay {:?}",
+            self.max_retries, self.base_delay, self.max_delay
+        )
+    }
+}
+
+pub fn classify_postgres_error(error: &tokio_postgres::Error) -> ErrorClass {
+    if error.is_closed() {
+        return ErrorClass::Connection;
+    }
+    match error.code() {
+        Some(code) => classify_sqlstate(code),
+        // without a SQLSTATE only a broken connection is worth another attempt,
+        // row conversion and protocol errors fail the same way every time
+        None if is_io_error(error) => ErrorClass::Connection,
+        None => ErrorClass::Permanent,
+    }
+}
+
+fn is_io_error(error: &tokio_postgres::Error) -> bool {
+    std::error::Error::source(error).is_some_and(|source| source.is::<std::io::Error>())
+}
+
+pub fn classify_pool_error(error: &RunError<tokio_postgres::Error>) -> ErrorClass {
+    match error {
+        RunError::User(e) => classify_postgres_error(e),
+        RunError::TimedOut => ErrorClass::Connection,
+    }
+}
+
+pub fn classify_sqlstate(code: &SqlState) -> ErrorClass {
+    let transient = [
+        SqlState::T_R_SERIALIZATION_FAILURE,
+        SqlState::T_R_DEADLOCK_DETECTED,
+        SqlState::LOCK_NOT_AVAILABLE,
+    ];
+    let connection = [
+        SqlState::ADMIN_SHUTDOWN,
+        SqlState::CRASH_SHUTDOWN,
+        SqlState::CANNOT_CONNECT_NOW,
+        SqlState::TOO_MANY_CONNECTIONS,
+    ];
+    // class 08 - Connection Exception
+    if code.code().starts_with("08") || connection.contains(code) {
+        ErrorClass::Connection
+    } else if transient.contains(code) {
+        ErrorClass::Transient
+    } else {
+        ErrorClass::Permanent
+    }
+}
+
+/// Classifies the error of a query that did not go through the import workers,
+/// e.g. reading the source tables from SQL Server, by the first database error in its chain.
+pub fn classify_query_error(error: &anyhow::Error) -> ErrorClass {
+    for cause in error.chain() {
+        if let Some(e) = cause.downcast_ref::<tokio_postgres::Error>() {
+            return classify_postgres_error(e);
+        }
+        if let Some(e) = cause.downcast_ref::<RunError<tokio_postgres::Error>>() {
+            return classify_pool_error(e);
+        }
+        if cause.is::<std::io::Error>()
+            || matches!(
+                cause.downcast_ref::<tiberius::error::Error>(),
+                Some(tiberius::error::Error::Io { .. })
+            )
+        {
+            return ErrorClass::Connection;
+        }
+    }
+    ErrorClass::Permanent
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutKind {
+    Build,
+    Lock,
+}
+
+impl fmt::Display for TimeoutKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TimeoutKind::Build => write!(f, "build timeout"),
+            TimeoutKind::Lock => write!(f, "lock timeout"),
+        }
+    }
+}
+
+/// Both timeouts are reported by PostgreSQL as "canceling statement due to ...",
+/// the SQLSTATE alone does not tell them apart from a cancel request or a NOWAIT failure.
+pub fn classify_timeout(error: &tokio_postgres::Error) -> Option<TimeoutKind> {
+    let db_error = error.as_db_error()?;
+    let message = db_error.message();
+    match db_error.code() {
+        code if *code == SqlState::QUERY_CANCELED && message.contains("statement timeout") => {
+            Some(TimeoutKind::Build)
+        }
+        code if *code == SqlState::LOCK_NOT_AVAILABLE && message.contains("lock timeout") => {
+            Some(TimeoutKind::Lock)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_sqlstate_separates_lock_and_connection_errors() {
+        for code in [
+            SqlState::T_R_DEADLOCK_DETECTED,
+            SqlState::T_R_SERIALIZATION_FAILURE,
+            SqlState::LOCK_NOT_AVAILABLE,
+        ] {
+            assert_eq!(
+                classify_sqlstate(&code),
+                ErrorClass::Transient,
+                "{:?}",
+                code
+            );
+        }
+        for code in [
+            SqlState::ADMIN_SHUTDOWN,
+            SqlState::TOO_MANY_CONNECTIONS,
+            SqlState::CONNECTION_FAILURE,
+            SqlState::from_code("08000"),
+        ] {
+            assert_eq!(
+                classify_sqlstate(&code),
+                ErrorClass::Connection,
+                "{:?}",
+                code
+            );
+        }
+        for code in [
+            SqlState::UNIQUE_VIOLATION,
+            SqlState::SYNTAX_ERROR,
+            SqlState::UNDEFINED_TABLE,
+            SqlState::QUERY_CANCELED,
+            SqlState::INSUFFICIENT_PRIVILEGE,
+        ] {
+            assert_eq!(
+                classify_sqlstate(&code),
+                ErrorClass::Permanent,
+                "{:?}",
+                code
+            );
+        }
+    }
+
+    #[test]
+    fn should_retry_transient_errors_up_to_max_retries() {
+        let retry_policy = RetryPolicy::new(2, 100);
+        assert!(retry_policy.should_retry(1, ErrorClass::Transient));
+        assert!(retry_policy.should_retry(2, ErrorClass::Transient));
+        assert!(!retry_policy.should_retry(3, ErrorClass::Transient));
+        assert!(retry_policy.should_retry(2, ErrorClass::Connection));
+        assert!(!retry_policy.should_retry(3, ErrorClass::Connection));
+        assert!(!retry_policy.should_retry(1, ErrorClass::Permanent));
+        assert!(!RetryPolicy::new(0, 100).should_retry(1, ErrorClass::Transient));
+    }
+
+    #[test]
+    fn classify_query_error_finds_the_database_error_in_the_chain() {
+        let io_error =
+            anyhow::Error::new(std::io::Error::from(std::io::ErrorKind::ConnectionReset))
+                .context("Failed to read the source tables");
+        assert_eq!(classify_query_error(&io_error), ErrorClass::Connection);
+        let pool_error = anyhow::Error::new(RunError::<tokio_postgres::Error>::TimedOut)
+            .context("Failed to get a connection");
+        assert_eq!(classify_query_error(&pool_error), ErrorClass::Connection);
+        let tiberius_error = anyhow::Error::new(tiberius::error::Error::Io {
+            kind: std::io::ErrorKind::BrokenPipe,
+            message: "broken pipe".to_string(),
+        });
+        assert_eq!(
+            classify_query_error(&tiberius_error),
+            ErrorClass::Connection
+        );
+        let other_error = anyhow::anyhow!("Invalid object name 'dbo.t'");
+        assert_eq!(classify_query_error(&other_error), ErrorClass::Permanent);
+    }
+
+    #[test]
+    fn get_backoff_doubles_with_half_of_it_random() {
+        let retry_policy = RetryPolicy::new(10, 1000);
+        for (attempt, delay_in_ms) in [(1, 1000), (2, 2000), (3, 4000), (6, 32000)] {
+            for _ in 0..20 {
+                let backoff = retry_policy.get_backoff(attempt);
+                assert!(
+                    backoff >= Duration::from_millis(delay_in_ms / 2)
+                        && backoff <= Duration::from_millis(delay_in_ms),
+                    "attempt {}: {:?}",
+                    attempt,
+                    backoff
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn get_backoff_stops_at_the_max_delay() {
+        let retry_policy = RetryPolicy::new(10, 60000);
+        for attempt in [2, 10, 100, u32::MAX] {
+            let backoff = retry_policy.get_backoff(attempt);
+            assert!(
+                backoff >= Duration::from_secs(MAX_BACKOFF_IN_SECONDS / 2)
+                    && backoff <= Duration::from_secs(MAX_BACKOFF_IN_SECONDS),
+                "attempt {}: {:?}",
+                attempt,
+                backoff
+            );
+        }
+    }
+}
@@ -0,0 +1,416 @@
+use crate::config_provider::Config;
+use crate::import_summary::import_summary::{ImportStatus, ImportSummary, IndexImportResult};
+use crate::settings::settings::Settings;
+use crate::version::{PRODUCT_NAME, VERSION_MAJOR, VERSION_MINOR, VERSION_PATCH};
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde_json::{Value, json};
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+const REDACTED: &str = "********";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    Json,
+    /// JUnit XML, every index is a test case
+    Junit,
+}
+
+impl ReportFormat {
+    /// `.xml` files get JUnit, everything else JSON.
+    pub fn from_path(path: &str) -> Self {
+        match Path::new(path).extension().and_then(|e| e.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("xml") => ReportFormat::Junit,
+            _ => ReportFormat::Json,
+        }
+    }
+}
+
+impl fmt::Display for ReportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReportFormat::Json => write!(f, "json"),
+            ReportFormat::Junit => write!(f, "junit"),
+        }
+    }
+}
+
+/// Everything a report is rendered from: the run itself and the results the workers produced.
+pub struct RunReport<'a> {
+    pub command: &'a str,
+    pub started_at: OffsetDateTime,
+    pub settings: &'a Settings,
+    pub config: &'a Config,
+    pub summary: &'a ImportSummary,
+}
+
+impl RunReport<'_> {
+    pub async fn write(&self, path: &str, format: ReportFormat) -> Result<()> {
+        let content = match format {
+            ReportFormat::Json => serde_json::to_string_pretty(&self.to_json()?)?,
+            ReportFormat::Junit => self.to_junit(),
+        };
+        tokio::fs::write(path, content)
+            .await
+            .with_context(|| format!("Failed to write report file: {}", path))
+    }
+
+    fn to_json(&self) -> Result<Value> {
+        let mut config = serde_json::to_value(self.config)?;
+        redact_secrets(&mut config);
+        let summary = self.summary;
+        let retried = summary
+            .get_results_as_ref()
+            .iter()
+            .filter(|r| r.get_retries() > 0)
+            .count();
+        Ok(json!({
+            "product": PRODUCT_NAME,
+            "version": format!("{}.{}.{}", VERSION_MAJOR, VERSION_MINOR, VERSION_PATCH),
+            "command": self.command,
+            "started_at": format_timestamp(self.started_at),
+            "finished_at": format_timestamp(OffsetDateTime::now_utc()),
+            "duration_ms": to_millis(summary.get_elapsed()),
+            "success": summary.is_success(),
+            "settings": get_settings_json(self.settings),
+            "config": config,
+            "counts": {
+                "total": summary.get_results_as_ref().len(),
+                "succeeded": summary.get_count(ImportStatus::Succeeded),
+                "failed": summary.get_count(ImportStatus::Failed),
+                "timed_out": summary.get_count(ImportStatus::TimedOut),
+                "cancelled": summary.get_count(ImportStatus::Cancelled),
+                "skipped": summary.get_count(ImportStatus::Skipped),
+                "unsupported": summary.get_count(ImportStatus::Unsupported),
+                "retried": retried,
+            },
+            "failed_tables": summary.get_failed_transaction_groups(),
+            "indexes": summary
+                .get_results_as_ref()
+                .iter()
+                .map(get_result_json)
+                .collect::<Vec<Value>>(),
+        }))
+    }
+
+    fn to_junit(&self) -> String {
+        let summary = self.summary;
+        let results = summary.get_results_as_ref();
+        let failures =
+            summary.get_count(ImportStatus::Failed) + summary.get_count(ImportStatus::TimedOut);
+        let skipped =
+            summary.get_count(ImportStatus::Skipped) + summary.get_count(ImportStatus::Unsupported);
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(&format!(
+            "<testsuites name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\">\n",
+            escape_xml(PRODUCT_NAME),
+            results.len(),
+            failures,
+            summary.get_count(ImportStatus::Cancelled),
+            skipped,
+            summary.get_elapsed().as_secs_f64()
+        ));
+        xml.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\" timestamp=\"{}\">\n",
+            escape_xml(self.command),
+            results.len(),
+            failures,
+            summary.get_count(ImportStatus::Cancelled),
+            skipped,
+            summary.get_elapsed().as_secs_f64(),
+            format_timestamp(self.started_at)
+        ));
+        for result in results {
+            xml.push_str(&get_result_junit(result));
+        }
+        xml.push_str("  </testsuite>\n");
+        xml.push_str("</testsuites>\n");
+        xml
+    }
+}
+
+fn get_settings_json(settings: &Settings) -> Value {
+    let retry_policy = settings.get_retry_policy_as_ref();
+    json!({
+        "profile": settings.get_profile_as_ref(),
+        "source_schema": settings.get_source_schema_name_as_ref(),
+        "source_table": settings.get_source_table_name_as_ref(),
+        "output_dir": settings.get_output_dir_as_ref(),
+        "input_dir": settings.get_input_dir_as_ref(),
+        "threads": settings.get_threads(),
+        "build_timeout_ms": to_millis(settings.get_build_timeout()),
+        "lock_timeout_ms": to_millis(settings.get_lock_timeout()),
+        "max_retries": retry_policy.get_max_retries(),
+        "retry_delay_ms": to_millis(retry_policy.get_base_delay()),
+        "schedule": settings.get_schedule_strategy().to_string(),
+        "progress_interval_ms": to_millis(settings.get_progress_interval()),
+        "concurrently": settings.get_concurrently(),
+        "transactional": settings.get_transaction_mode().map(|m| m.to_string()),
+        "analyze": settings.get_analyze(),
+        "drop_extra": settings.get_drop_extra(),
+    })
+}
+
+fn get_result_json(result: &IndexImportResult) -> Value {
+    let last_error = result.attempts.last().filter(|a| a.error.is_some());
+    json!({
+        "file_name": result.file_name,
+        "source_name": result.source_name,
+        "target_name": result.target_name,
+        "table": result.schema_table,
+        "kind": result.kind.to_string(),
+        "statement": result.statement,
+        "status": result.get_status().to_string(),
+        "unsupported": result.unsupported,
+        "rollback": result.rollback,
+        "transaction_group": result.transaction_group,
+        "error": last_error.map(|a| json!({
+            "message": a.error,
+            "sqlstate": a.sqlstate,
+            "class": a.error_class.map(|c| c.to_string()),
+            "timeout": a.timeout.map(|t| t.to_string()),
+        })),
+        "duration_ms": to_millis(result.elapsed),
+        "attempts": result.attempts.iter().map(|a| json!({
+            "attempt": a.attempt,
+            "duration_ms": to_millis(a.elapsed),
+            "error": a.error,
+            "sqlstate": a.sqlstate,
+        })).collect::<Vec<Value>>(),
+    })
+}
+
+fn get_result_junit(result: &IndexImportResult) -> String {
+    let mut xml = format!(
+        "    <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\"",
+        escape_xml(result.schema_table.as_deref().unwrap_or("")),
+        escape_xml(&result.source_name),
+        result.elapsed.as_secs_f64()
+    );
+    let last_attempt = result.attempts.last();
+    let message = last_attempt
+        .map(|a| {
+            format!(
+                "[{}] {}",
+                a.sqlstate.as_deref().unwrap_or("-----"),
+                a.error.as_deref().unwrap_or_default()
+            )
+        })
+        .unwrap_or_default();
+    let element = match result.get_status() {
+        ImportStatus::Succeeded => {
+            xml.push_str(">\n");
+            None
+        }
+        ImportStatus::Skipped => {
+            xml.push_str(">\n      <skipped message=\"never started\"/>\n");
+            None
+        }
+        ImportStatus::Unsupported => {
+            xml.push_str(&format!(
+                ">\n      <skipped message=\"{}\"/>\n",
+                escape_xml(result.unsupported.as_deref().unwrap_or_default())
+            ));
+            None
+        }
+        ImportStatus::Failed => Some(("failure", "failed")),
+        ImportStatus::TimedOut => Some(("failure", "timed out")),
+        ImportStatus::Cancelled => Some(("error", "cancelled")),
+    };
+    if let Some((tag, kind)) = element {
+        xml.push_str(&format!(
+            ">\n      <{} message=\"{}\" type=\"{}\">{}</{}>\n",
+            tag,
+            escape_xml(&message),
+            kind,
+            escape_xml(&result.statement),
+            tag
+        ));
+    }
+    xml.push_str(&format!(
+        "      <system-out>{}</system-out>\n",
+        escape_xml(&format!(
+            "{} -> {}, attempts: {}",
+            result.file_name,
+            result.target_name.as_deref().unwrap_or("-"),
+            result.attempts.len()
+        ))
+    ));
+    xml.push_str("    </testcase>\n");
+    xml
+}
+
+/// Replaces every `password` value in the serialized config, wherever it is nested.
+fn redact_secrets(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if key.to_lowercase().contains("password") && !value.is_null() {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    redact_secrets(value);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(redact_secrets),
+        _ => {}
+    }
+}
+
+fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // not allowed in XML 1.0 at all
+            c if (c as u32) < 0x20 && !matches!(c, '\t' | '\n' | '\r') => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn format_timestamp(timestamp: OffsetDateTime) -> String {
+    timestamp.format(&Rfc3339).unwrap_or_default()
+}
+
+fn to_millis(duration: Duration) -> u64 {
+    duration.as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clap_parser::clap_parser::Cli;
+    use crate::config_provider::ConfigProvider;
+    use crate::importer::importer::IndexTask;
+    use clap::{CommandFactory, FromArgMatches};
+
+    const CONFIG: &str = r#"
+[source_database]
+url = "Server=mssql.example.com;Database=sales;User Id=sa;Password=source-secret"
+
+[target_database]
+host = "pg.example.com"
+database = "sales"
+user = "postgres"
+password = "target-secret"
+"#;
+
+    fn write_report(format: ReportFormat) -> String {
+        let config = ConfigProvider::new("test.toml")
+            .parse_config(CONFIG.to_string(), None)
+            .unwrap();
+        let matches = Cli::command()
+            .try_get_matches_from(["pg_index_import", "import"])
+            .unwrap();
+        let cli = Cli::from_arg_matches(&matches).unwrap();
+        let settings = Settings::from_args(&cli, &matches, &config).unwrap();
+        let mut succeeded = IndexImportResult::new(&IndexTask::new(
+            "INPUT/dbo/orders/IX_A.sql",
+            Some("dbo.orders".to_string()),
+            "CREATE INDEX ix_a ON dbo.orders (a);".to_string(),
+        ));
+        succeeded.add_attempt(Duration::from_millis(1500), None);
+        let mut failed = IndexImportResult::new(&IndexTask::new(
+            "INPUT/dbo/orders/IX_B.sql",
+            Some("dbo.orders".to_string()),
+            "CREATE INDEX ix_b ON dbo.orders (b) WHERE b <> '<none>';".to_string(),
+        ));
+        failed.add_attempt(Duration::from_millis(10), Some("first".to_string()));
+        failed.add_attempt(
+            Duration::from_millis(20),
+            Some("a \"quoted\" error".to_string()),
+        );
+        let mut summary = ImportSummary::new();
+        summary.add_results(vec![succeeded, failed]);
+        summary.set_elapsed(Duration::from_secs(2));
+        let report = RunReport {
+            command: "import",
+            started_at: OffsetDateTime::UNIX_EPOCH,
+            settings: &settings,
+            config: &config,
+            summary: &summary,
+        };
+        match format {
+            ReportFormat::Json => serde_json::to_string(&report.to_json().unwrap()).unwrap(),
+            ReportFormat::Junit => report.to_junit(),
+        }
+    }
+
+    #[test]
+    fn redact_secrets_replaces_nested_passwords() {
+        let mut value = json!({
+            "password": "secret",
+            "user": "sa",
+            "password_file": null,
+            "nested": {"Password": "secret", "items": [{"db_password": "secret"}]},
+        });
+        redact_secrets(&mut value);
+        assert_eq!(
+            value,
+            json!({
+                "password": REDACTED,
+                "user": "sa",
+                "password_file": null,
+                "nested": {"Password": REDACTED, "items": [{"db_password": REDACTED}]},
+            })
+        );
+    }
+
+    #[test]
+    fn escape_xml_escapes_markup_and_drops_control_characters() {
+        assert_eq!(
+            escape_xml("a < b && c > 'd' \"e\""),
+            "a &lt; b &amp;&amp; c &gt; &apos;d&apos; &quot;e&quot;"
+        );
+        assert_eq!(escape_xml("tab\there\u{1}\u{1b}\r\n"), "tab\there\r\n");
+    }
+
+    #[test]
+    fn json_report_has_counts_and_no_passwords() {
+        let report = write_report(ReportFormat::Json);
+        assert!(!report.contains("secret"), "{}", report);
+        let report: Value = serde_json::from_str(&report).unwrap();
+        assert_eq!(report["command"], "import");
+        assert_eq!(report["started_at"], "1970-01-01T00:00:00Z");
+        assert_eq!(report["duration_ms"], 2000);
+        assert_eq!(report["success"], false);
+        assert_eq!(report["config"]["target_database"]["password"], REDACTED);
+        assert_eq!(report["counts"]["total"], 2);
+        assert_eq!(report["counts"]["succeeded"], 1);
+        assert_eq!(report["counts"]["failed"], 1);
+        assert_eq!(report["counts"]["retried"], 1);
+        let indexes = report["indexes"].as_array().unwrap();
+        assert_eq!(indexes[0]["status"], "succeeded");
+        assert_eq!(indexes[0]["error"], Value::Null);
+        assert_eq!(indexes[1]["status"], "failed");
+        assert_eq!(indexes[1]["error"]["message"], "a \"quoted\" error");
+        assert_eq!(indexes[1]["attempts"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn junit_report_has_a_test_case_per_index() {
+        let report = write_report(ReportFormat::Junit);
+        assert!(report.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"));
+        assert!(report.contains(
+            "<testsuite name=\"import\" tests=\"2\" failures=\"1\" errors=\"0\" skipped=\"0\" time=\"2.000\" timestamp=\"1970-01-01T00:00:00Z\">"
+        ));
+        assert!(report.contains(
+            "    <testcase classname=\"dbo.orders\" name=\"IX_A\" time=\"1.500\">\n      <system-out>"
+        ));
+        assert!(report.contains(
+            "<failure message=\"[-----] a &quot;quoted&quot; error\" type=\"failed\">CREATE INDEX ix_b ON dbo.orders (b) WHERE b &lt;&gt; &apos;&lt;none&gt;&apos;;</failure>"
+        ));
+        assert_eq!(report.matches("<testcase ").count(), 2);
+        assert!(report.ends_with("  </testsuite>\n</testsuites>\n"));
+    }
+}
@@ -0,0 +1,505 @@
+use crate::ddl_parser::ddl_parser::StatementKind;
+use crate::importer::importer::IndexTask;
+use crate::shutdown::shutdown::Shutdown;
+use clap::ValueEnum;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ScheduleStrategy {
+    /// Biggest tables first, so the longest builds do not end up last
+    LargestFirst,
+    /// Smallest tables first, to get many indexes done early
+    SmallestFirst,
+    /// Order in which files are found in the input directory
+    FileOrder,
+}
+
+impl fmt::Display for ScheduleStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScheduleStrategy::LargestFirst => write!(f, "largest-first"),
+            ScheduleStrategy::SmallestFirst => write!(f, "smallest-first"),
+            ScheduleStrategy::FileOrder => write!(f, "file-order"),
+        }
+    }
+}
+
+struct ScheduledTask {
+    index_task: IndexTask,
+    table_size_in_bytes: i64,
+    order: usize,
+}
+
+/// Hands out index builds so that a table never has two builds running at once.
+/// Among the tables that are free, primary keys and unique constraints go first,
+/// then the strategy decides which table is next. With `group_by_table` all
+/// statements of a table are handed out together, in priority order. Foreign keys
+/// wait for the keys of the table they point to. With
+/// `analyze_tables` a table gets an `ANALYZE` once all its statements are done
+/// and at least one of them succeeded. While tables are still streaming in, a foreign key
+/// also waits for the table it points to to arrive.
+pub struct Scheduler {
+    strategy: ScheduleStrategy,
+    group_by_table: bool,
+    analyze_tables: bool,
+    analyze_tasks: HashMap<String, IndexTask>,
+    succeeded_tables: HashSet<String>,
+    pending: Vec<ScheduledTask>,
+    busy_tables: HashSet<String>,
+    /// script position of the constraint drop running on a busy table
+    busy_constraint_drops: HashMap<String, usize>,
+    /// script position of the index or statistics drop running on a busy table
+    busy_drops: HashMap<String, usize>,
+    next_order: usize,
+    /// more tables may still arrive, see `run`
+    streaming: bool,
+    received_tables: HashSet<String>,
+}
+
+/// All statements of one table and the size of the table, as streamed to `Scheduler::run`.
+pub type TableTasks = (Vec<IndexTask>, i64);
+
+impl Scheduler {
+    pub fn new(strategy: ScheduleStrategy) -> Self {
+        Scheduler {
+            strategy,
+            group_by_table: false,
+            analyze_tables: false,
+            analyze_tasks: HashMap::new(),
+            succeeded_tables: HashSet::new(),
+            pending: Vec::new(),
+            busy_tables: HashSet::new(),
+            busy_constraint_drops: HashMap::new(),
+            busy_drops: HashMap::new(),
+            next_order: 0,
+            streaming: false,
+            received_tables: HashSet::new(),
+        }
+    }
+
+    pub fn set_group_by_table(&mut self, group_by_table: bool) {
+        self.group_by_table = group_by_table;
+    }
+
+    pub fn set_analyze_tables(&mut self, analyze_tables: bool) {
+        self.analyze_tables = analyze_tables;
+    }
+
+    pub fn add_task(&mut self, index_task: IndexTask, table_size_in_bytes: i64) {
+        if !self.analyze_tasks.contains_key(&index_task.table_key)
+            && let Some(analyze_task) = index_task.for_analyze()
+        {
+            self.analyze_tasks
+                .insert(index_task.table_key.clone(), analyze_task);
+        }
+        self.received_tables.insert(index_task.table_key.clone());
+        self.pending.push(ScheduledTask {
+            index_task,
+            table_size_in_bytes,
+            order: self.next_order,
+        });
+        self.next_order += 1;
+    }
+
+    pub fn get_task_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Takes the best task whose table is not busy, and with `group_by_table` the
+    /// rest of that table, and marks the table busy.
+    pub fn pop_ready(&mut self) -> Option<Vec<IndexTask>> {
+        // foreign keys that point at each other would wait forever once nothing runs,
+        // unless the table they wait for is still to come
+        let position = self.find_next(true).or_else(|| {
+            (self.busy_tables.is_empty() && !self.streaming).then(|| self.find_next(false))?
+        })?;
+        let scheduled_task = self.pending.remove(position);
+        let table_key = scheduled_task.index_task.table_key.clone();
+        match scheduled_task.index_task.kind {
+            StatementKind::DropConstraint => {
+                self.busy_constraint_drops
+                    .insert(table_key.clone(), scheduled_task.order);
+            }
+            StatementKind::Drop => {
+                self.busy_drops
+                    .insert(table_key.clone(), scheduled_task.order);
+            }
+            _ => {}
+        }
+        let mut index_tasks = vec![scheduled_task.index_task];
+        if self.group_by_table {
+            let (mut same_table, others): (Vec<ScheduledTask>, Vec<ScheduledTask>) = self
+                .pending
+                .drain(..)
+                .partition(|t| t.index_task.table_key == table_key);
+            self.pending = others;
+            same_table.sort_by_key(|t| (t.index_task.kind.get_priority(), t.order));
+            index_tasks.extend(same_table.into_iter().map(|t| t.index_task));
+        }
+        self.busy_tables.insert(table_key);
+        Some(index_tasks)
+    }
+
+    fn find_next(&self, respect_dependencies: bool) -> Option<usize> {
+        // a group is handed out as a whole, so one waiting foreign key holds the table
+        let waiting_tables: HashSet<&str> = self
+            .pending
+            .iter()
+            .filter(|t| respect_dependencies && self.group_by_table && self.is_waiting(t))
+            .map(|t| t.index_task.table_key.as_str())
+            .collect();
+        self.pending
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| !self.busy_tables.contains(&t.index_task.table_key))
+            .filter(|(_, t)| !waiting_tables.contains(t.index_task.table_key.as_str()))
+            .filter(|(_, t)| !respect_dependencies || !self.is_waiting(t))
+            .min_by_key(|(_, t)| {
+                let size_key = match self.strategy {
+                    ScheduleStrategy::LargestFirst => -t.table_size_in_bytes,
+                    ScheduleStrategy::SmallestFirst => t.table_size_in_bytes,
+                    ScheduleStrategy::FileOrder => 0,
+                };
+                (t.index_task.kind.get_priority(), size_key, t.order)
+            })
+            .map(|(position, _)| position)
+    }
+
+    /// A foreign key waits while the table it points to is busy or still has a primary
+    /// key or unique constraint to build. Drops wait for the constraint drops listed
+    /// before them on other tables, since rollback scripts list foreign keys before the
+    /// keys they point to. A materialized view drop also waits for all drops listed
+    /// before it: the script lists the indexes on the view first but does not say
+    /// which table a dropped index belongs to.
+    fn is_waiting(&self, scheduled_task: &ScheduledTask) -> bool {
+        let index_task = &scheduled_task.index_task;
+        if matches!(
+            index_task.kind,
+            StatementKind::Drop
+                | StatementKind::DropMaterializedView
+                | StatementKind::DropConstraint
+        ) {
+            let is_earlier_drop = |table_key: &str, order: usize| {
+                table_key != index_task.table_key && order < scheduled_task.order
+            };
+            let waits_for = |kind: StatementKind| {
+                kind == StatementKind::DropConstraint
+                    || (index_task.kind == StatementKind::DropMaterializedView
+                        && kind == StatementKind::Drop)
+            };
+            return self.pending.iter().any(|t| {
+                waits_for(t.index_task.kind) && is_earlier_drop(&t.index_task.table_key, t.order)
+            }) || self
+                .busy_constraint_drops
+                .iter()
+                .any(|(table_key, order)| is_earlier_drop(table_key, *order))
+                || (index_task.kind == StatementKind::DropMaterializedView
+                    && self
+                        .busy_drops
+                        .iter()
+                        .any(|(table_key, order)| is_earlier_drop(table_key, *order)));
+        }
+        let Some(referenced_table_key) = &index_task.referenced_table_key else {
+            return false;
+        };
+        if *referenced_table_key == index_task.table_key {
+            // the table's own keys go first anyway, by priority or inside its transaction
+            return !self.group_by_table
+                && self.pending.iter().any(|t| {
+                    t.index_task.table_key == index_task.table_key && t.index_task.kind.is_key()
+                });
+        }
+        (self.streaming && !self.received_tables.contains(referenced_table_key))
+            || self.busy_tables.contains(referenced_table_key)
+            || self.pending.iter().any(|t| {
+                t.index_task.table_key == *referenced_table_key && t.index_task.kind.is_key()
+            })
+    }
+
+    pub fn complete(&mut self, table_key: &str, succeeded: bool) {
+        self.busy_tables.remove(table_key);
+        self.busy_constraint_drops.remove(table_key);
+        self.busy_drops.remove(table_key);
+        if succeeded {
+            self.succeeded_tables.insert(table_key.to_string());
+        }
+        let table_done = !self
+            .pending
+            .iter()
+            .any(|t| t.index_task.table_key == table_key);
+        if self.analyze_tables && table_done && self.succeeded_tables.contains(table_key) {
+            // removed, so finishing the ANALYZE itself does not queue another one
+            if let Some(analyze_task) = self.analyze_tasks.remove(table_key) {
+                self.add_task(analyze_task, 0);
+            }
+        }
+    }
+
+    /// Feeds the worker channel, keeping at most `threads` builds in flight, and
+    /// releases a table every time a worker reports its build as finished and
+    /// whether it succeeded.
+    /// With `incoming` the tables streamed in are scheduled as they arrive, and the run
+    /// lasts until the sender is dropped.
+    /// After a shutdown request nothing new is handed out; the tasks that never
+    /// started are returned.
+    pub async fn run(
+        mut self,
+        threads: u32,
+        shutdown: &Shutdown,
+        tx: flume::Sender<Vec<IndexTask>>,
+        done_rx: flume::Receiver<(String, bool)>,
+        incoming: Option<flume::Receiver<TableTasks>>,
+    ) -> Vec<IndexTask> {
+        self.streaming = incoming.is_some();
+        let incoming = incoming.unwrap_or_else(|| flume::bounded(0).1);
+        let mut in_flight = 0;
+        loop {
+            while in_flight < threads && !shutdown.is_stopping() {
+                let Some(index_tasks) = self.pop_ready() else {
+                    break;
+                };
+                if tx.send_async(index_tasks).await.is_err() {
+                    break; // all workers are gone
+                }
+                in_flight += 1;
+            }
+            if in_flight == 0 && (!self.streaming || shutdown.is_stopping()) {
+                break;
+            }
+            tokio::select! {
+                table_tasks = incoming.recv_async(), if self.streaming => match table_tasks {
+                    Ok((index_tasks, table_size_in_bytes)) => {
+                        for index_task in index_tasks {
+                            self.add_task(index_task, table_size_in_bytes);
+                        }
+                    }
+                    Err(_) => self.streaming = false,
+                },
+                done = done_rx.recv_async() => match done {
+                    Ok((table_key, succeeded)) => {
+                        self.complete(&table_key, succeeded);
+                        in_flight -= 1;
+                    }
+                    Err(_) => break,
+                },
+                // wake up so that the loop above stops dispatching
+                _ = shutdown.stopping(), if !shutdown.is_stopping() => {}
+            }
+        }
+        self.pending.into_iter().map(|t| t.index_task).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(table: &str, query: &str) -> IndexTask {
+        IndexTask::new(
+            &format!("{}.sql", table),
+            Some(table.to_string()),
+            query.to_string(),
+        )
+    }
+
+    fn kinds(index_tasks: &[IndexTask]) -> Vec<StatementKind> {
+        index_tasks.iter().map(|t| t.kind).collect()
+    }
+
+    #[test]
+    fn keys_go_first_and_a_table_is_never_busy_twice() {
+        let mut scheduler = Scheduler::new(ScheduleStrategy::FileOrder);
+        scheduler.add_task(task("dbo.a", "CREATE INDEX ix ON dbo.a (x);"), 0);
+        scheduler.add_task(
+            task(
+                "dbo.a",
+                "ALTER TABLE dbo.a ADD CONSTRAINT pk PRIMARY KEY (id);",
+            ),
+            0,
+        );
+        assert_eq!(
+            kinds(&scheduler.pop_ready().unwrap()),
+            vec![StatementKind::PrimaryKey]
+        );
+        assert!(scheduler.pop_ready().is_none());
+        scheduler.complete("dbo.a", true);
+        assert_eq!(
+            kinds(&scheduler.pop_ready().unwrap()),
+            vec![StatementKind::Index]
+        );
+    }
+
+    #[test]
+    fn strategy_orders_tables_by_size() {
+        for (strategy, expected) in [
+            (ScheduleStrategy::LargestFirst, ["dbo.big", "dbo.small"]),
+            (ScheduleStrategy::SmallestFirst, ["dbo.small", "dbo.big"]),
+        ] {
+            let mut scheduler = Scheduler::new(strategy);
+            scheduler.add_task(task("dbo.small", "CREATE INDEX a ON dbo.small (x);"), 10);
+            scheduler.add_task(task("dbo.big", "CREATE INDEX b ON dbo.big (x);"), 1000);
+            let first = scheduler.pop_ready().unwrap();
+            let second = scheduler.pop_ready().unwrap();
+            assert_eq!(
+                [first[0].table_key.as_str(), second[0].table_key.as_str()],
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn foreign_key_waits_for_the_referenced_key() {
+        let mut scheduler = Scheduler::new(ScheduleStrategy::FileOrder);
+        scheduler.add_task(
+            task(
+                "dbo.orders",
+                "ALTER TABLE dbo.orders ADD CONSTRAINT fk FOREIGN KEY (c) REFERENCES dbo.customers (id);",
+            ),
+            0,
+        );
+        scheduler.add_task(
+            task(
+                "dbo.customers",
+                "ALTER TABLE dbo.customers ADD CONSTRAINT pk PRIMARY KEY (id);",
+            ),
+            0,
+        );
+        assert_eq!(
+            kinds(&scheduler.pop_ready().unwrap()),
+            vec![StatementKind::PrimaryKey]
+        );
+        // the key is still being built
+        assert!(scheduler.pop_ready().is_none());
+        scheduler.complete("dbo.customers", true);
+        assert_eq!(
+            kinds(&scheduler.pop_ready().unwrap()),
+            vec![StatementKind::ForeignKey]
+        );
+    }
+
+    #[test]
+    fn drops_wait_for_earlier_constraint_drops() {
+        let mut scheduler = Scheduler::new(ScheduleStrategy::LargestFirst);
+        let script = [
+            "ALTER TABLE IF EXISTS dbo.orders DROP CONSTRAINT IF EXISTS fk;",
+            "ALTER TABLE IF EXISTS dbo.customers DROP CONSTRAINT IF EXISTS pk;",
+            "DROP INDEX CONCURRENTLY IF EXISTS dbo.ix;",
+        ];
+        for (line, query) in script.iter().enumerate() {
+            scheduler.add_task(
+                IndexTask::from_script("rollback.sql", line + 1, query.to_string()),
+                0,
+            );
+        }
+        let first = scheduler.pop_ready().unwrap();
+        assert_eq!(first[0].table_key, "dbo.orders");
+        assert!(scheduler.pop_ready().is_none());
+        scheduler.complete("dbo.orders", true);
+        let second = scheduler.pop_ready().unwrap();
+        assert_eq!(second[0].table_key, "dbo.customers");
+        assert!(scheduler.pop_ready().is_none());
+        scheduler.complete("dbo.customers", true);
+        assert_eq!(
+            kinds(&scheduler.pop_ready().unwrap()),
+            vec![StatementKind::Drop]
+        );
+    }
+
+    #[test]
+    fn materialized_view_drops_wait_for_earlier_drops() {
+        let mut scheduler = Scheduler::new(ScheduleStrategy::LargestFirst);
+        let script = [
+            "DROP INDEX CONCURRENTLY IF EXISTS dbo.ux_totals;",
+            "DROP MATERIALIZED VIEW IF EXISTS dbo.totals;",
+            "DROP INDEX CONCURRENTLY IF EXISTS dbo.ix;",
+        ];
+        for (line, query) in script.iter().enumerate() {
+            scheduler.add_task(
+                IndexTask::from_script("rollback.sql", line + 1, query.to_string()),
+                0,
+            );
+        }
+        let first = scheduler.pop_ready().unwrap();
+        assert_eq!(first[0].source_name, "ux_totals");
+        // the later index drop does not hold back, the view drop waits
+        let second = scheduler.pop_ready().unwrap();
+        assert_eq!(second[0].source_name, "ix");
+        assert!(scheduler.pop_ready().is_none());
+        scheduler.complete(&first[0].table_key, true);
+        assert_eq!(
+            kinds(&scheduler.pop_ready().unwrap()),
+            vec![StatementKind::DropMaterializedView]
+        );
+    }
+
+    #[test]
+    fn group_by_table_hands_out_a_table_in_priority_order() {
+        let mut scheduler = Scheduler::new(ScheduleStrategy::FileOrder);
+        scheduler.set_group_by_table(true);
+        scheduler.add_task(task("dbo.a", "CREATE INDEX ix ON dbo.a (x);"), 0);
+        scheduler.add_task(
+            task(
+                "dbo.a",
+                "ALTER TABLE dbo.a ADD CONSTRAINT pk PRIMARY KEY (id);",
+            ),
+            0,
+        );
+        scheduler.add_task(task("dbo.b", "CREATE INDEX ix_b ON dbo.b (x);"), 0);
+        assert_eq!(
+            kinds(&scheduler.pop_ready().unwrap()),
+            vec![StatementKind::PrimaryKey, StatementKind::Index]
+        );
+        assert_eq!(scheduler.get_task_count(), 1);
+    }
+
+    #[test]
+    fn group_by_table_falls_back_when_foreign_keys_point_at_each_other() {
+        let mut scheduler = Scheduler::new(ScheduleStrategy::FileOrder);
+        scheduler.set_group_by_table(true);
+        for (table, other) in [("dbo.a", "dbo.b"), ("dbo.b", "dbo.a")] {
+            scheduler.add_task(
+                task(
+                    table,
+                    &format!("ALTER TABLE {} ADD CONSTRAINT pk PRIMARY KEY (id);", table),
+                ),
+                0,
+            );
+            scheduler.add_task(
+                task(
+                    table,
+                    &format!(
+                        "ALTER TABLE {} ADD CONSTRAINT fk FOREIGN KEY (o) REFERENCES {} (id);",
+                        table, other
+                    ),
+                ),
+                0,
+            );
+        }
+        // both groups hold a foreign key waiting for the other one's key
+        let first = scheduler.pop_ready().unwrap();
+        assert_eq!(
+            kinds(&first),
+            vec![StatementKind::PrimaryKey, StatementKind::ForeignKey]
+        );
+        assert!(scheduler.pop_ready().is_none());
+        scheduler.complete(&first[0].table_key, true);
+        assert_eq!(scheduler.pop_ready().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn analyze_is_queued_once_a_table_succeeded() {
+        let mut scheduler = Scheduler::new(ScheduleStrategy::FileOrder);
+        scheduler.set_analyze_tables(true);
+        scheduler.add_task(task("dbo.a", "CREATE INDEX ix ON dbo.a (x);"), 0);
+        scheduler.add_task(task("dbo.b", "CREATE INDEX ix ON dbo.b (x);"), 0);
+        scheduler.pop_ready().unwrap();
+        scheduler.pop_ready().unwrap();
+        scheduler.complete("dbo.a", true);
+        scheduler.complete("dbo.b", false);
+        let analyze = scheduler.pop_ready().unwrap();
+        assert_eq!(kinds(&analyze), vec![StatementKind::Analyze]);
+        assert_eq!(analyze[0].table_key, "dbo.a");
+        assert!(scheduler.pop_ready().is_none());
+    }
+}
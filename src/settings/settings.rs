@@ -1,41 +1,271 @@
-use crate::clap_parser::clap_parser::Cli;
+use crate::clap_parser::clap_parser::{
+    BuildArgs, Cli, Command, ReportArgs, SourceArgs, default_args,
+};
+use crate::config_provider::{
+    Config, Encryption, FileSettings, SessionSettings, SslMode, config_error,
+};
+use crate::importer::importer::TransactionMode;
+use crate::retry_policy::retry_policy::RetryPolicy;
+use crate::run_report::run_report::ReportFormat;
+use crate::scheduler::scheduler::ScheduleStrategy;
+use crate::shared::duration_utils::{format_duration, parse_duration};
+use anyhow::{Result, anyhow};
+use clap::ArgMatches;
+use clap::ValueEnum;
+use clap::parser::ValueSource;
+use std::collections::BTreeMap;
 use std::fmt;
+use std::time::Duration;
 
 pub struct Settings {
+    profile: Option<String>,
+    /// where each setting taken from the command line, environment or config came from
+    sources: BTreeMap<&'static str, SettingSource>,
     source_schema_name: String,
     source_table_name: String,
     output_dir: Option<String>,
     input_dir: Option<String>,
     threads: u32,
-    timeout_in_hours: u64,
+    build_timeout: Duration,
+    lock_timeout: Duration,
+    retry_policy: RetryPolicy,
+    schedule_strategy: ScheduleStrategy,
+    progress_interval: Duration,
+    concurrently: bool,
+    transaction_mode: Option<TransactionMode>,
+    analyze: bool,
+    drop_extra: bool,
+    session_settings: SessionSettings,
+    target_sslmode: SslMode,
+    source_encryption: Encryption,
+    source_trust_server_certificate: bool,
+    report_file: Option<String>,
+    report_format: ReportFormat,
 }
 
 impl Settings {
-    pub fn from_args(cli: &Cli) -> Self {
-        let source_schema_name = cli.source_schema.clone();
+    /// Every setting comes from, in increasing precedence: its default, `[settings]` of the
+    /// config file, the profile, its `PG_INDEX_IMPORT_*` environment variable and the
+    /// command line.
+    pub fn from_args(cli: &Cli, matches: &ArgMatches, config: &Config) -> Result<Self> {
+        let default_source = default_args::<SourceArgs>();
+        let default_build = default_args::<BuildArgs>();
+        let default_report = default_args::<ReportArgs>();
+        let (source, output_dir, input_dir, build, execution, drop_extra, report) =
+            match &cli.command {
+                Command::Export(args) => (
+                    &args.source,
+                    Some(args.output.output_dir.clone()),
+                    None,
+                    &default_build,
+                    &default_build.execution,
+                    false,
+                    &args.report,
+                ),
+                Command::Import(args) => (
+                    &default_source,
+                    None,
+                    Some(args.input_dir.clone()),
+                    &args.build,
+                    &args.build.execution,
+                    false,
+                    &args.report,
+                ),
+                Command::Migrate(args) => (
+                    &args.source,
+                    args.output_dir.clone(),
+                    None,
+                    &args.build,
+                    &args.build.execution,
+                    false,
+                    &args.report,
+                ),
+                Command::Verify(args) => (
+                    &args.source,
+                    None,
+                    None,
+                    &default_build,
+                    &default_build.execution,
+                    false,
+                    &args.report,
+                ),
+                Command::Diff(args) => (
+                    &args.source,
+                    args.output_dir.clone(),
+                    None,
+                    &default_build,
+                    &default_build.execution,
+                    args.drop_extra,
+                    &args.report,
+                ),
+                Command::Config(_) => (
+                    &default_source,
+                    None,
+                    None,
+                    &default_build,
+                    &default_build.execution,
+                    false,
+                    &default_report,
+                ),
+                Command::Rollback(args) => (
+                    &default_source,
+                    None,
+                    None,
+                    &default_build,
+                    &args.execution,
+                    false,
+                    &args.report,
+                ),
+            };
 
-        let mut source_table_name = cli.source_table.clone();
-        if source_table_name.eq("*") {
-            source_table_name = "*".to_string();
+        let mut resolver = SettingResolver {
+            matches: matches.subcommand().map(|(_, matches)| matches),
+            config: config.get_settings_as_ref(),
+            profile: config.get_profile_as_ref().as_ref(),
+            sources: BTreeMap::new(),
+        };
+        let source_schema_name =
+            resolver.resolve("source_schema", source.source_schema.clone(), |s| {
+                s.source_schema.clone().map(Ok)
+            })?;
+        let source_table_name =
+            resolver.resolve("source_table", source.source_table.clone(), |s| {
+                s.source_table.clone().map(Ok)
+            })?;
+        let output_dir = match output_dir {
+            Some(output_dir) => {
+                Some(resolver.resolve("output_dir", output_dir, |s| s.output_dir.clone().map(Ok))?)
+            }
+            // migrate takes it optionally as an audit trail, diff for its sync script
+            None if matches!(cli.command, Command::Migrate(_) | Command::Diff(_)) => resolver
+                .resolve("output_dir", None, |s| {
+                    s.output_dir.clone().map(|d| Ok(Some(d)))
+                })?,
+            None => None,
+        };
+        let input_dir = match input_dir {
+            Some(input_dir) => {
+                Some(resolver.resolve("input_dir", input_dir, |s| s.input_dir.clone().map(Ok))?)
+            }
+            None => None,
+        };
+        let threads = resolver.resolve("threads", execution.threads, |s| {
+            s.threads.map(|v| check_range(v, 1, 10))
+        })?;
+        let build_timeout = resolver.resolve("build_timeout", execution.build_timeout, |s| {
+            s.build_timeout.as_deref().map(parse_duration)
+        })?;
+        let lock_timeout = resolver.resolve("lock_timeout", execution.lock_timeout, |s| {
+            s.lock_timeout.as_deref().map(parse_duration)
+        })?;
+        let max_retries = resolver.resolve("max_retries", execution.max_retries, |s| {
+            s.max_retries.map(|v| check_range(v, 0, 10))
+        })?;
+        let retry_delay_in_ms =
+            resolver.resolve("retry_delay_in_ms", execution.retry_delay_in_ms, |s| {
+                s.retry_delay_in_ms.map(|v| check_range(v, 1, 60000))
+            })?;
+        let retry_policy = RetryPolicy::new(max_retries, retry_delay_in_ms);
+        let progress_interval =
+            resolver.resolve("progress_interval", execution.progress_interval, |s| {
+                s.progress_interval.as_deref().map(parse_duration)
+            })?;
+        // only import and migrate build anything, and config check validates them; the other
+        // commands keep the defaults, so that e.g. transactional = "per-table" in [settings]
+        // does not reach a rollback
+        let (schedule_strategy, concurrently, transaction_mode, analyze) = if matches!(
+            cli.command,
+            Command::Import(_) | Command::Migrate(_) | Command::Config(_)
+        ) {
+            resolve_build_settings(&mut resolver, build)?
+        } else {
+            for name in ["schedule", "concurrently", "transactional", "analyze"] {
+                resolver.sources.insert(name, SettingSource::Default);
+            }
+            (
+                build.schedule,
+                build.concurrently,
+                build.transactional,
+                build.analyze,
+            )
+        };
+        let drop_extra = match &cli.command {
+            Command::Diff(_) => {
+                resolver.resolve("drop_extra", drop_extra, |s| s.drop_extra.map(Ok))?
+            }
+            _ => drop_extra,
+        };
+        let session_settings = config.get_session_settings_as_ref().clone();
+        let target_sslmode = config.get_target_database_as_ref().get_sslmode();
+        let source_encryption = config.get_source_database_as_ref().get_encrypt();
+        let source_trust_server_certificate = config
+            .get_source_database_as_ref()
+            .get_trust_server_certificate();
+        let report_file = resolver.resolve("report", report.report.clone(), |s| {
+            s.report.clone().map(|r| Ok(Some(r)))
+        })?;
+        let report_format = resolver.resolve("report_format", report.report_format, |s| {
+            s.report_format
+                .as_deref()
+                .map(|v| parse_value_enum(v).map(Some))
+        })?;
+        // checked here and not by clap, the report file may come from the config file
+        if report_format.is_some() && report_file.is_none() {
+            return Err(resolver.get_error("report_format", "needs report".to_string()));
         }
+        let report_format = report_format.unwrap_or_else(|| {
+            report_file
+                .as_deref()
+                .map(ReportFormat::from_path)
+                .unwrap_or(ReportFormat::Json)
+        });
+        let profile = config
+            .get_profile_as_ref()
+            .as_ref()
+            .map(|(name, _)| name.clone());
+        let sources = resolver.sources;
 
-        let output_dir = cli.output_dir.clone();
-
-        let input_dir = cli.input_dir.clone();
-        let threads = cli.threads;
-        let timeout_in_hours = cli.timeout_in_hours;
-
-        Settings {
+        Ok(Settings {
+            profile,
+            sources,
             source_schema_name,
             source_table_name,
             output_dir,
             input_dir,
             threads,
-            timeout_in_hours,
-        }
+            build_timeout,
+            lock_timeout,
+            retry_policy,
+            schedule_strategy,
+            progress_interval,
+            concurrently,
+            transaction_mode,
+            analyze,
+            drop_extra,
+            session_settings,
+            target_sslmode,
+            source_encryption,
+            source_trust_server_certificate,
+            report_file,
+            report_format,
+        })
     }
 
     // region Getters
+    /// ` (<source>)` of the setting, the highest one for several settings shown together.
+    fn get_source(&self, names: &[&str]) -> String {
+        names
+            .iter()
+            .filter_map(|name| self.sources.get(name))
+            .max()
+            .map(|source| format!(" ({})", source))
+            .unwrap_or_default()
+    }
+
+    pub fn get_profile_as_ref(&self) -> &Option<String> {
+        &self.profile
+    }
+
     pub fn get_source_schema_name_as_ref(&self) -> &String {
         &self.source_schema_name
     }
@@ -56,23 +286,474 @@ impl Settings {
         self.threads
     }
 
-    pub fn get_timeout(&self) -> u64 {
-        self.timeout_in_hours
+    pub fn get_build_timeout(&self) -> Duration {
+        self.build_timeout
+    }
+
+    pub fn get_lock_timeout(&self) -> Duration {
+        self.lock_timeout
+    }
+
+    pub fn get_retry_policy_as_ref(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    pub fn get_schedule_strategy(&self) -> ScheduleStrategy {
+        self.schedule_strategy
+    }
+
+    pub fn get_progress_interval(&self) -> Duration {
+        self.progress_interval
+    }
+
+    pub fn get_concurrently(&self) -> bool {
+        self.concurrently
+    }
+
+    pub fn get_transaction_mode(&self) -> Option<TransactionMode> {
+        self.transaction_mode
+    }
+
+    pub fn get_analyze(&self) -> bool {
+        self.analyze
+    }
+
+    pub fn get_drop_extra(&self) -> bool {
+        self.drop_extra
+    }
+
+    pub fn get_session_settings_as_ref(&self) -> &SessionSettings {
+        &self.session_settings
+    }
+
+    pub fn get_source_trust_server_certificate(&self) -> bool {
+        self.source_trust_server_certificate
+    }
+
+    pub fn get_report_file_as_ref(&self) -> &Option<String> {
+        &self.report_file
+    }
+
+    pub fn get_report_format(&self) -> ReportFormat {
+        self.report_format
     }
 }
 
 impl fmt::Display for Settings {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(f, "Source schema name: <{}>", self.source_schema_name)?;
-        writeln!(f, "Source table name: <{}>", self.source_table_name)?;
+        match &self.profile {
+            Some(profile) => writeln!(f, "Profile: <{}>", profile)?,
+            None => writeln!(f, "Profile: <none>")?,
+        }
+        writeln!(
+            f,
+            "Source schema name: <{}>{}",
+            self.source_schema_name,
+            self.get_source(&["source_schema"])
+        )?;
+        writeln!(
+            f,
+            "Source table name: <{}>{}",
+            self.source_table_name,
+            self.get_source(&["source_table"])
+        )?;
+        if let Some(output_dir) = &self.output_dir {
+            writeln!(
+                f,
+                "Output directory: <{}>{}",
+                output_dir,
+                self.get_source(&["output_dir"])
+            )?;
+        }
+        if let Some(input_dir) = &self.input_dir {
+            writeln!(
+                f,
+                "Input directory: <{}>{}",
+                input_dir,
+                self.get_source(&["input_dir"])
+            )?;
+        }
+        writeln!(
+            f,
+            "Threads: <{}>{}",
+            self.threads,
+            self.get_source(&["threads"])
+        )?;
+        writeln!(
+            f,
+            "Build timeout: <{}>{}",
+            format_duration(&self.build_timeout),
+            self.get_source(&["build_timeout"])
+        )?;
+        writeln!(
+            f,
+            "Lock timeout: <{}>{}",
+            format_duration(&self.lock_timeout),
+            self.get_source(&["lock_timeout"])
+        )?;
+        writeln!(
+            f,
+            "Retry policy: <{}>{}",
+            self.retry_policy,
+            self.get_source(&["max_retries", "retry_delay_in_ms"])
+        )?;
+        writeln!(
+            f,
+            "Schedule strategy: <{}>{}",
+            self.schedule_strategy,
+            self.get_source(&["schedule"])
+        )?;
+        writeln!(
+            f,
+            "Progress interval: <{}>{}",
+            if self.progress_interval.is_zero() {
+                "disabled".to_string()
+            } else {
+                format_duration(&self.progress_interval)
+            },
+            self.get_source(&["progress_interval"])
+        )?;
+        writeln!(
+            f,
+            "Concurrently: <{}>{}",
+            self.concurrently,
+            self.get_source(&["concurrently"])
+        )?;
+        writeln!(
+            f,
+            "Transactional: <{}>{}",
+            self.transaction_mode
+                .map(|transaction_mode| transaction_mode.to_string())
+                .unwrap_or_else(|| "off".to_string()),
+            self.get_source(&["transactional"])
+        )?;
+        writeln!(
+            f,
+            "Analyze: <{}>{}",
+            self.analyze,
+            self.get_source(&["analyze"])
+        )?;
+        writeln!(
+            f,
+            "Drop extra indexes: <{}>{}",
+            self.drop_extra,
+            self.get_source(&["drop_extra"])
+        )?;
+        writeln!(f, "Session settings: {}", self.session_settings)?;
+        writeln!(f, "Postgres SSL mode: <{}>", self.target_sslmode)?;
         writeln!(
             f,
-            "Output directory: <{}>",
-            self.output_dir.as_ref().unwrap()
+            "SQL Server encryption: <{}>, certificate validation: <{}>",
+            self.source_encryption,
+            if self.source_trust_server_certificate {
+                "off"
+            } else {
+                "on"
+            }
         )?;
-        writeln!(f, "Input directory: <{}>", self.input_dir.as_ref().unwrap())?;
-        writeln!(f, "Threads: <{}>", self.threads)?;
-        writeln!(f, "Timeout: <{}>", self.timeout_in_hours)?;
+        match &self.report_file {
+            Some(report_file) => writeln!(
+                f,
+                "Report: <{}>, format <{}>{}",
+                report_file,
+                self.report_format,
+                self.get_source(&["report", "report_format"])
+            )?,
+            None => writeln!(f, "Report: <none>")?,
+        }
         Ok(())
     }
 }
+
+/// Where the value of a setting came from, lowest precedence first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SettingSource {
+    Default,
+    Config,
+    Profile,
+    Environment,
+    CommandLine,
+}
+
+impl fmt::Display for SettingSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SettingSource::Default => write!(f, "default"),
+            SettingSource::Config => write!(f, "config"),
+            SettingSource::Profile => write!(f, "profile"),
+            SettingSource::Environment => write!(f, "environment"),
+            SettingSource::CommandLine => write!(f, "command line"),
+        }
+    }
+}
+
+/// Picks each setting from the command line and environment when clap saw it there,
+/// then from the profile, then from `[settings]`, and falls back to the clap default.
+struct SettingResolver<'a> {
+    /// of the subcommand; settings it does not take only come from the config file
+    matches: Option<&'a ArgMatches>,
+    config: &'a FileSettings,
+    profile: Option<&'a (String, FileSettings)>,
+    sources: BTreeMap<&'static str, SettingSource>,
+}
+
+impl SettingResolver<'_> {
+    /// Config file section a value from `source` was read from.
+    fn get_section(&self, source: SettingSource) -> Option<String> {
+        match source {
+            SettingSource::Config => Some("settings".to_string()),
+            SettingSource::Profile => self
+                .profile
+                .map(|(profile_name, _)| format!("profiles.{}", profile_name)),
+            _ => None,
+        }
+    }
+
+    /// An error about a resolved setting, with the config file key it came from.
+    fn get_error(&self, name: &'static str, message: String) -> anyhow::Error {
+        let source = self.sources[name];
+        match self.get_section(source) {
+            Some(section) => config_error(&section, name, message),
+            None => anyhow!("{} ({}) {}", name, source, message),
+        }
+    }
+
+    fn resolve<T>(
+        &mut self,
+        name: &'static str,
+        value: T,
+        from_file: impl Fn(&FileSettings) -> Option<Result<T, String>>,
+    ) -> Result<T> {
+        let value_source = self
+            .matches
+            .filter(|matches| matches.ids().any(|id| id == name))
+            .and_then(|matches| matches.value_source(name));
+        let source = match value_source {
+            Some(ValueSource::CommandLine) => Some(SettingSource::CommandLine),
+            Some(ValueSource::EnvVariable) => Some(SettingSource::Environment),
+            _ => None,
+        };
+        if let Some(source) = source {
+            self.sources.insert(name, source);
+            return Ok(value);
+        }
+        if let Some((profile_name, profile)) = self.profile
+            && let Some(profile_value) = from_file(profile)
+        {
+            self.sources.insert(name, SettingSource::Profile);
+            return profile_value
+                .map_err(|e| config_error(&format!("profiles.{}", profile_name), name, e));
+        }
+        if let Some(config_value) = from_file(self.config) {
+            self.sources.insert(name, SettingSource::Config);
+            return config_value.map_err(|e| config_error("settings", name, e));
+        }
+        self.sources.insert(name, SettingSource::Default);
+        Ok(value)
+    }
+}
+
+/// Schedule, concurrently, transactional and analyze of the commands that build.
+fn resolve_build_settings(
+    resolver: &mut SettingResolver,
+    build: &BuildArgs,
+) -> Result<(ScheduleStrategy, bool, Option<TransactionMode>, bool)> {
+    let schedule_strategy = resolver.resolve("schedule", build.schedule, |s| {
+        s.schedule.as_deref().map(parse_value_enum)
+    })?;
+    let concurrently = resolver.resolve("concurrently", build.concurrently, |s| {
+        s.concurrently.map(Ok)
+    })?;
+    let transaction_mode = resolver.resolve("transactional", build.transactional, |s| {
+        s.transactional.as_deref().map(|v| match v {
+            "off" => Ok(None),
+            v => parse_value_enum(v).map(Some),
+        })
+    })?;
+    if concurrently && transaction_mode.is_some() {
+        let message = format!(
+            "cannot be combined with concurrently ({})",
+            resolver.sources["concurrently"]
+        );
+        return Err(resolver.get_error("transactional", message));
+    }
+    let analyze = resolver.resolve("analyze", build.analyze, |s| s.analyze.map(Ok))?;
+    Ok((schedule_strategy, concurrently, transaction_mode, analyze))
+}
+
+fn check_range<T: PartialOrd + fmt::Display>(value: T, min: T, max: T) -> Result<T, String> {
+    if value < min || value > max {
+        return Err(format!("{} is not from {} to {}", value, min, max));
+    }
+    Ok(value)
+}
+
+/// Parses a choice the way the command line takes it, e.g. `largest-first`.
+fn parse_value_enum<T: ValueEnum>(value: &str) -> Result<T, String> {
+    T::from_str(value, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config_provider::ConfigProvider;
+    use clap::{CommandFactory, FromArgMatches};
+
+    const DATABASES: &str = r#"
+[source_database]
+host = "mssql.example.com"
+port = 1433
+database = "sales"
+user = "sa"
+password = "secret"
+
+[target_database]
+host = "pg.example.com"
+port = 5432
+database = "sales"
+user = "postgres"
+password = "secret"
+"#;
+
+    fn resolve_settings(config: &str, profile: Option<&str>, args: &[&str]) -> Result<Settings> {
+        let config = ConfigProvider::new("test.toml")
+            .parse_config(format!("{}{}", DATABASES, config), profile)?;
+        let matches =
+            Cli::command().try_get_matches_from(["pg_index_import"].iter().chain(args))?;
+        let cli = Cli::from_arg_matches(&matches)?;
+        Settings::from_args(&cli, &matches, &config)
+    }
+
+    fn resolve_error(config: &str, args: &[&str]) -> String {
+        match resolve_settings(config, None, args) {
+            Ok(_) => panic!("{:?} resolved", args),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn each_source_overrides_the_lower_ones() {
+        let config = r#"
+[settings]
+threads = 2
+build_timeout = "10m"
+lock_timeout = "20s"
+
+[profiles.night]
+threads = 3
+build_timeout = "20m"
+"#;
+        let settings =
+            resolve_settings(config, Some("night"), &["import", "--threads", "4"]).unwrap();
+        assert_eq!(settings.get_threads(), 4);
+        assert_eq!(settings.get_build_timeout(), Duration::from_secs(20 * 60));
+        assert_eq!(settings.get_lock_timeout(), Duration::from_secs(20));
+        assert_eq!(settings.get_progress_interval(), Duration::from_secs(30));
+        assert_eq!(settings.sources["threads"], SettingSource::CommandLine);
+        assert_eq!(settings.sources["build_timeout"], SettingSource::Profile);
+        assert_eq!(settings.sources["lock_timeout"], SettingSource::Config);
+        assert_eq!(
+            settings.sources["progress_interval"],
+            SettingSource::Default
+        );
+
+        let settings = resolve_settings(config, None, &["import"]).unwrap();
+        assert_eq!(settings.get_threads(), 2);
+        assert_eq!(settings.sources["threads"], SettingSource::Config);
+    }
+
+    #[test]
+    fn environment_overrides_the_config_file_and_not_the_command_line() {
+        // only this test reads max_retries, the environment is shared by the tests
+        unsafe { std::env::set_var("PG_INDEX_IMPORT_MAX_RETRIES", "5") };
+        let config = "[settings]\nmax_retries = 1\n[profiles.night]\nmax_retries = 2\n";
+        let from_env = resolve_settings(config, Some("night"), &["import"]).unwrap();
+        let from_cli = resolve_settings(config, None, &["import", "--max-retries", "6"]).unwrap();
+        unsafe { std::env::remove_var("PG_INDEX_IMPORT_MAX_RETRIES") };
+        assert_eq!(from_env.get_retry_policy_as_ref().get_max_retries(), 5);
+        assert_eq!(from_env.sources["max_retries"], SettingSource::Environment);
+        assert_eq!(from_cli.get_retry_policy_as_ref().get_max_retries(), 6);
+        assert_eq!(from_cli.sources["max_retries"], SettingSource::CommandLine);
+    }
+
+    #[test]
+    fn display_shows_the_source_of_each_setting() {
+        let config = "[settings]\nthreads = 2\n[profiles.night]\nschedule = \"largest-first\"\n";
+        let settings = resolve_settings(config, Some("night"), &["import", "--analyze"]).unwrap();
+        let shown = settings.to_string();
+        assert!(shown.contains("Profile: <night>\n"));
+        assert!(shown.contains("Threads: <2> (config)\n"));
+        assert!(shown.contains("Schedule strategy: <largest-first> (profile)\n"));
+        assert!(shown.contains("Analyze: <true> (command line)\n"));
+        assert!(shown.contains("Concurrently: <false> (default)\n"));
+    }
+
+    #[test]
+    fn command_line_turns_off_a_switch_of_the_config_file() {
+        let config = "[settings]\nconcurrently = true\nanalyze = true\ndrop_extra = true\n";
+        let settings = resolve_settings(config, None, &["import"]).unwrap();
+        assert!(settings.get_concurrently());
+        assert!(settings.get_analyze());
+
+        let settings = resolve_settings(
+            config,
+            None,
+            &["import", "--concurrently=false", "--analyze=false"],
+        )
+        .unwrap();
+        assert!(!settings.get_concurrently());
+        assert!(!settings.get_analyze());
+        assert_eq!(settings.sources["concurrently"], SettingSource::CommandLine);
+
+        let settings = resolve_settings(config, None, &["diff", "--drop-extra=false"]).unwrap();
+        assert!(!settings.get_drop_extra());
+    }
+
+    #[test]
+    fn diff_takes_the_output_dir_optionally() {
+        let settings = resolve_settings("", None, &["diff"]).unwrap();
+        assert_eq!(settings.get_output_dir_as_ref(), &None);
+
+        let config = "[settings]\noutput_dir = \"SYNC\"\n";
+        let settings = resolve_settings(config, None, &["diff"]).unwrap();
+        assert_eq!(settings.get_output_dir_as_ref().as_deref(), Some("SYNC"));
+        assert_eq!(settings.sources["output_dir"], SettingSource::Config);
+
+        let settings = resolve_settings(config, None, &["diff", "--output-dir", "OUT"]).unwrap();
+        assert_eq!(settings.get_output_dir_as_ref().as_deref(), Some("OUT"));
+        assert_eq!(settings.sources["output_dir"], SettingSource::CommandLine);
+    }
+
+    #[test]
+    fn transactional_conflicts_with_concurrently_from_any_source() {
+        let config = "[settings]\nconcurrently = true\n";
+        let e = resolve_error(config, &["import", "--transactional", "per-table"]);
+        assert!(e.contains("concurrently (config)"), "{}", e);
+        resolve_settings(
+            config,
+            None,
+            &[
+                "import",
+                "--concurrently=false",
+                "--transactional",
+                "per-table",
+            ],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn report_format_takes_the_report_file_of_the_config_file() {
+        let config = "[settings]\nreport = \"run.json\"\n";
+        let settings =
+            resolve_settings(config, None, &["import", "--report-format", "junit"]).unwrap();
+        assert_eq!(
+            settings.get_report_file_as_ref().as_deref(),
+            Some("run.json")
+        );
+        assert_eq!(settings.get_report_format(), ReportFormat::Junit);
+
+        let e = resolve_error("", &["import", "--report-format", "junit"]);
+        assert!(e.contains("needs report"), "{}", e);
+    }
+}
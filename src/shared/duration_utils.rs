@@ -0,0 +1,160 @@
+use std::time::Duration;
+
+// PostgreSQL timeouts are an int of milliseconds
+const MAX_DURATION_IN_MS: u64 = i32::MAX as u64;
+
+/// Parses durations like `90s`, `15m`, `2h`, `1h30m` or a plain number of seconds.
+/// `0` means "no limit", the same as in PostgreSQL. At most 2147483647 ms, about 596h.
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err("duration is empty".to_string());
+    }
+    if let Ok(seconds) = value.parse::<u64>() {
+        return check_max_duration(value, Some(seconds));
+    }
+    let mut total_in_seconds: u64 = 0;
+    let mut number = String::new();
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let multiplier = match c.to_ascii_lowercase() {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => {
+                return Err(format!(
+                    "invalid duration <{}>: unknown unit '{}', use h, m or s",
+                    value, c
+                ));
+            }
+        };
+        let amount: u64 = number.parse().map_err(|_| {
+            format!(
+                "invalid duration <{}>: missing number before '{}'",
+                value, c
+            )
+        })?;
+        total_in_seconds = match amount
+            .checked_mul(multiplier)
+            .and_then(|seconds| total_in_seconds.checked_add(seconds))
+        {
+            Some(total_in_seconds) => total_in_seconds,
+            None => return check_max_duration(value, None),
+        };
+        number.clear();
+    }
+    if !number.is_empty() {
+        return Err(format!(
+            "invalid duration <{}>: missing unit after {}",
+            value, number
+        ));
+    }
+    check_max_duration(value, Some(total_in_seconds))
+}
+
+fn check_max_duration(value: &str, seconds: Option<u64>) -> Result<Duration, String> {
+    match seconds.and_then(|seconds| seconds.checked_mul(1000)) {
+        Some(ms) if ms <= MAX_DURATION_IN_MS => Ok(Duration::from_millis(ms)),
+        _ => Err(format!(
+            "invalid duration <{}>: more than {} ms",
+            value, MAX_DURATION_IN_MS
+        )),
+    }
+}
+
+pub fn format_duration(duration: &Duration) -> String {
+    let total_in_seconds = duration.as_secs();
+    if total_in_seconds == 0 {
+        return "no limit".to_string();
+    }
+    let hours = total_in_seconds / 3600;
+    let minutes = total_in_seconds % 3600 / 60;
+    let seconds = total_in_seconds % 60;
+    let mut result = String::new();
+    if hours > 0 {
+        result.push_str(&format!("{}h", hours));
+    }
+    if minutes > 0 {
+        result.push_str(&format!("{}m", minutes));
+    }
+    if seconds > 0 {
+        result.push_str(&format!("{}s", seconds));
+    }
+    result
+}
+
+pub fn format_elapsed(duration: &Duration) -> String {
+    if duration.as_secs() == 0 {
+        return "0s".to_string();
+    }
+    format_duration(duration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_duration_reads_units_and_plain_seconds() {
+        for (value, seconds) in [
+            ("90", 90),
+            ("0", 0),
+            ("90s", 90),
+            ("15m", 900),
+            ("2h", 7200),
+            ("1h30m", 5400),
+            ("1H30M15S", 5415),
+            (" 10m ", 600),
+            ("596h", 596 * 3600),
+        ] {
+            assert_eq!(
+                parse_duration(value),
+                Ok(Duration::from_secs(seconds)),
+                "{}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn parse_duration_rejects_malformed_values() {
+        for (value, error) in [
+            ("", "duration is empty"),
+            (
+                "10d",
+                "invalid duration <10d>: unknown unit 'd', use h, m or s",
+            ),
+            ("1h30", "invalid duration <1h30>: missing unit after 30"),
+            ("h", "invalid duration <h>: missing number before 'h'"),
+            (
+                "-5s",
+                "invalid duration <-5s>: unknown unit '-', use h, m or s",
+            ),
+        ] {
+            assert_eq!(parse_duration(value), Err(error.to_string()), "{}", value);
+        }
+    }
+
+    #[test]
+    fn parse_duration_rejects_more_than_a_postgres_timeout_holds() {
+        assert_eq!(
+            parse_duration("597h"),
+            Err("invalid duration <597h>: more than 2147483647 ms".to_string())
+        );
+        assert!(parse_duration("2147484").is_err());
+        assert!(parse_duration("99999999999999999999h").is_err());
+    }
+
+    #[test]
+    fn format_duration_writes_what_parse_duration_reads() {
+        assert_eq!(format_duration(&Duration::ZERO), "no limit");
+        assert_eq!(format_elapsed(&Duration::ZERO), "0s");
+        for value in ["45s", "15m", "2h", "1h30m15s"] {
+            let duration = parse_duration(value).unwrap();
+            assert_eq!(format_duration(&duration), value);
+        }
+    }
+}
@@ -1,15 +1,17 @@
 use anyhow::Context;
 use async_recursion::async_recursion;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tokio::fs;
 
 pub async fn ensure_directory_exists_and_empty(dir: &PathBuf) -> anyhow::Result<()> {
     if !dir.exists() {
         fs::create_dir_all(&dir)
             .await
-            .with_context(|| "Failed to create directory".to_string())?;
+            .with_context(|| format!("Failed to create directory <{}>", dir.display()))?;
     } else {
-        let mut entries = fs::read_dir(dir).await?;
+        let mut entries = fs::read_dir(dir)
+            .await
+            .with_context(|| format!("Failed to read directory <{}>", dir.display()))?;
         while let Some(entry) = entries.next_entry().await? {
             let path = entry.path();
             if path.is_file() {
@@ -20,6 +22,13 @@ pub async fn ensure_directory_exists_and_empty(dir: &PathBuf) -> anyhow::Result<
     Ok(())
 }
 
+/// Unlike `ensure_directory_exists_and_empty` keeps the files of earlier runs.
+pub async fn ensure_directory_exists(dir: &PathBuf) -> anyhow::Result<()> {
+    fs::create_dir_all(&dir)
+        .await
+        .with_context(|| format!("Failed to create directory <{}>", dir.display()))
+}
+
 pub async fn write_index_to_file(file: &PathBuf, index: &str) -> anyhow::Result<()> {
     fs::write(file, index)
         .await
@@ -49,3 +58,17 @@ pub async fn read_file(file: &PathBuf) -> anyhow::Result<String> {
         .with_context(|| format!("Failed to read file: {}", file.to_str().unwrap()))?;
     Ok(content)
 }
+
+/// Export writes `<dir>/<schema>/<table>/<index>.sql`, so the table of an input
+/// file is recovered from its path relative to the input directory.
+pub fn get_schema_table_from_path(input_dir: &Path, file: &Path) -> Option<String> {
+    let relative = file.strip_prefix(input_dir).ok()?;
+    let components: Vec<&str> = relative
+        .components()
+        .filter_map(|c| c.as_os_str().to_str())
+        .collect();
+    match components.as_slice() {
+        [.., schema, table, _file] => Some(format!("{}.{}", schema, table)),
+        _ => None,
+    }
+}
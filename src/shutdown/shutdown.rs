@@ -0,0 +1,79 @@
+use crate::build_registry::build_registry::BuildRegistry;
+use crate::{log_error, log_warn};
+use colored::Colorize;
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+/// Two-stage shutdown: the first SIGINT/SIGTERM stops handing out new work and
+/// lets running builds finish, the second one cancels the running builds.
+#[derive(Debug, Default)]
+pub struct Shutdown {
+    stop_dispatching: CancellationToken,
+    cancel_builds: CancellationToken,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Shutdown::default()
+    }
+
+    pub fn is_stopping(&self) -> bool {
+        self.stop_dispatching.is_cancelled()
+    }
+
+    pub fn is_cancelling(&self) -> bool {
+        self.cancel_builds.is_cancelled()
+    }
+
+    pub async fn stopping(&self) {
+        self.stop_dispatching.cancelled().await
+    }
+
+    pub fn listen_for_signals(self: Arc<Self>, build_registry: Arc<BuildRegistry>) {
+        tokio::spawn(async move {
+            if wait_for_signal().await.is_err() {
+                return;
+            }
+            log_warn!(
+                "{}",
+                "Shutdown requested: no new indexes will be started, waiting for running builds. Press Ctrl-C again to cancel them"
+                    .yellow()
+            );
+            self.stop_dispatching.cancel();
+            if wait_for_signal().await.is_err() {
+                return;
+            }
+            log_warn!("{}", "Cancelling running index builds ...".red());
+            self.cancel_builds.cancel();
+            for (pid, running_build) in build_registry.get_running_builds() {
+                log_warn!(
+                    "Cancelling build of <{}> on backend {}",
+                    running_build.label.yellow(),
+                    pid
+                );
+                if let Err(e) = build_registry.cancel(pid).await {
+                    log_error!(
+                        "{}: {}",
+                        "Failed to cancel backend".red(),
+                        e.to_string().red()
+                    );
+                }
+            }
+        });
+    }
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() -> std::io::Result<()> {
+    use tokio::signal::unix::{SignalKind, signal};
+    let mut sigterm = signal(SignalKind::terminate())?;
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => result,
+        _ = sigterm.recv() => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() -> std::io::Result<()> {
+    tokio::signal::ctrl_c().await
+}
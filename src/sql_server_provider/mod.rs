@@ -1,2 +1,7 @@
+mod sql_server_constraint_extract_query;
+mod sql_server_foreign_key_extract_query;
+mod sql_server_index_extract_query;
+mod sql_server_index_metadata_query;
 pub mod sql_server_provider;
-mod sql_server_index_extract_query;
\ No newline at end of file
+mod sql_server_statistics_extract_query;
+mod sql_server_view_extract_query;
@@ -0,0 +1,48 @@
+pub const SQL_SERVER_CHECK_CONSTRAINT_EXTRACT_QUERY: &str = r#"
+SELECT QUOTENAME(cc.name),
+    CAST(CONCAT(
+            CAST(N'' AS NVARCHAR(MAX)),
+            N'ALTER TABLE ' COLLATE DATABASE_DEFAULT, QUOTENAME(s.name) COLLATE DATABASE_DEFAULT,
+            N'.' COLLATE DATABASE_DEFAULT, QUOTENAME(t.name) COLLATE DATABASE_DEFAULT,
+            -- not trusted: existing rows were never checked
+            CASE WHEN cc.is_not_trusted = 1 THEN N' WITH NOCHECK' ELSE N' WITH CHECK' END,
+            N' ADD CONSTRAINT ' COLLATE DATABASE_DEFAULT, QUOTENAME(cc.name) COLLATE DATABASE_DEFAULT,
+            N' CHECK ' COLLATE DATABASE_DEFAULT, cc.definition COLLATE DATABASE_DEFAULT,
+            N';',
+            CASE
+                WHEN cc.is_disabled = 1 THEN CONCAT(
+                        N' ALTER TABLE ' COLLATE DATABASE_DEFAULT, QUOTENAME(s.name) COLLATE DATABASE_DEFAULT,
+                        N'.' COLLATE DATABASE_DEFAULT, QUOTENAME(t.name) COLLATE DATABASE_DEFAULT,
+                        N' NOCHECK CONSTRAINT ' COLLATE DATABASE_DEFAULT, QUOTENAME(cc.name) COLLATE DATABASE_DEFAULT,
+                        N';')
+                ELSE N'' END
+        ) AS NVARCHAR(MAX)) COLLATE DATABASE_DEFAULT AS check_constraint_ddl
+FROM sys.check_constraints cc
+         JOIN sys.tables t ON t.object_id = cc.parent_object_id
+         JOIN sys.schemas s ON s.schema_id = t.schema_id
+WHERE s.name = @SchemaName
+  AND t.name = @TableName
+ORDER BY cc.name;
+"#;
+
+pub const SQL_SERVER_DEFAULT_CONSTRAINT_EXTRACT_QUERY: &str = r#"
+SELECT QUOTENAME(dc.name),
+    CAST(CONCAT(
+            CAST(N'' AS NVARCHAR(MAX)),
+            N'ALTER TABLE ' COLLATE DATABASE_DEFAULT, QUOTENAME(s.name) COLLATE DATABASE_DEFAULT,
+            N'.' COLLATE DATABASE_DEFAULT, QUOTENAME(t.name) COLLATE DATABASE_DEFAULT,
+            N' ADD CONSTRAINT ' COLLATE DATABASE_DEFAULT, QUOTENAME(dc.name) COLLATE DATABASE_DEFAULT,
+            N' DEFAULT ' COLLATE DATABASE_DEFAULT, dc.definition COLLATE DATABASE_DEFAULT,
+            N' FOR ' COLLATE DATABASE_DEFAULT, QUOTENAME(c.name) COLLATE DATABASE_DEFAULT,
+            N';'
+        ) AS NVARCHAR(MAX)) COLLATE DATABASE_DEFAULT AS default_constraint_ddl
+FROM sys.default_constraints dc
+         JOIN sys.tables t ON t.object_id = dc.parent_object_id
+         JOIN sys.schemas s ON s.schema_id = t.schema_id
+         JOIN sys.columns c
+              ON c.object_id = dc.parent_object_id
+                  AND c.column_id = dc.parent_column_id
+WHERE s.name = @SchemaName
+  AND t.name = @TableName
+ORDER BY dc.name;
+"#;
@@ -0,0 +1,52 @@
+pub const SQL_SERVER_FOREIGN_KEY_EXTRACT_QUERY: &str = r#"
+SELECT QUOTENAME(fk.name),
+    CAST(CONCAT(
+            CAST(N'' AS NVARCHAR(MAX)),
+            N'ALTER TABLE ' COLLATE DATABASE_DEFAULT, QUOTENAME(s.name) COLLATE DATABASE_DEFAULT,
+            N'.' COLLATE DATABASE_DEFAULT, QUOTENAME(t.name) COLLATE DATABASE_DEFAULT,
+            -- not trusted: existing rows were never checked
+            CASE WHEN fk.is_not_trusted = 1 THEN N' WITH NOCHECK' ELSE N' WITH CHECK' END,
+            N' ADD CONSTRAINT ' COLLATE DATABASE_DEFAULT, QUOTENAME(fk.name) COLLATE DATABASE_DEFAULT,
+            N' FOREIGN KEY (' COLLATE DATABASE_DEFAULT, cols.parent_list, N')',
+            N' REFERENCES ' COLLATE DATABASE_DEFAULT, QUOTENAME(rs.name) COLLATE DATABASE_DEFAULT,
+            N'.' COLLATE DATABASE_DEFAULT, QUOTENAME(rt.name) COLLATE DATABASE_DEFAULT,
+            N' (' COLLATE DATABASE_DEFAULT, cols.referenced_list, N')',
+            N' ON DELETE ' COLLATE DATABASE_DEFAULT,
+            REPLACE(fk.delete_referential_action_desc, N'_', N' ') COLLATE DATABASE_DEFAULT,
+            N' ON UPDATE ' COLLATE DATABASE_DEFAULT,
+            REPLACE(fk.update_referential_action_desc, N'_', N' ') COLLATE DATABASE_DEFAULT,
+            CASE WHEN fk.is_not_for_replication = 1 THEN N' NOT FOR REPLICATION' ELSE N'' END,
+            N';',
+            CASE
+                WHEN fk.is_disabled = 1 THEN CONCAT(
+                        N' ALTER TABLE ' COLLATE DATABASE_DEFAULT, QUOTENAME(s.name) COLLATE DATABASE_DEFAULT,
+                        N'.' COLLATE DATABASE_DEFAULT, QUOTENAME(t.name) COLLATE DATABASE_DEFAULT,
+                        N' NOCHECK CONSTRAINT ' COLLATE DATABASE_DEFAULT, QUOTENAME(fk.name) COLLATE DATABASE_DEFAULT,
+                        N';')
+                ELSE N'' END
+        ) AS NVARCHAR(MAX)) COLLATE DATABASE_DEFAULT AS foreign_key_ddl
+FROM sys.foreign_keys fk
+         JOIN sys.tables t ON t.object_id = fk.parent_object_id
+         JOIN sys.schemas s ON s.schema_id = t.schema_id
+         JOIN sys.tables rt ON rt.object_id = fk.referenced_object_id
+         JOIN sys.schemas rs ON rs.schema_id = rt.schema_id
+         CROSS APPLY (SELECT STRING_AGG(
+                                     CAST(QUOTENAME(pc.name) AS NVARCHAR(MAX)) COLLATE DATABASE_DEFAULT,
+                                     N', '
+                             ) WITHIN GROUP (ORDER BY fkc.constraint_column_id) COLLATE DATABASE_DEFAULT AS parent_list,
+                             STRING_AGG(
+                                     CAST(QUOTENAME(rc.name) AS NVARCHAR(MAX)) COLLATE DATABASE_DEFAULT,
+                                     N', '
+                             ) WITHIN GROUP (ORDER BY fkc.constraint_column_id) COLLATE DATABASE_DEFAULT AS referenced_list
+                      FROM sys.foreign_key_columns fkc
+                               JOIN sys.columns pc
+                                    ON pc.object_id = fkc.parent_object_id
+                                        AND pc.column_id = fkc.parent_column_id
+                               JOIN sys.columns rc
+                                    ON rc.object_id = fkc.referenced_object_id
+                                        AND rc.column_id = fkc.referenced_column_id
+                      WHERE fkc.constraint_object_id = fk.object_id) cols
+WHERE s.name = @SchemaName
+  AND t.name = @TableName
+ORDER BY fk.name;
+"#;
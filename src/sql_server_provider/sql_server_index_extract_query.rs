@@ -3,10 +3,12 @@ WITH base AS (SELECT t.object_id,
                      -- Pre-collate names
                      QUOTENAME(s.name) COLLATE DATABASE_DEFAULT AS q_schema,
                      QUOTENAME(t.name) COLLATE DATABASE_DEFAULT AS q_table
-              FROM sys.tables t
+              -- tables and indexed views
+              FROM sys.objects t
                        JOIN sys.schemas s ON s.schema_id = t.schema_id
               WHERE s.name = @SchemaName
-                AND t.name = @TableName),
+                AND t.name = @TableName
+                AND t.type IN ('U', 'V')),
      idx AS (SELECT i.object_id,
                     i.index_id,
                     i.name,
@@ -192,4 +194,4 @@ FROM idx i
          LEFT JOIN ds d ON d.data_space_id = i.data_space_id
          LEFT JOIN part_comp pc ON pc.object_id = i.object_id AND pc.index_id = i.index_id
 ORDER BY i.is_primary_key DESC, i.is_unique DESC, i.name;
-"#;
\ No newline at end of file
+"#;
@@ -0,0 +1,27 @@
+pub const SQL_SERVER_INDEX_METADATA_QUERY: &str = r#"
+SELECT CAST(i.name AS NVARCHAR(MAX)) COLLATE DATABASE_DEFAULT,
+    CAST(i.is_unique AS NVARCHAR(1)),
+    CAST(i.is_primary_key AS NVARCHAR(1)),
+    CAST(ISNULL(i.filter_definition, N'') AS NVARCHAR(MAX)) COLLATE DATABASE_DEFAULT,
+    CAST(c.name AS NVARCHAR(MAX)) COLLATE DATABASE_DEFAULT,
+    CAST(ic.is_descending_key AS NVARCHAR(1)),
+    CAST(ic.is_included_column AS NVARCHAR(1)),
+    CAST(CASE WHEN i.is_primary_key = 1 OR i.is_unique_constraint = 1 THEN 1 ELSE 0 END AS NVARCHAR(1))
+FROM sys.indexes i
+         JOIN sys.objects t ON t.object_id = i.object_id AND t.type IN ('U', 'V')
+         JOIN sys.schemas s ON s.schema_id = t.schema_id
+         JOIN sys.index_columns ic
+              ON ic.object_id = i.object_id
+                  AND ic.index_id = i.index_id
+         JOIN sys.columns c
+              ON c.object_id = ic.object_id
+                  AND c.column_id = ic.column_id
+WHERE s.name = @SchemaName
+  AND t.name = @TableName
+  -- rowstore indexes; columnstore, XML and spatial indexes have nothing to compare with
+  AND i.type IN (1, 2)
+  AND i.is_hypothetical = 0
+  -- partitioning columns SQL Server adds on its own have key_ordinal 0
+  AND (ic.key_ordinal > 0 OR ic.is_included_column = 1)
+ORDER BY i.name, ic.is_included_column, ic.key_ordinal, ic.index_column_id;
+"#;
@@ -1,31 +1,118 @@
-use crate::config_provider::SourceDatabase;
+use crate::config_provider::{Encryption, SourceDatabase};
+use crate::index_verifier::index_verifier::{IndexColumn, IndexDefinition};
+use crate::sql_server_provider::sql_server_constraint_extract_query::{
+    SQL_SERVER_CHECK_CONSTRAINT_EXTRACT_QUERY, SQL_SERVER_DEFAULT_CONSTRAINT_EXTRACT_QUERY,
+};
+use crate::sql_server_provider::sql_server_foreign_key_extract_query::SQL_SERVER_FOREIGN_KEY_EXTRACT_QUERY;
 use crate::sql_server_provider::sql_server_index_extract_query::SQL_SERVER_INDEX_EXTRACT_QUERY;
+use crate::sql_server_provider::sql_server_index_metadata_query::SQL_SERVER_INDEX_METADATA_QUERY;
+use crate::sql_server_provider::sql_server_statistics_extract_query::SQL_SERVER_STATISTICS_EXTRACT_QUERY;
+use crate::sql_server_provider::sql_server_view_extract_query::SQL_SERVER_INDEXED_VIEW_EXTRACT_QUERY;
 use crate::version::PRODUCT_NAME;
-use anyhow::Result;
+use anyhow::{Result, bail};
 use futures_util::TryStreamExt;
+use std::fmt;
+use std::path::Path;
 use tiberius::{AuthMethod, Client, Config, EncryptionLevel, QueryItem};
 use tokio::net::TcpStream;
 use tokio_util::compat::TokioAsyncReadCompatExt;
 
+/// Objects exported next to the indexes of a table, each translated on import.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TableObjectType {
+    /// definition of a view with a unique clustered index, whose indexes come with the table's
+    IndexedView,
+    /// user-created statistics over two or more columns
+    Statistics,
+    /// untrusted ones come `WITH NOCHECK`, disabled ones are followed by `NOCHECK CONSTRAINT`
+    ForeignKey,
+    /// same flags as foreign keys
+    CheckConstraint,
+    DefaultConstraint,
+}
+
+impl TableObjectType {
+    pub const ALL: [TableObjectType; 5] = [
+        TableObjectType::IndexedView,
+        TableObjectType::Statistics,
+        TableObjectType::ForeignKey,
+        TableObjectType::CheckConstraint,
+        TableObjectType::DefaultConstraint,
+    ];
+
+    fn get_extract_query(&self) -> &'static str {
+        match self {
+            TableObjectType::IndexedView => SQL_SERVER_INDEXED_VIEW_EXTRACT_QUERY,
+            TableObjectType::Statistics => SQL_SERVER_STATISTICS_EXTRACT_QUERY,
+            TableObjectType::ForeignKey => SQL_SERVER_FOREIGN_KEY_EXTRACT_QUERY,
+            TableObjectType::CheckConstraint => SQL_SERVER_CHECK_CONSTRAINT_EXTRACT_QUERY,
+            TableObjectType::DefaultConstraint => SQL_SERVER_DEFAULT_CONSTRAINT_EXTRACT_QUERY,
+        }
+    }
+
+    /// Lower-case plural for the export totals.
+    pub fn get_plural(&self) -> &'static str {
+        match self {
+            TableObjectType::IndexedView => "indexed views",
+            TableObjectType::Statistics => "statistics",
+            TableObjectType::ForeignKey => "foreign keys",
+            TableObjectType::CheckConstraint => "check constraints",
+            TableObjectType::DefaultConstraint => "default constraints",
+        }
+    }
+}
+
+impl fmt::Display for TableObjectType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TableObjectType::IndexedView => write!(f, "Indexed View"),
+            TableObjectType::Statistics => write!(f, "Statistics"),
+            TableObjectType::ForeignKey => write!(f, "Foreign Key"),
+            TableObjectType::CheckConstraint => write!(f, "Check Constraint"),
+            TableObjectType::DefaultConstraint => write!(f, "Default Constraint"),
+        }
+    }
+}
+
 pub struct SqlServerProvider {
     config: Config,
 }
 
 impl SqlServerProvider {
-    pub fn new(source_database: &SourceDatabase) -> Self {
+    pub fn new(source_database: &SourceDatabase) -> Result<Self> {
         let mut config = Config::new();
         config.host(source_database.get_host_as_ref());
-        config.port(source_database.get_port_as_ref().clone());
+        config.port(*source_database.get_port_as_ref());
         config.database(source_database.get_database_as_ref());
         config.authentication(AuthMethod::sql_server(
             source_database.get_user_as_ref(),
-            source_database.get_password_as_ref(),
+            source_database.get_password()?,
         ));
-        config.trust_cert();
+        config.encryption(match source_database.get_encrypt() {
+            Encryption::Off => EncryptionLevel::Off,
+            Encryption::On => EncryptionLevel::On,
+            Encryption::Required => EncryptionLevel::Required,
+        });
+        match (
+            source_database.get_trust_server_certificate(),
+            source_database.get_trust_cert_ca_as_ref(),
+        ) {
+            (true, Some(_)) => {
+                bail!("trust_server_certificate and trust_cert_ca cannot be used together")
+            }
+            (true, None) => config.trust_cert(),
+            (false, Some(trust_cert_ca)) => {
+                // read on connect, checked here so a typo fails before any work is done
+                if !Path::new(trust_cert_ca).is_file() {
+                    bail!("trust_cert_ca not found: {}", trust_cert_ca);
+                }
+                config.trust_cert_ca(trust_cert_ca);
+            }
+            (false, None) => {}
+        }
         config.readonly(true);
         config.application_name(PRODUCT_NAME);
-        config.encryption(EncryptionLevel::NotSupported); // TODO: remove on PROD
-        SqlServerProvider { config }
+        Ok(SqlServerProvider { config })
     }
 
     pub async fn get_all_schemas(&self) -> Result<Vec<String>> {
@@ -53,6 +140,69 @@ impl SqlServerProvider {
         self.execute_query_2(&get_indexes_query).await
     }
 
+    /// Statements for one kind of table object, as `(name, statement)` pairs.
+    pub async fn get_all_objects_in_table(
+        &self,
+        object_type: TableObjectType,
+        schema_name: &str,
+        table_name: &str,
+    ) -> Result<Vec<(String, String)>> {
+        let schema = format!("DECLARE @SchemaName sysname = N'{}';", schema_name);
+        let table = format!("DECLARE @TableName sysname = N'{}';", table_name);
+        let get_objects_query =
+            format!("{}\n{}\n{}", schema, table, object_type.get_extract_query());
+        let objects = self.execute_query_2(&get_objects_query).await?;
+        if object_type != TableObjectType::IndexedView {
+            return Ok(objects);
+        }
+        let quoted_name = format!(
+            "{}.{}",
+            quote_sql_server_name(schema_name),
+            quote_sql_server_name(table_name)
+        );
+        Ok(objects
+            .into_iter()
+            .map(|(name, definition)| (name, qualify_view_name(&definition, &quoted_name)))
+            .collect())
+    }
+
+    /// Key columns, include columns and filters of the rowstore indexes, for verification.
+    pub async fn get_index_definitions_in_table(
+        &self,
+        schema_name: &str,
+        table_name: &str,
+    ) -> Result<Vec<IndexDefinition>> {
+        let schema = format!("DECLARE @SchemaName sysname = N'{}';", schema_name);
+        let table = format!("DECLARE @TableName sysname = N'{}';", table_name);
+        let get_index_metadata_query =
+            format!("{}\n{}\n{}", schema, table, SQL_SERVER_INDEX_METADATA_QUERY);
+        // one row per index column, ordered by index
+        let rows = self.execute_query_n(&get_index_metadata_query, 8).await?;
+        let mut index_definitions: Vec<IndexDefinition> = Vec::new();
+        for row in rows {
+            if index_definitions.last().is_none_or(|d| d.name != row[0]) {
+                index_definitions.push(IndexDefinition {
+                    name: row[0].clone(),
+                    is_unique: row[1] == "1",
+                    is_primary_key: row[2] == "1",
+                    predicate: Some(row[3].clone()).filter(|p| !p.is_empty()),
+                    is_constraint: row[7] == "1",
+                    ..IndexDefinition::default()
+                });
+            }
+            let index_definition = index_definitions.last_mut().unwrap();
+            if row[6] == "1" {
+                index_definition.include_columns.push(row[4].clone());
+            } else {
+                index_definition.key_columns.push(IndexColumn {
+                    name: row[4].clone(),
+                    descending: row[5] == "1",
+                });
+            }
+        }
+        Ok(index_definitions)
+    }
+
     async fn execute_query(&self, query: &str) -> Result<Vec<String>> {
         let tcp = TcpStream::connect(&self.config.get_addr()).await?;
         tcp.set_nodelay(true)?;
@@ -60,13 +210,9 @@ impl SqlServerProvider {
         let mut stream = client.query(query, &[]).await?;
         let mut result = Vec::new();
         while let Some(item) = stream.try_next().await? {
-            match item {
-                QueryItem::Row(row) => {
-                    let data0: &str = row.get(0).unwrap();
-
-                    result.push(data0.to_string());
-                }
-                _ => {}
+            if let QueryItem::Row(row) = item {
+                let data0: &str = row.get(0).unwrap();
+                result.push(data0.to_string());
             }
         }
         Ok(result)
@@ -79,15 +225,188 @@ impl SqlServerProvider {
         let mut stream = client.query(query, &[]).await?;
         let mut result = Vec::new();
         while let Some(item) = stream.try_next().await? {
-            match item {
-                QueryItem::Row(row) => {
-                    let data0: &str = row.get(0).unwrap();
-                    let data1: &str = row.get(1).unwrap();
-                    result.push((data0.to_string(), data1.to_string()));
-                }
-                _ => {}
+            if let QueryItem::Row(row) = item {
+                let data0: &str = row.get(0).unwrap();
+                let data1: &str = row.get(1).unwrap();
+                result.push((data0.to_string(), data1.to_string()));
             }
         }
         Ok(result)
     }
-}
\ No newline at end of file
+
+    /// All columns must be strings; NULL becomes an empty string.
+    async fn execute_query_n(&self, query: &str, columns: usize) -> Result<Vec<Vec<String>>> {
+        let tcp = TcpStream::connect(&self.config.get_addr()).await?;
+        tcp.set_nodelay(true)?;
+        let mut client = Client::connect(self.config.clone(), tcp.compat()).await?;
+        let mut stream = client.query(query, &[]).await?;
+        let mut result = Vec::new();
+        while let Some(item) = stream.try_next().await? {
+            if let QueryItem::Row(row) = item {
+                let data = (0..columns)
+                    .map(|i| row.get::<&str, usize>(i).unwrap_or_default().to_string())
+                    .collect();
+                result.push(data);
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// `[name]`, as `QUOTENAME` writes it.
+fn quote_sql_server_name(name: &str) -> String {
+    format!("[{}]", name.replace(']', "]]"))
+}
+
+/// The stored definition of a view names it as it was written in `CREATE VIEW`, maybe
+/// without its schema. The name is replaced by `quoted_name`, comments before `CREATE` are
+/// left out, and the column list, options and body are kept as written. A definition not
+/// starting with `CREATE VIEW` is returned unchanged.
+fn qualify_view_name(definition: &str, quoted_name: &str) -> String {
+    let bytes = definition.as_bytes();
+    let start = skip_comments(bytes, 0);
+    let Some(after_create) = skip_keyword(bytes, start, "CREATE") else {
+        return definition.to_string();
+    };
+    let Some(after_view) = skip_keyword(bytes, skip_comments(bytes, after_create), "VIEW") else {
+        return definition.to_string();
+    };
+    // `name`, `[name]` or `"name"` parts separated by dots
+    let mut i = skip_comments(bytes, after_view);
+    loop {
+        i = match bytes.get(i) {
+            Some(&quote @ (b'[' | b'"')) => {
+                let close = if quote == b'[' { b']' } else { b'"' };
+                let mut j = i + 1;
+                while j < bytes.len() {
+                    if bytes[j] == close && bytes.get(j + 1) == Some(&close) {
+                        j += 2;
+                    } else if bytes[j] == close {
+                        break;
+                    } else {
+                        j += 1;
+                    }
+                }
+                j + 1
+            }
+            _ => {
+                let end = i + bytes[i..].iter().take_while(|&&b| is_name_byte(b)).count();
+                if end == i {
+                    return definition.to_string();
+                }
+                end
+            }
+        };
+        // an unterminated quoted name
+        if i > bytes.len() {
+            return definition.to_string();
+        }
+        let next = skip_comments(bytes, i);
+        if bytes.get(next) != Some(&b'.') {
+            break;
+        }
+        i = skip_comments(bytes, next + 1);
+    }
+    format!("CREATE VIEW {}{}", quoted_name, &definition[i..])
+}
+
+/// Position of the first byte after whitespace, `--` and `/* */` comments from `i`.
+fn skip_comments(bytes: &[u8], mut i: usize) -> usize {
+    loop {
+        if bytes.get(i).is_some_and(|b| b.is_ascii_whitespace()) {
+            i += 1;
+        } else if bytes[i..].starts_with(b"--") {
+            i += bytes[i..]
+                .iter()
+                .position(|&b| b == b'\n')
+                .unwrap_or(bytes.len() - i);
+        } else if bytes[i..].starts_with(b"/*") {
+            // T-SQL block comments nest
+            let mut depth = 0;
+            while i < bytes.len() {
+                if bytes[i..].starts_with(b"/*") {
+                    depth += 1;
+                    i += 2;
+                } else if bytes[i..].starts_with(b"*/") {
+                    depth -= 1;
+                    i += 2;
+                    if depth == 0 {
+                        break;
+                    }
+                } else {
+                    i += 1;
+                }
+            }
+        } else {
+            return i;
+        }
+    }
+}
+
+/// Position after `keyword` at `i`, case-insensitive and not followed by a name byte.
+fn skip_keyword(bytes: &[u8], i: usize, keyword: &str) -> Option<usize> {
+    let end = i + keyword.len();
+    (bytes.get(i..end)?.eq_ignore_ascii_case(keyword.as_bytes())
+        && !bytes.get(end).is_some_and(|&b| is_name_byte(b)))
+    .then_some(end)
+}
+
+/// Byte of an unquoted name; bytes of non-ASCII characters all count.
+fn is_name_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || matches!(b, b'_' | b'@' | b'#' | b'$') || !b.is_ascii()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn qualify_view_name_adds_the_schema() {
+        assert_eq!(
+            qualify_view_name(
+                "CREATE VIEW OrderTotals WITH SCHEMABINDING AS SELECT 1 AS a FROM dbo.T",
+                "[dbo].[OrderTotals]"
+            ),
+            "CREATE VIEW [dbo].[OrderTotals] WITH SCHEMABINDING AS SELECT 1 AS a FROM dbo.T"
+        );
+        assert_eq!(
+            qualify_view_name(
+                "create view [dbo] . [Order.Totals]]] with schemabinding as select 1 as a",
+                "[sales].[Order.Totals]]]"
+            ),
+            "CREATE VIEW [sales].[Order.Totals]]] with schemabinding as select 1 as a"
+        );
+    }
+
+    #[test]
+    fn qualify_view_name_keeps_the_column_list() {
+        assert_eq!(
+            qualify_view_name(
+                "CREATE VIEW dbo.V (Id, Total) WITH SCHEMABINDING AS SELECT a, b FROM dbo.T",
+                "[dbo].[V]"
+            ),
+            "CREATE VIEW [dbo].[V] (Id, Total) WITH SCHEMABINDING AS SELECT a, b FROM dbo.T"
+        );
+    }
+
+    #[test]
+    fn qualify_view_name_skips_leading_comments() {
+        let definition = "-- not WITH SCHEMABINDING yet\n/* old /* nested */ version */\nCREATE /* c */ VIEW\n  V WITH SCHEMABINDING AS SELECT a FROM dbo.T";
+        assert_eq!(
+            qualify_view_name(definition, "[dbo].[V]"),
+            "CREATE VIEW [dbo].[V] WITH SCHEMABINDING AS SELECT a FROM dbo.T"
+        );
+    }
+
+    #[test]
+    fn qualify_view_name_leaves_other_text_unchanged() {
+        for definition in [
+            "SELECT 1",
+            "CREATE VIEWS x",
+            "CREATE VIEW",
+            "-- only a comment",
+        ] {
+            assert_eq!(qualify_view_name(definition, "[dbo].[V]"), definition);
+        }
+    }
+}
@@ -0,0 +1,34 @@
+pub const SQL_SERVER_STATISTICS_EXTRACT_QUERY: &str = r#"
+SELECT QUOTENAME(st.name),
+    CAST(CONCAT(
+            CAST(N'' AS NVARCHAR(MAX)),
+            N'CREATE STATISTICS ' COLLATE DATABASE_DEFAULT, QUOTENAME(st.name) COLLATE DATABASE_DEFAULT,
+            N' ON ' COLLATE DATABASE_DEFAULT, QUOTENAME(s.name) COLLATE DATABASE_DEFAULT,
+            N'.' COLLATE DATABASE_DEFAULT, QUOTENAME(t.name) COLLATE DATABASE_DEFAULT,
+            N' (' COLLATE DATABASE_DEFAULT, cols.column_list, N')',
+            CASE
+                WHEN st.has_filter = 1 THEN CONCAT(N' WHERE ' COLLATE DATABASE_DEFAULT, st.filter_definition)
+                ELSE N'' END,
+            N';'
+        ) AS NVARCHAR(MAX)) COLLATE DATABASE_DEFAULT AS statistics_ddl
+FROM sys.stats st
+         JOIN sys.tables t ON t.object_id = st.object_id
+         JOIN sys.schemas s ON s.schema_id = t.schema_id
+         CROSS APPLY (SELECT STRING_AGG(
+                                     CAST(QUOTENAME(c.name) AS NVARCHAR(MAX)) COLLATE DATABASE_DEFAULT,
+                                     N', '
+                             ) WITHIN GROUP (ORDER BY sc.stats_column_id) COLLATE DATABASE_DEFAULT AS column_list,
+                             COUNT(*) AS column_count
+                      FROM sys.stats_columns sc
+                               JOIN sys.columns c
+                                    ON c.object_id = sc.object_id
+                                        AND c.column_id = sc.column_id
+                      WHERE sc.object_id = st.object_id
+                        AND sc.stats_id = st.stats_id) cols
+WHERE s.name = @SchemaName
+  AND t.name = @TableName
+  -- hand-made statistics only; auto-created and index statistics are single column or come with the index
+  AND st.user_created = 1
+  AND cols.column_count > 1
+ORDER BY st.name;
+"#;
@@ -0,0 +1,20 @@
+pub const SQL_SERVER_INDEXED_VIEW_EXTRACT_QUERY: &str = r#"
+SELECT QUOTENAME(v.name),
+    -- the stored definition as written, `qualify_view_name` adds the schema to its name
+    CAST(COALESCE(
+            m.definition COLLATE DATABASE_DEFAULT,
+            -- definition is NULL for encrypted views, the header alone is reported on import
+            CONCAT(
+                N'CREATE VIEW ' COLLATE DATABASE_DEFAULT, QUOTENAME(s.name) COLLATE DATABASE_DEFAULT,
+                N'.' COLLATE DATABASE_DEFAULT, QUOTENAME(v.name) COLLATE DATABASE_DEFAULT,
+                N' WITH ENCRYPTION, SCHEMABINDING' COLLATE DATABASE_DEFAULT
+            )
+        ) AS NVARCHAR(MAX)) COLLATE DATABASE_DEFAULT AS view_ddl
+FROM sys.views v
+         JOIN sys.schemas s ON s.schema_id = v.schema_id
+         JOIN sys.sql_modules m ON m.object_id = v.object_id
+WHERE s.name = @SchemaName
+  AND v.name = @TableName
+  -- indexed views have a unique clustered index
+  AND EXISTS (SELECT 1 FROM sys.indexes i WHERE i.object_id = v.object_id AND i.index_id = 1);
+"#;
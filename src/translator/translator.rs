@@ -0,0 +1,935 @@
+use crate::ddl_parser::ddl_parser::{
+    QualifiedName, Token, parse_qualified_name, quote_identifier, quote_literal, tokenize,
+};
+
+/// Result of turning an exported SQL Server statement into PostgreSQL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Translation {
+    /// Already PostgreSQL, or nothing this translator knows about
+    Unchanged,
+    Translated(String),
+    /// No PostgreSQL equivalent; the reason is reported instead of running the statement
+    Unsupported(String),
+}
+
+pub fn translate_statement(query: &str) -> Translation {
+    let tokens = tokenize(query);
+    let keyword = |i: usize, keyword: &str| tokens.get(i).is_some_and(|t| t.is_keyword(keyword));
+    // PostgreSQL spells it `CREATE STATISTICS ... ON columns FROM table`
+    if keyword(0, "CREATE")
+        && keyword(1, "STATISTICS")
+        && !tokens.iter().any(|t| t.is_keyword("FROM"))
+    {
+        translate_statistics(&tokens[2..])
+    } else if keyword(0, "CREATE") && keyword(1, "VIEW") {
+        translate_view(&tokens[2..])
+    } else if keyword(0, "CREATE") && is_sql_server_index(&tokens[1..]) {
+        translate_index(&tokens[1..])
+    } else if keyword(0, "ALTER") && keyword(1, "TABLE") && is_sql_server_index(&tokens[2..]) {
+        translate_constraint(&tokens[2..])
+    } else if keyword(0, "ALTER") && keyword(1, "TABLE") {
+        translate_alter_table(&tokens[2..])
+    } else {
+        Translation::Unchanged
+    }
+}
+
+/// The export spells out `CLUSTERED` or `NONCLUSTERED` on every index and key,
+/// PostgreSQL has neither keyword.
+fn is_sql_server_index(tokens: &[Token]) -> bool {
+    tokens
+        .iter()
+        .take_while(|t| **t != Token::Symbol('('))
+        .any(|t| t.is_keyword("CLUSTERED") || t.is_keyword("NONCLUSTERED"))
+}
+
+/// `[UNIQUE] CLUSTERED | NONCLUSTERED INDEX name ON table (column [ASC | DESC], ...)
+/// [INCLUDE (column, ...)] [WHERE ...] [ON storage] [WITH (option = value, ...)]` becomes
+/// `CREATE [UNIQUE] INDEX IF NOT EXISTS name ON table (...) [INCLUDE (...)] [WITH (fillfactor = n)] [WHERE ...]`.
+/// Storage and all options but the fill factor are dropped.
+fn translate_index(tokens: &[Token]) -> Translation {
+    let unique = tokens.first().is_some_and(|t| t.is_keyword("UNIQUE"));
+    let Some(index_position) = tokens.iter().position(|t| t.is_keyword("INDEX")) else {
+        return Translation::Unchanged;
+    };
+    let Some((index_name, next)) = parse_qualified_name(tokens, index_position + 1) else {
+        return Translation::Unchanged;
+    };
+    if !tokens.get(next).is_some_and(|t| t.is_keyword("ON")) {
+        return Translation::Unchanged;
+    }
+    let Some((table, i)) = parse_qualified_name(tokens, next + 1) else {
+        return Translation::Unchanged;
+    };
+    let Some((key_columns, mut i)) = translate_column_list(tokens, i, true) else {
+        return Translation::Unchanged;
+    };
+    let mut include_columns = None;
+    if tokens.get(i).is_some_and(|t| t.is_keyword("INCLUDE")) {
+        let Some((columns, next)) = translate_column_list(tokens, i + 1, false) else {
+            return Translation::Unchanged;
+        };
+        include_columns = Some(columns);
+        i = next;
+    }
+    let mut predicate = None;
+    if tokens.get(i).is_some_and(|t| t.is_keyword("WHERE")) {
+        let end = find_clause_end(tokens, i + 1);
+        match translate_expression(&tokens[i + 1..end]) {
+            Ok(expression) => predicate = Some(expression),
+            Err(reason) => {
+                return Translation::Unsupported(format!(
+                    "filter of index <{}>: {}",
+                    index_name.name, reason
+                ));
+            }
+        }
+        i = end;
+    }
+    let fill_factor = match translate_options(&index_name, &tokens[i..]) {
+        Ok(fill_factor) => fill_factor,
+        Err(unsupported) => return unsupported,
+    };
+    let mut statement = format!(
+        "CREATE {}INDEX IF NOT EXISTS {} ON {} ({})",
+        if unique { "UNIQUE " } else { "" },
+        to_postgres_identifier(&index_name.name),
+        to_postgres_name(&table),
+        key_columns.join(", ")
+    );
+    if let Some(include_columns) = include_columns {
+        statement.push_str(&format!(" INCLUDE ({})", include_columns.join(", ")));
+    }
+    if let Some(fill_factor) = fill_factor {
+        statement.push_str(&format!(" WITH (fillfactor = {})", fill_factor));
+    }
+    if let Some(predicate) = predicate {
+        statement.push_str(&format!(" WHERE {}", predicate));
+    }
+    statement.push(';');
+    Translation::Translated(statement)
+}
+
+/// `table ADD CONSTRAINT name PRIMARY KEY | UNIQUE CLUSTERED | NONCLUSTERED (column [ASC | DESC], ...)
+/// [ON storage] [WITH (option = value, ...)]` becomes `ALTER TABLE table ADD CONSTRAINT name
+/// PRIMARY KEY | UNIQUE (column, ...) [WITH (fillfactor = n)]`. PostgreSQL constraint columns
+/// have no direction, so `DESC` is lost.
+fn translate_constraint(tokens: &[Token]) -> Translation {
+    let Some((table, next)) = parse_qualified_name(tokens, 0) else {
+        return Translation::Unchanged;
+    };
+    let keyword = |i: usize, keyword: &str| tokens.get(i).is_some_and(|t| t.is_keyword(keyword));
+    if !keyword(next, "ADD") || !keyword(next + 1, "CONSTRAINT") {
+        return Translation::Unchanged;
+    }
+    let Some((constraint_name, mut i)) = parse_qualified_name(tokens, next + 2) else {
+        return Translation::Unchanged;
+    };
+    let constraint_type = if keyword(i, "PRIMARY") && keyword(i + 1, "KEY") {
+        i += 2;
+        "PRIMARY KEY"
+    } else if keyword(i, "UNIQUE") {
+        i += 1;
+        "UNIQUE"
+    } else {
+        return Translation::Unchanged;
+    };
+    if keyword(i, "CLUSTERED") || keyword(i, "NONCLUSTERED") {
+        i += 1;
+    }
+    let Some((columns, i)) = translate_column_list(tokens, i, false) else {
+        return Translation::Unchanged;
+    };
+    let fill_factor = match translate_options(&constraint_name, &tokens[i..]) {
+        Ok(fill_factor) => fill_factor,
+        Err(unsupported) => return unsupported,
+    };
+    let mut statement = format!(
+        "ALTER TABLE {} ADD CONSTRAINT {} {} ({})",
+        to_postgres_name(&table),
+        to_postgres_identifier(&constraint_name.name),
+        constraint_type,
+        columns.join(", ")
+    );
+    if let Some(fill_factor) = fill_factor {
+        statement.push_str(&format!(" WITH (fillfactor = {})", fill_factor));
+    }
+    statement.push(';');
+    Translation::Translated(statement)
+}
+
+/// `table WITH CHECK | NOCHECK ADD CONSTRAINT name FOREIGN KEY ... | CHECK (...)` becomes
+/// `ALTER TABLE table ADD CONSTRAINT name ... NOT VALID`, followed by `VALIDATE CONSTRAINT`
+/// unless the constraint was not trusted in SQL Server. Adding it `NOT VALID` is quick; the
+/// validation scans the table without blocking writes.
+/// `table ADD CONSTRAINT name DEFAULT expression FOR column` becomes
+/// `ALTER TABLE table ALTER COLUMN column SET DEFAULT expression`.
+fn translate_alter_table(tokens: &[Token]) -> Translation {
+    let Some((table, next)) = parse_qualified_name(tokens, 0) else {
+        return Translation::Unchanged;
+    };
+    let keyword = |i: usize, keyword: &str| tokens.get(i).is_some_and(|t| t.is_keyword(keyword));
+    // PostgreSQL has no `WITH CHECK`, so this is T-SQL
+    let trusted = keyword(next + 1, "CHECK");
+    let with_check = keyword(next, "WITH") && (trusted || keyword(next + 1, "NOCHECK"));
+    let add = if with_check { next + 2 } else { next };
+    if !keyword(add, "ADD") || !keyword(add + 1, "CONSTRAINT") {
+        return Translation::Unchanged;
+    }
+    let Some((constraint_name, i)) = parse_qualified_name(tokens, add + 2) else {
+        return Translation::Unchanged;
+    };
+    let quoted_table = to_postgres_name(&table);
+    if !with_check {
+        return if keyword(i, "DEFAULT") {
+            translate_default(&quoted_table, &constraint_name, tokens, i + 1)
+        } else {
+            Translation::Unchanged
+        };
+    }
+    let body = if keyword(i, "FOREIGN") && keyword(i + 1, "KEY") {
+        translate_foreign_key(tokens, i + 2)
+    } else if keyword(i, "CHECK") {
+        let end = find_clause_end(tokens, i + 1);
+        translate_expression(&tokens[i + 1..end])
+            .map(|expression| (format!("CHECK {}", parenthesize(expression)), end))
+            .map_err(|reason| format!("check constraint <{}>: {}", constraint_name.name, reason))
+    } else {
+        return Translation::Unchanged;
+    };
+    let (body, i) = match body {
+        Ok(body) => body,
+        Err(reason) if reason.is_empty() => return Translation::Unchanged,
+        Err(reason) => return Translation::Unsupported(reason),
+    };
+    // a disabled constraint is followed by `ALTER TABLE ... NOCHECK CONSTRAINT name`
+    if tokens.iter().skip(i).any(|t| t.is_keyword("NOCHECK")) {
+        return Translation::Unsupported(format!(
+            "constraint <{}> is disabled, PostgreSQL cannot disable a constraint",
+            constraint_name.name
+        ));
+    }
+    let quoted_constraint_name = to_postgres_identifier(&constraint_name.name);
+    let mut statement = format!(
+        "ALTER TABLE {} ADD CONSTRAINT {} {} NOT VALID;",
+        quoted_table, quoted_constraint_name, body
+    );
+    if trusted {
+        statement.push_str(&format!(
+            "\nALTER TABLE {} VALIDATE CONSTRAINT {};",
+            quoted_table, quoted_constraint_name
+        ));
+    }
+    Translation::Translated(statement)
+}
+
+/// `(column, ...) REFERENCES table (column, ...) [ON DELETE action] [ON UPDATE action]
+/// [NOT FOR REPLICATION]`; an empty error means the statement is not what the export writes.
+fn translate_foreign_key(tokens: &[Token], start: usize) -> Result<(String, usize), String> {
+    let keyword = |i: usize, keyword: &str| tokens.get(i).is_some_and(|t| t.is_keyword(keyword));
+    let (columns, i) = translate_column_list(tokens, start, false).ok_or_else(String::new)?;
+    if !keyword(i, "REFERENCES") {
+        return Err(String::new());
+    }
+    let (referenced_table, i) = parse_qualified_name(tokens, i + 1).ok_or_else(String::new)?;
+    let (referenced_columns, mut i) =
+        translate_column_list(tokens, i, false).ok_or_else(String::new)?;
+    let mut body = format!(
+        "FOREIGN KEY ({}) REFERENCES {} ({})",
+        columns.join(", "),
+        to_postgres_name(&referenced_table),
+        referenced_columns.join(", ")
+    );
+    while keyword(i, "ON") && (keyword(i + 1, "DELETE") || keyword(i + 1, "UPDATE")) {
+        let event = if keyword(i + 1, "DELETE") {
+            "DELETE"
+        } else {
+            "UPDATE"
+        };
+        let (action, length) = if keyword(i + 2, "NO") && keyword(i + 3, "ACTION") {
+            ("NO ACTION", 2)
+        } else if keyword(i + 2, "CASCADE") {
+            ("CASCADE", 1)
+        } else if keyword(i + 2, "SET") && keyword(i + 3, "NULL") {
+            ("SET NULL", 2)
+        } else if keyword(i + 2, "SET") && keyword(i + 3, "DEFAULT") {
+            ("SET DEFAULT", 2)
+        } else {
+            return Err(String::new());
+        };
+        body.push_str(&format!(" ON {} {}", event, action));
+        i += 2 + length;
+    }
+    Ok((body, i))
+}
+
+/// PostgreSQL defaults belong to the column and have no name.
+fn translate_default(
+    quoted_table: &str,
+    constraint_name: &QualifiedName,
+    tokens: &[Token],
+    start: usize,
+) -> Translation {
+    let Some(for_position) = tokens[start..]
+        .iter()
+        .position(|t| t.is_keyword("FOR"))
+        .map(|p| start + p)
+    else {
+        return Translation::Unchanged;
+    };
+    let Some(column) = tokens.get(for_position + 1).and_then(|t| t.as_identifier()) else {
+        return Translation::Unchanged;
+    };
+    match translate_expression(&tokens[start..for_position]) {
+        Ok(expression) => Translation::Translated(format!(
+            "ALTER TABLE {} ALTER COLUMN {} SET DEFAULT {};",
+            quoted_table,
+            to_postgres_identifier(column),
+            expression
+        )),
+        Err(reason) => Translation::Unsupported(format!(
+            "default constraint <{}>: {}",
+            constraint_name.name, reason
+        )),
+    }
+}
+
+/// `(column [ASC | DESC], ...)` starting at `start`; returns the columns and the position
+/// after the closing parenthesis. `DESC` is kept only when `with_direction` is set.
+fn translate_column_list(
+    tokens: &[Token],
+    start: usize,
+    with_direction: bool,
+) -> Option<(Vec<String>, usize)> {
+    if tokens.get(start) != Some(&Token::Symbol('(')) {
+        return None;
+    }
+    let mut columns: Vec<String> = Vec::new();
+    let mut i = start + 1;
+    loop {
+        match tokens.get(i)? {
+            Token::Symbol(')') => return Some((columns, i + 1)),
+            Token::Symbol(',') => {}
+            token if token.is_keyword("ASC") => {}
+            token if token.is_keyword("DESC") => {
+                if with_direction {
+                    columns.last_mut()?.push_str(" DESC");
+                }
+            }
+            token => columns.push(to_postgres_identifier(token.as_identifier()?)),
+        }
+        i += 1;
+    }
+}
+
+/// A `WHERE` clause runs up to the storage or options clause of the statement.
+fn find_clause_end(tokens: &[Token], start: usize) -> usize {
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate().skip(start) {
+        match token {
+            Token::Symbol('(') => depth += 1,
+            Token::Symbol(')') => depth -= 1,
+            Token::Symbol(';') if depth == 0 => return i,
+            token if depth == 0 && (token.is_keyword("ON") || token.is_keyword("WITH")) => {
+                return i;
+            }
+            _ => {}
+        }
+    }
+    tokens.len()
+}
+
+enum FunctionTranslation {
+    Keep,
+    Rename(&'static str),
+    /// replaces a call without arguments as a whole, calls with arguments are unsupported
+    Call(&'static str),
+}
+
+/// T-SQL functions with a PostgreSQL counterpart.
+const FUNCTION_TRANSLATIONS: [(&str, FunctionTranslation); 31] = [
+    ("ABS", FunctionTranslation::Keep),
+    ("AVG", FunctionTranslation::Keep),
+    ("CEILING", FunctionTranslation::Rename("ceil")),
+    ("COALESCE", FunctionTranslation::Keep),
+    ("COUNT", FunctionTranslation::Keep),
+    ("COUNT_BIG", FunctionTranslation::Rename("count")),
+    ("DATALENGTH", FunctionTranslation::Rename("octet_length")),
+    ("DB_NAME", FunctionTranslation::Call("current_database()")),
+    ("FLOOR", FunctionTranslation::Keep),
+    ("GETDATE", FunctionTranslation::Call("LOCALTIMESTAMP")),
+    (
+        "GETUTCDATE",
+        FunctionTranslation::Call("(now() AT TIME ZONE 'utc')"),
+    ),
+    ("ISNULL", FunctionTranslation::Rename("coalesce")),
+    ("LEN", FunctionTranslation::Rename("length")),
+    ("LOWER", FunctionTranslation::Keep),
+    ("LTRIM", FunctionTranslation::Keep),
+    ("MAX", FunctionTranslation::Keep),
+    ("MIN", FunctionTranslation::Keep),
+    ("NEWID", FunctionTranslation::Call("gen_random_uuid()")),
+    (
+        "NEWSEQUENTIALID",
+        FunctionTranslation::Call("gen_random_uuid()"),
+    ),
+    ("REPLACE", FunctionTranslation::Keep),
+    ("ROUND", FunctionTranslation::Keep),
+    ("RTRIM", FunctionTranslation::Keep),
+    ("SUBSTRING", FunctionTranslation::Keep),
+    ("SUM", FunctionTranslation::Keep),
+    ("SUSER_NAME", FunctionTranslation::Call("current_user")),
+    ("SUSER_SNAME", FunctionTranslation::Call("current_user")),
+    ("SYSDATETIME", FunctionTranslation::Call("LOCALTIMESTAMP")),
+    (
+        "SYSDATETIMEOFFSET",
+        FunctionTranslation::Call("CURRENT_TIMESTAMP"),
+    ),
+    (
+        "SYSUTCDATETIME",
+        FunctionTranslation::Call("(now() AT TIME ZONE 'utc')"),
+    ),
+    ("UPPER", FunctionTranslation::Keep),
+    ("USER_NAME", FunctionTranslation::Call("current_user")),
+];
+
+/// Words that may precede a parenthesis without being a function call.
+const EXPRESSION_KEYWORDS: [&str; 19] = [
+    "AND", "AS", "BETWEEN", "BY", "ELSE", "EXISTS", "FROM", "HAVING", "IN", "IS", "JOIN", "LIKE",
+    "NOT", "ON", "OR", "SELECT", "THEN", "WHEN", "WHERE",
+];
+
+/// Rewrites a T-SQL expression of a filtered index, check constraint, default or view into
+/// PostgreSQL: operators and literals are kept, bracketed names are mapped, `N'...'`
+/// literals lose their prefix and known functions are renamed. The error names the part
+/// that has no translation.
+fn translate_expression(tokens: &[Token]) -> Result<String, String> {
+    let is_operator = |t: &Token| matches!(t, Token::Symbol('<' | '>' | '=' | '!'));
+    let mut expression = String::new();
+    let mut previous: Option<&Token> = None;
+    let mut i = 0;
+    while let Some(token) = tokens.get(i) {
+        let separated = match previous {
+            None | Some(Token::Symbol('(' | '.')) => false,
+            Some(previous) => {
+                !(matches!(token, Token::Symbol(')' | ',' | '.'))
+                    || is_operator(previous) && is_operator(token)
+                    || matches!(token, Token::Symbol('('))
+                        && matches!(previous, Token::Word(word) if is_function(word)))
+            }
+        };
+        if separated {
+            expression.push(' ');
+        }
+        match token {
+            Token::Word(word)
+                if is_function(word) && tokens.get(i + 1) == Some(&Token::Symbol('(')) =>
+            {
+                let translation = FUNCTION_TRANSLATIONS
+                    .iter()
+                    .find(|(name, _)| word.eq_ignore_ascii_case(name))
+                    .map(|(_, translation)| translation)
+                    .ok_or_else(|| {
+                        format!(
+                            "function {} has no PostgreSQL translation",
+                            word.to_uppercase()
+                        )
+                    })?;
+                match translation {
+                    FunctionTranslation::Keep => expression.push_str(&word.to_lowercase()),
+                    FunctionTranslation::Rename(name) => expression.push_str(name),
+                    FunctionTranslation::Call(call) => {
+                        // e.g. `DB_NAME(5)` names another database
+                        if tokens.get(i + 2) != Some(&Token::Symbol(')')) {
+                            return Err(format!(
+                                "function {} with arguments has no PostgreSQL translation",
+                                word.to_uppercase()
+                            ));
+                        }
+                        expression.push_str(call);
+                        // skip `()`
+                        i += 2;
+                    }
+                }
+            }
+            Token::Word(word) => {
+                if word.eq_ignore_ascii_case("LIKE")
+                    && matches!(tokens.get(i + 1), Some(Token::StringLiteral(pattern)) if pattern.contains('['))
+                {
+                    return Err(
+                        "LIKE with a character class has no PostgreSQL translation".to_string()
+                    );
+                }
+                expression.push_str(word)
+            }
+            Token::QuotedIdentifier(name) => expression.push_str(&to_postgres_identifier(name)),
+            Token::StringLiteral(value) => expression.push_str(&quote_literal(value)),
+            Token::Symbol(symbol) => expression.push(*symbol),
+        }
+        previous = tokens.get(i);
+        i += 1;
+    }
+    Ok(expression)
+}
+
+/// SQL Server stores check definitions in parentheses, PostgreSQL requires them.
+fn parenthesize(expression: String) -> String {
+    let mut depth = 0;
+    let enclosed = expression.starts_with('(')
+        && expression.char_indices().all(|(i, c)| {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+            depth > 0 || i == expression.len() - 1
+        });
+    if enclosed {
+        expression
+    } else {
+        format!("({})", expression)
+    }
+}
+
+fn is_function(word: &str) -> bool {
+    word.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && !EXPRESSION_KEYWORDS
+            .iter()
+            .any(|keyword| word.eq_ignore_ascii_case(keyword))
+}
+
+/// Reads `WITH (option = value, ...)` clauses and returns the fill factor worth keeping.
+/// `IGNORE_DUP_KEY = ON` changes what a unique index accepts, so it cannot just be dropped.
+fn translate_options(name: &QualifiedName, tokens: &[Token]) -> Result<Option<u32>, Translation> {
+    let mut fill_factor = None;
+    for (i, token) in tokens.iter().enumerate() {
+        let value = tokens.get(i + 2);
+        if token.is_keyword("IGNORE_DUP_KEY") && value.is_some_and(|v| v.is_keyword("ON")) {
+            return Err(Translation::Unsupported(format!(
+                "<{}> uses IGNORE_DUP_KEY, PostgreSQL has no equivalent",
+                name.name
+            )));
+        }
+        if token.is_keyword("FILLFACTOR") {
+            // 0 and 100 both mean full pages; PostgreSQL accepts 10 to 100
+            fill_factor = value
+                .and_then(|v| v.as_identifier())
+                .and_then(|v| v.parse::<u32>().ok())
+                .filter(|v| (10..100).contains(v));
+        }
+    }
+    Ok(fill_factor)
+}
+
+/// `name [(column, ...)] WITH SCHEMABINDING [, option ...] AS SELECT ...`, the definition of an
+/// indexed view, becomes `CREATE MATERIALIZED VIEW IF NOT EXISTS name [(column, ...)] AS SELECT ...`.
+/// Its unique clustered index turns into a unique index, which `REFRESH MATERIALIZED VIEW
+/// CONCURRENTLY` needs.
+fn translate_view(tokens: &[Token]) -> Translation {
+    let Some((view_name, mut i)) = parse_qualified_name(tokens, 0) else {
+        return Translation::Unchanged;
+    };
+    let mut columns = None;
+    if tokens.get(i) == Some(&Token::Symbol('(')) {
+        let Some((view_columns, next)) = translate_column_list(tokens, i, false) else {
+            return Translation::Unchanged;
+        };
+        columns = Some(view_columns);
+        i = next;
+    }
+    // PostgreSQL has no `SCHEMABINDING`, so this is T-SQL
+    if !tokens.get(i).is_some_and(|t| t.is_keyword("WITH"))
+        || !tokens[i..]
+            .iter()
+            .take_while(|t| !t.is_keyword("AS"))
+            .any(|t| t.is_keyword("SCHEMABINDING"))
+    {
+        return Translation::Unchanged;
+    }
+    let Some(as_position) = tokens.iter().skip(i).position(|t| t.is_keyword("AS")) else {
+        return Translation::Unsupported(format!(
+            "view <{}> has no definition, it may be encrypted",
+            view_name.name
+        ));
+    };
+    let mut body = &tokens[i + as_position + 1..];
+    while let Some((Token::Symbol(';'), rest)) = body.split_last() {
+        body = rest;
+    }
+    if let Err(reason) = check_view_body(body) {
+        return Translation::Unsupported(format!("view <{}>: {}", view_name.name, reason));
+    }
+    match translate_expression(body) {
+        Ok(query) => Translation::Translated(format!(
+            "CREATE MATERIALIZED VIEW IF NOT EXISTS {}{} AS {};",
+            to_postgres_name(&view_name),
+            columns.map_or(String::new(), |c| format!(" ({})", c.join(", "))),
+            query
+        )),
+        Err(reason) => Translation::Unsupported(format!("view <{}>: {}", view_name.name, reason)),
+    }
+}
+
+/// Finds T-SQL query syntax the token rewrite would get wrong: `TOP` and `alias = expression`
+/// columns.
+fn check_view_body(tokens: &[Token]) -> Result<(), String> {
+    if !tokens.first().is_some_and(|t| t.is_keyword("SELECT")) {
+        return Err("the body is not a single SELECT".to_string());
+    }
+    if tokens.iter().any(|t| t.is_keyword("TOP")) {
+        return Err("TOP has no PostgreSQL translation".to_string());
+    }
+    let mut depth = 0;
+    let mut column_start = true;
+    for (i, token) in tokens.iter().enumerate().skip(1) {
+        match token {
+            Token::Symbol('(') => depth += 1,
+            Token::Symbol(')') => depth -= 1,
+            Token::Symbol(',') if depth == 0 => {
+                column_start = true;
+                continue;
+            }
+            token if depth == 0 && token.is_keyword("FROM") => break,
+            token
+                if column_start
+                    && token.as_identifier().is_some()
+                    && tokens.get(i + 1) == Some(&Token::Symbol('=')) =>
+            {
+                return Err(format!(
+                    "column alias <{}> = expression has no PostgreSQL translation",
+                    token.as_identifier().unwrap_or_default()
+                ));
+            }
+            _ => {}
+        }
+        column_start = false;
+    }
+    Ok(())
+}
+
+/// `CREATE STATISTICS name ON table (column, ...) [WHERE ...] [WITH ...]` becomes
+/// `CREATE STATISTICS IF NOT EXISTS name (dependencies, ndistinct) ON column, ... FROM table`.
+/// The statistics object is created in the schema of its table.
+fn translate_statistics(tokens: &[Token]) -> Translation {
+    let Some((statistics_name, next)) = parse_qualified_name(tokens, 0) else {
+        return Translation::Unchanged;
+    };
+    if !tokens.get(next).is_some_and(|t| t.is_keyword("ON")) {
+        return Translation::Unchanged;
+    }
+    let Some((table, mut i)) = parse_qualified_name(tokens, next + 1) else {
+        return Translation::Unchanged;
+    };
+    if tokens.get(i) != Some(&Token::Symbol('(')) {
+        return Translation::Unchanged;
+    }
+    let mut columns = Vec::new();
+    i += 1;
+    while let Some(token) = tokens.get(i) {
+        match token {
+            Token::Symbol(')') => break,
+            Token::Symbol(',') => {}
+            token => match token.as_identifier() {
+                Some(column) => columns.push(to_postgres_identifier(column)),
+                None => return Translation::Unchanged,
+            },
+        }
+        i += 1;
+    }
+    if tokens.get(i + 1).is_some_and(|t| t.is_keyword("WHERE")) {
+        return Translation::Unsupported(format!(
+            "filtered statistics <{}> have no PostgreSQL equivalent",
+            statistics_name.name
+        ));
+    }
+    if columns.len() < 2 {
+        return Translation::Unsupported(format!(
+            "statistics <{}> cover a single column, PostgreSQL keeps those itself",
+            statistics_name.name
+        ));
+    }
+    let statistics_name = QualifiedName {
+        schema: table.schema.clone(),
+        ..statistics_name
+    };
+    Translation::Translated(format!(
+        "CREATE STATISTICS IF NOT EXISTS {} (dependencies, ndistinct) ON {} FROM {};",
+        to_postgres_name(&statistics_name),
+        columns.join(", "),
+        to_postgres_name(&table)
+    ))
+}
+
+// PostgreSQL truncates longer identifiers
+const MAX_IDENTIFIER_LENGTH_IN_BYTES: usize = 63;
+
+/// Name mapping from SQL Server to PostgreSQL: folded to lower case, the way PostgreSQL
+/// stores unquoted names, and cut to the identifier length limit.
+pub fn map_name(name: &str) -> String {
+    let mut mapped_name = name.to_lowercase();
+    if mapped_name.len() > MAX_IDENTIFIER_LENGTH_IN_BYTES {
+        let mut end = MAX_IDENTIFIER_LENGTH_IN_BYTES;
+        while !mapped_name.is_char_boundary(end) {
+            end -= 1;
+        }
+        mapped_name.truncate(end);
+    }
+    mapped_name
+}
+
+fn to_postgres_identifier(name: &str) -> String {
+    quote_identifier(&map_name(name))
+}
+
+fn to_postgres_name(name: &QualifiedName) -> String {
+    match &name.schema {
+        Some(schema) => format!(
+            "{}.{}",
+            to_postgres_identifier(schema),
+            to_postgres_identifier(&name.name)
+        ),
+        None => to_postgres_identifier(&name.name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translated(statement: &str) -> Translation {
+        Translation::Translated(statement.to_string())
+    }
+
+    fn unsupported(reason: &str) -> Translation {
+        Translation::Unsupported(reason.to_string())
+    }
+
+    fn check(cases: &[(&str, Translation)]) {
+        for (query, expected) in cases {
+            assert_eq!(&translate_statement(query), expected, "{}", query);
+        }
+    }
+
+    #[test]
+    fn translates_indexes() {
+        check(&[
+            (
+                "CREATE NONCLUSTERED INDEX [IX_Orders_Date] ON [dbo].[Orders] ([OrderDate] ASC, [Total] DESC)",
+                translated(
+                    "CREATE INDEX IF NOT EXISTS \"ix_orders_date\" ON \"dbo\".\"orders\" (\"orderdate\", \"total\" DESC);",
+                ),
+            ),
+            (
+                "CREATE UNIQUE NONCLUSTERED INDEX [UX_Code] ON [dbo].[Items] ([Code] ASC) INCLUDE ([Name], [Price]) WITH (PAD_INDEX = OFF, FILLFACTOR = 80) ON [PRIMARY]",
+                translated(
+                    "CREATE UNIQUE INDEX IF NOT EXISTS \"ux_code\" ON \"dbo\".\"items\" (\"code\") INCLUDE (\"name\", \"price\") WITH (fillfactor = 80);",
+                ),
+            ),
+            (
+                "CREATE NONCLUSTERED INDEX [IX_Active] ON [dbo].[Users] ([Email] ASC) WHERE ([IsActive]=(1) AND [Name] IS NOT NULL) ON [PRIMARY]",
+                translated(
+                    "CREATE INDEX IF NOT EXISTS \"ix_active\" ON \"dbo\".\"users\" (\"email\") WHERE (\"isactive\" = (1) AND \"name\" IS NOT NULL);",
+                ),
+            ),
+            (
+                "CREATE CLUSTERED INDEX [CX] ON [dbo].[T] ([A] ASC) WITH (FILLFACTOR = 100)",
+                translated("CREATE INDEX IF NOT EXISTS \"cx\" ON \"dbo\".\"t\" (\"a\");"),
+            ),
+        ]);
+    }
+
+    #[test]
+    fn translates_constraints() {
+        check(&[
+            (
+                "ALTER TABLE [dbo].[Orders] ADD CONSTRAINT [PK_Orders] PRIMARY KEY CLUSTERED ([Id] ASC) WITH (FILLFACTOR = 90) ON [PRIMARY]",
+                translated(
+                    "ALTER TABLE \"dbo\".\"orders\" ADD CONSTRAINT \"pk_orders\" PRIMARY KEY (\"id\") WITH (fillfactor = 90);",
+                ),
+            ),
+            (
+                "ALTER TABLE [dbo].[Orders] ADD CONSTRAINT [UQ_Number] UNIQUE NONCLUSTERED ([Number] DESC)",
+                translated(
+                    "ALTER TABLE \"dbo\".\"orders\" ADD CONSTRAINT \"uq_number\" UNIQUE (\"number\");",
+                ),
+            ),
+        ]);
+    }
+
+    #[test]
+    fn translates_foreign_keys() {
+        check(&[
+            (
+                "ALTER TABLE [dbo].[Orders] WITH CHECK ADD CONSTRAINT [FK_Orders_Customers] FOREIGN KEY([CustomerId]) REFERENCES [dbo].[Customers] ([Id]) ON DELETE CASCADE ON UPDATE NO ACTION",
+                translated(
+                    "ALTER TABLE \"dbo\".\"orders\" ADD CONSTRAINT \"fk_orders_customers\" FOREIGN KEY (\"customerid\") REFERENCES \"dbo\".\"customers\" (\"id\") ON DELETE CASCADE ON UPDATE NO ACTION NOT VALID;\nALTER TABLE \"dbo\".\"orders\" VALIDATE CONSTRAINT \"fk_orders_customers\";",
+                ),
+            ),
+            (
+                "ALTER TABLE [dbo].[Orders] WITH NOCHECK ADD CONSTRAINT [FK_Untrusted] FOREIGN KEY([A], [B]) REFERENCES [dbo].[Other] ([A], [B]) ON DELETE SET NULL",
+                translated(
+                    "ALTER TABLE \"dbo\".\"orders\" ADD CONSTRAINT \"fk_untrusted\" FOREIGN KEY (\"a\", \"b\") REFERENCES \"dbo\".\"other\" (\"a\", \"b\") ON DELETE SET NULL NOT VALID;",
+                ),
+            ),
+        ]);
+    }
+
+    #[test]
+    fn translates_check_constraints() {
+        check(&[
+            (
+                "ALTER TABLE [dbo].[Items] WITH CHECK ADD CONSTRAINT [CK_Price] CHECK (([Price]>=(0)))",
+                translated(
+                    "ALTER TABLE \"dbo\".\"items\" ADD CONSTRAINT \"ck_price\" CHECK ((\"price\" >= (0))) NOT VALID;\nALTER TABLE \"dbo\".\"items\" VALIDATE CONSTRAINT \"ck_price\";",
+                ),
+            ),
+            (
+                "ALTER TABLE [dbo].[Items] WITH CHECK ADD CONSTRAINT [CK_Name] CHECK (len([Name])>(0) AND [Code] LIKE N'A%')",
+                translated(
+                    "ALTER TABLE \"dbo\".\"items\" ADD CONSTRAINT \"ck_name\" CHECK (length(\"name\") > (0) AND \"code\" LIKE 'A%') NOT VALID;\nALTER TABLE \"dbo\".\"items\" VALIDATE CONSTRAINT \"ck_name\";",
+                ),
+            ),
+        ]);
+    }
+
+    #[test]
+    fn translates_defaults() {
+        check(&[
+            (
+                "ALTER TABLE [dbo].[Orders] ADD CONSTRAINT [DF_Created] DEFAULT (getdate()) FOR [Created]",
+                translated(
+                    "ALTER TABLE \"dbo\".\"orders\" ALTER COLUMN \"created\" SET DEFAULT (LOCALTIMESTAMP);",
+                ),
+            ),
+            (
+                "ALTER TABLE [dbo].[Orders] ADD CONSTRAINT [DF_Id] DEFAULT (newsequentialid()) FOR [Id]",
+                translated(
+                    "ALTER TABLE \"dbo\".\"orders\" ALTER COLUMN \"id\" SET DEFAULT (gen_random_uuid());",
+                ),
+            ),
+            (
+                "ALTER TABLE [dbo].[Orders] ADD CONSTRAINT [DF_Db] DEFAULT (db_name()) FOR [Db]",
+                translated(
+                    "ALTER TABLE \"dbo\".\"orders\" ALTER COLUMN \"db\" SET DEFAULT (current_database());",
+                ),
+            ),
+            (
+                "ALTER TABLE [dbo].[Orders] ADD CONSTRAINT [DF_Status] DEFAULT (N'new') FOR [Status]",
+                translated(
+                    "ALTER TABLE \"dbo\".\"orders\" ALTER COLUMN \"status\" SET DEFAULT ('new');",
+                ),
+            ),
+        ]);
+    }
+
+    #[test]
+    fn translates_views() {
+        check(&[(
+            "CREATE VIEW [dbo].[OrderTotals] WITH SCHEMABINDING AS SELECT [CustomerId], COUNT_BIG(*) AS [Orders], SUM(ISNULL([Total], 0)) AS [Total] FROM [dbo].[Orders] GROUP BY [CustomerId];",
+            translated(
+                "CREATE MATERIALIZED VIEW IF NOT EXISTS \"dbo\".\"ordertotals\" AS SELECT \"customerid\", count(*) AS \"orders\", sum(coalesce(\"total\", 0)) AS \"total\" FROM \"dbo\".\"orders\" GROUP BY \"customerid\";",
+            ),
+        )]);
+    }
+
+    #[test]
+    fn translates_statistics() {
+        check(&[(
+            "CREATE STATISTICS [ST_Orders] ON [dbo].[Orders]([CustomerId], [OrderDate])",
+            translated(
+                "CREATE STATISTICS IF NOT EXISTS \"dbo\".\"st_orders\" (dependencies, ndistinct) ON \"customerid\", \"orderdate\" FROM \"dbo\".\"orders\";",
+            ),
+        )]);
+    }
+
+    #[test]
+    fn reports_unsupported_statements() {
+        check(&[
+            (
+                "CREATE UNIQUE NONCLUSTERED INDEX [UX_Dup] ON [dbo].[T] ([A] ASC) WITH (IGNORE_DUP_KEY = ON)",
+                unsupported("<UX_Dup> uses IGNORE_DUP_KEY, PostgreSQL has no equivalent"),
+            ),
+            (
+                "CREATE NONCLUSTERED INDEX [IX_F] ON [dbo].[T] ([A] ASC) WHERE ([B]=dbo.fn([C]))",
+                unsupported("filter of index <IX_F>: function FN has no PostgreSQL translation"),
+            ),
+            (
+                "ALTER TABLE [dbo].[T] WITH CHECK ADD CONSTRAINT [CK_Like] CHECK ([A] LIKE '[0-9]%')",
+                unsupported(
+                    "check constraint <CK_Like>: LIKE with a character class has no PostgreSQL translation",
+                ),
+            ),
+            (
+                "ALTER TABLE [dbo].[T] WITH CHECK ADD CONSTRAINT [CK_Off] CHECK ([A]>(0)); ALTER TABLE [dbo].[T] NOCHECK CONSTRAINT [CK_Off];",
+                unsupported(
+                    "constraint <CK_Off> is disabled, PostgreSQL cannot disable a constraint",
+                ),
+            ),
+            (
+                "ALTER TABLE [dbo].[T] ADD CONSTRAINT [DF_Db] DEFAULT (db_name(5)) FOR [Db]",
+                unsupported(
+                    "default constraint <DF_Db>: function DB_NAME with arguments has no PostgreSQL translation",
+                ),
+            ),
+            (
+                "ALTER TABLE [dbo].[T] ADD CONSTRAINT [DF_X] DEFAULT (host_name()) FOR [X]",
+                unsupported(
+                    "default constraint <DF_X>: function HOST_NAME has no PostgreSQL translation",
+                ),
+            ),
+            (
+                "CREATE VIEW [dbo].[V] WITH SCHEMABINDING AS SELECT TOP 10 [A] FROM [dbo].[T]",
+                unsupported("view <V>: TOP has no PostgreSQL translation"),
+            ),
+            (
+                "CREATE VIEW [dbo].[V] WITH SCHEMABINDING AS SELECT Total = [A] + [B] FROM [dbo].[T]",
+                unsupported(
+                    "view <V>: column alias <Total> = expression has no PostgreSQL translation",
+                ),
+            ),
+            (
+                "CREATE VIEW [dbo].[V] WITH ENCRYPTION, SCHEMABINDING",
+                unsupported("view <V> has no definition, it may be encrypted"),
+            ),
+            (
+                "CREATE STATISTICS [ST_A] ON [dbo].[T]([A])",
+                unsupported(
+                    "statistics <ST_A> cover a single column, PostgreSQL keeps those itself",
+                ),
+            ),
+            (
+                "CREATE STATISTICS [ST_F] ON [dbo].[T]([A], [B]) WHERE [A] > 0",
+                unsupported("filtered statistics <ST_F> have no PostgreSQL equivalent"),
+            ),
+        ]);
+    }
+
+    #[test]
+    fn leaves_postgres_statements_unchanged() {
+        check(&[
+            ("CREATE INDEX ix ON t (a);", Translation::Unchanged),
+            (
+                "ALTER TABLE t ADD CONSTRAINT pk PRIMARY KEY (id);",
+                Translation::Unchanged,
+            ),
+            (
+                "ALTER TABLE t ADD CONSTRAINT fk FOREIGN KEY (a) REFERENCES u (id);",
+                Translation::Unchanged,
+            ),
+            (
+                "CREATE STATISTICS s (ndistinct) ON a, b FROM t;",
+                Translation::Unchanged,
+            ),
+            ("CREATE VIEW v AS SELECT 1;", Translation::Unchanged),
+            ("ANALYZE t;", Translation::Unchanged),
+        ]);
+    }
+
+    #[test]
+    fn map_name_folds_and_truncates() {
+        assert_eq!(map_name("OrderDate"), "orderdate");
+        assert_eq!(map_name(&"a".repeat(70)).len(), 63);
+        // never cut inside a character
+        assert_eq!(map_name(&"ä".repeat(40)), "ä".repeat(31));
+    }
+}
@@ -6,4 +6,4 @@ pub const VERSION_ALIAS: &str = "Denver";
 pub const COPYRIGHT: &str = "Oleg Potapenko";
 pub const COPYRIGHT_YEARS: &str = "2026";
 pub const LICENSE: &str = "MIT";
-pub const LINK: &str = "https://github.com/PotapenkoOleg/pg_index_import";
\ No newline at end of file
+pub const LINK: &str = "https://github.com/PotapenkoOleg/pg_index_import";